@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use strudel_fuzz::Op;
+
+fuzz_target!(|ops: Vec<Op>| {
+    strudel_fuzz::run(&ops);
+});
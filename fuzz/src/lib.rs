@@ -0,0 +1,547 @@
+//! A state machine model of the `st_hash` C API exported by `strudel-ffi`.
+//!
+//! [`run`] drives a table through the exported `extern "C"` functions the same
+//! way MRI does and mirrors every operation on a [`Model`]: a plain insertion
+//! ordered list of key-value pairs. Callbacks passed to `st_update` and
+//! `st_foreach` mutate the table while they run, which exercises the raw
+//! pointer juggling these functions do to avoid aliasing the table.
+//!
+//! After each operation, [`run`] checks that the table and the model agree on
+//! `num_entries`, on the value of every key, and on insertion order.
+
+#![warn(clippy::all)]
+#![warn(clippy::pedantic)]
+#![allow(clippy::missing_panics_doc)]
+#![allow(non_camel_case_types)]
+
+// Link the `st_*` symbols exported by `strudel-ffi`.
+extern crate strudel_st;
+
+use std::os::raw::{c_int, c_uchar, c_uint};
+use std::ptr;
+
+use arbitrary::Arbitrary;
+
+type st_data_t = usize;
+type st_index_t = usize;
+
+type st_update_callback_func =
+    unsafe extern "C" fn(*mut st_data_t, *mut st_data_t, st_data_t, c_int) -> c_int;
+type st_foreach_callback_func =
+    unsafe extern "C" fn(st_data_t, st_data_t, st_data_t, c_int) -> c_int;
+
+#[repr(C)]
+struct st_hash_type {
+    compare: unsafe extern "C" fn(st_data_t, st_data_t) -> c_int,
+    hash: unsafe extern "C" fn(st_data_t) -> st_index_t,
+}
+
+// The leading fields of `st_table` which MRI reads directly.
+#[repr(C)]
+struct st_table {
+    entry_power: c_uchar,
+    bin_power: c_uchar,
+    size_ind: c_uchar,
+    rebuilds_num: c_uint,
+    type_: *const st_hash_type,
+    num_entries: st_index_t,
+}
+
+const ST_CONTINUE: c_int = 0;
+const ST_STOP: c_int = 1;
+const ST_DELETE: c_int = 2;
+
+extern "C" {
+    fn st_init_table_with_size(hash_type: *const st_hash_type, size: st_index_t) -> *mut st_table;
+    fn st_free_table(table: *mut st_table);
+    fn st_insert(table: *mut st_table, key: st_data_t, value: st_data_t) -> c_int;
+    fn st_delete(table: *mut st_table, key: *mut st_data_t, value: *mut st_data_t) -> c_int;
+    fn st_lookup(table: *mut st_table, key: st_data_t, value: *mut st_data_t) -> c_int;
+    fn st_update(
+        table: *mut st_table,
+        key: st_data_t,
+        func: st_update_callback_func,
+        arg: st_data_t,
+    ) -> c_int;
+    fn st_foreach(table: *mut st_table, func: st_foreach_callback_func, arg: st_data_t) -> c_int;
+    fn st_rehash(table: *mut st_table);
+}
+
+unsafe extern "C" fn numcmp(x: st_data_t, y: st_data_t) -> c_int {
+    c_int::from(x != y)
+}
+
+// Hash into a handful of buckets so lookups must resolve collisions.
+unsafe extern "C" fn numhash(n: st_data_t) -> st_index_t {
+    n % 7
+}
+
+static NUM_HASH_TYPE: st_hash_type = st_hash_type {
+    compare: numcmp,
+    hash: numhash,
+};
+
+/// A key in the fuzzed table.
+///
+/// Keys are drawn from a small domain so operations frequently hit existing
+/// entries.
+pub type Key = u8;
+
+/// A value in the fuzzed table.
+pub type Value = u16;
+
+/// An operation on the table under test.
+#[derive(Arbitrary, Debug, Clone)]
+pub enum Op {
+    /// Free the table with `st_free_table` and create a new one with
+    /// `st_init_table_with_size`.
+    Reset { capacity: u8 },
+    /// Call `st_insert`.
+    Insert { key: Key, value: Value },
+    /// Call `st_delete`.
+    Delete { key: Key },
+    /// Call `st_lookup`.
+    Lookup { key: Key },
+    /// Call `st_rehash`.
+    Rehash,
+    /// Call `st_update` with a callback which applies `mutations` to the table
+    /// and then completes the update with `action`.
+    Update {
+        key: Key,
+        mutations: Vec<Mutation>,
+        action: UpdateAction,
+    },
+    /// Call `st_foreach` with a callback which runs the next step for each
+    /// visited entry. Entries visited after the steps run out continue the
+    /// traversal.
+    Foreach { steps: Vec<Step> },
+}
+
+/// A mutation of the table made from within a callback.
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum Mutation {
+    /// Call `st_insert`.
+    Insert { key: Key, value: Value },
+    /// Call `st_delete`.
+    Delete { key: Key },
+    /// Call `st_rehash`.
+    Rehash,
+}
+
+/// How an `st_update` callback completes.
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum UpdateAction {
+    /// Return `ST_CONTINUE` without changing the value.
+    Keep,
+    /// Write a new value and return `ST_CONTINUE`.
+    Set(Value),
+    /// Return `ST_DELETE`.
+    Delete,
+    /// Return `ST_STOP`, which neither stores nor deletes the entry.
+    Stop,
+}
+
+/// What an `st_foreach` callback does when it visits an entry.
+#[derive(Arbitrary, Debug, Clone)]
+pub struct Step {
+    /// Mutations applied to the table before returning.
+    pub mutations: Vec<Mutation>,
+    /// The value returned from the callback.
+    pub retval: Retval,
+}
+
+/// The return value of an `st_foreach` callback.
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum Retval {
+    /// Return `ST_CONTINUE`.
+    Continue,
+    /// Return `ST_STOP`.
+    Stop,
+    /// Return `ST_DELETE`.
+    Delete,
+}
+
+/// The expected contents of the table in insertion order.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Model {
+    entries: Vec<(st_data_t, st_data_t)>,
+}
+
+impl Model {
+    fn get(&self, key: st_data_t) -> Option<st_data_t> {
+        self.entries
+            .iter()
+            .find(|&&(k, _)| k == key)
+            .map(|&(_, value)| value)
+    }
+
+    // Update the value in place if `key` is present, otherwise append the pair.
+    fn insert(&mut self, key: st_data_t, value: st_data_t) -> bool {
+        if let Some(entry) = self.entries.iter_mut().find(|(k, _)| *k == key) {
+            entry.1 = value;
+            true
+        } else {
+            self.entries.push((key, value));
+            false
+        }
+    }
+
+    fn remove(&mut self, key: st_data_t) -> Option<st_data_t> {
+        let index = self.entries.iter().position(|&(k, _)| k == key)?;
+        Some(self.entries.remove(index).1)
+    }
+}
+
+// State shared with callbacks through the `arg` parameter.
+struct Context<'a> {
+    table: *mut st_table,
+    model: &'a mut Model,
+    mutations: &'a [Mutation],
+    steps: &'a [Step],
+    visits: usize,
+    visited: Vec<st_data_t>,
+    may_skip: bool,
+    action: UpdateAction,
+    existing: bool,
+}
+
+impl<'a> Context<'a> {
+    fn new(table: *mut st_table, model: &'a mut Model) -> Self {
+        Self {
+            table,
+            model,
+            mutations: &[],
+            steps: &[],
+            visits: 0,
+            visited: Vec::new(),
+            may_skip: false,
+            action: UpdateAction::Keep,
+            existing: false,
+        }
+    }
+
+    fn as_arg(&mut self) -> st_data_t {
+        ptr::addr_of_mut!(*self) as st_data_t
+    }
+
+    unsafe fn mutate(&mut self, mutations: &[Mutation]) {
+        for &mutation in mutations {
+            apply(self.table, self.model, mutation);
+        }
+    }
+}
+
+unsafe fn apply(table: *mut st_table, model: &mut Model, mutation: Mutation) {
+    match mutation {
+        Mutation::Insert { key, value } => {
+            let (key, value) = (st_data_t::from(key), st_data_t::from(value));
+            let existed = st_insert(table, key, value);
+            assert_eq!(existed != 0, model.insert(key, value), "st_insert({key})");
+        }
+        Mutation::Delete { key } => {
+            let mut key = st_data_t::from(key);
+            let mut value = st_data_t::MAX;
+            let found = st_delete(table, ptr::addr_of_mut!(key), ptr::addr_of_mut!(value));
+            match model.remove(key) {
+                Some(expected) => {
+                    assert_eq!(found, 1, "st_delete({key})");
+                    assert_eq!(value, expected, "st_delete({key})");
+                }
+                None => assert_eq!(found, 0, "st_delete({key})"),
+            }
+        }
+        // Keys are never mutated, so rehashing must not change the table.
+        Mutation::Rehash => st_rehash(table),
+    }
+}
+
+unsafe extern "C" fn update_callback(
+    key: *mut st_data_t,
+    value: *mut st_data_t,
+    arg: st_data_t,
+    existing: c_int,
+) -> c_int {
+    let cx = &mut *(arg as *mut Context<'_>);
+    assert_eq!(existing != 0, cx.existing, "st_update existing flag");
+    let expected = cx.model.get(*key).unwrap_or(0);
+    assert_eq!(*value, expected, "st_update value for {}", *key);
+
+    let mutations = cx.mutations;
+    cx.mutate(mutations);
+    match cx.action {
+        UpdateAction::Keep => ST_CONTINUE,
+        UpdateAction::Set(new) => {
+            *value = st_data_t::from(new);
+            ST_CONTINUE
+        }
+        UpdateAction::Delete => ST_DELETE,
+        UpdateAction::Stop => ST_STOP,
+    }
+}
+
+unsafe extern "C" fn foreach_callback(
+    key: st_data_t,
+    value: st_data_t,
+    arg: st_data_t,
+    error: c_int,
+) -> c_int {
+    let cx = &mut *(arg as *mut Context<'_>);
+    assert_eq!(error, 0, "st_foreach error flag");
+    assert_eq!(cx.model.get(key), Some(value), "st_foreach visited {key}");
+
+    let step = cx.steps.get(cx.visits);
+    cx.visits += 1;
+    cx.visited.push(key);
+    let Some(step) = step else {
+        return ST_CONTINUE;
+    };
+    let rebuilds_num = ptr::addr_of!((*cx.table).rebuilds_num).read();
+    cx.mutate(&step.mutations);
+    // After the table renumbers its insertion ranks, traversal resumes after
+    // the current position of the visited key. If the callback deleted that
+    // key, traversal either ends or resumes after the reinserted key, skipping
+    // entries appended before it.
+    let deleted_visited = step.mutations.iter().any(
+        |&mutation| matches!(mutation, Mutation::Delete { key: k } if st_data_t::from(k) == key),
+    );
+    if deleted_visited && rebuilds_num != ptr::addr_of!((*cx.table).rebuilds_num).read() {
+        cx.may_skip = true;
+    }
+    match step.retval {
+        Retval::Continue => ST_CONTINUE,
+        Retval::Stop => {
+            cx.may_skip = true;
+            ST_STOP
+        }
+        Retval::Delete => {
+            // `st_foreach` deletes the visited key, even if the callback
+            // deleted and reinserted it.
+            let _ = cx.model.remove(key);
+            ST_DELETE
+        }
+    }
+}
+
+unsafe extern "C" fn collect_callback(
+    key: st_data_t,
+    value: st_data_t,
+    arg: st_data_t,
+    _error: c_int,
+) -> c_int {
+    let entries = &mut *(arg as *mut Vec<(st_data_t, st_data_t)>);
+    entries.push((key, value));
+    ST_CONTINUE
+}
+
+unsafe fn update(
+    table: *mut st_table,
+    model: &mut Model,
+    key: Key,
+    mutations: &[Mutation],
+    action: UpdateAction,
+) {
+    let key = st_data_t::from(key);
+    let before = model.get(key);
+    let mut cx = Context::new(table, model);
+    cx.mutations = mutations;
+    cx.action = action;
+    cx.existing = before.is_some();
+    let arg = cx.as_arg();
+    let existing = st_update(table, key, update_callback, arg);
+    assert_eq!(existing != 0, before.is_some(), "st_update({key})");
+
+    // `st_update` decides what to store based on the entry as it was before
+    // the callback ran, regardless of how the callback mutated the table.
+    match (action, before) {
+        (UpdateAction::Keep, Some(_)) | (UpdateAction::Delete, None) | (UpdateAction::Stop, _) => {}
+        (UpdateAction::Keep, None) => {
+            let _ = model.insert(key, 0);
+        }
+        (UpdateAction::Set(new), Some(old)) if st_data_t::from(new) == old => {}
+        (UpdateAction::Set(new), _) => {
+            let _ = model.insert(key, st_data_t::from(new));
+        }
+        (UpdateAction::Delete, Some(_)) => {
+            let _ = model.remove(key);
+        }
+    }
+}
+
+unsafe fn foreach(table: *mut st_table, model: &mut Model, steps: &[Step]) {
+    let mut cx = Context::new(table, model);
+    cx.steps = steps;
+    let arg = cx.as_arg();
+    assert_eq!(st_foreach(table, foreach_callback, arg), 0, "st_foreach");
+
+    // Otherwise callbacks only append entries or update them in place, so
+    // every entry left in the table is visited.
+    if !cx.may_skip {
+        for (key, _) in &cx.model.entries {
+            assert!(cx.visited.contains(key), "st_foreach skipped {}", key);
+        }
+    }
+}
+
+// Check that `table` holds exactly the entries of `model` in insertion order.
+unsafe fn check(table: *mut st_table, model: &Model) {
+    let num_entries = ptr::addr_of!((*table).num_entries).read();
+    assert_eq!(num_entries, model.entries.len(), "num_entries");
+
+    for &(key, expected) in &model.entries {
+        let mut value = st_data_t::MAX;
+        assert_eq!(
+            st_lookup(table, key, ptr::addr_of_mut!(value)),
+            1,
+            "st_lookup({key})"
+        );
+        assert_eq!(value, expected, "st_lookup({key})");
+    }
+
+    let mut entries = Vec::<(st_data_t, st_data_t)>::with_capacity(num_entries);
+    let arg = ptr::addr_of_mut!(entries) as st_data_t;
+    assert_eq!(st_foreach(table, collect_callback, arg), 0, "st_foreach");
+    assert_eq!(entries, model.entries, "insertion order");
+}
+
+/// Run `ops` against a fresh table and a [`Model`], checking that they agree
+/// after every operation.
+///
+/// # Panics
+///
+/// Panics if the table and model disagree.
+pub fn run(ops: &[Op]) {
+    let mut model = Model::default();
+    unsafe {
+        let mut table = st_init_table_with_size(ptr::addr_of!(NUM_HASH_TYPE), 0);
+        for op in ops {
+            match *op {
+                Op::Reset { capacity } => {
+                    st_free_table(table);
+                    table = st_init_table_with_size(
+                        ptr::addr_of!(NUM_HASH_TYPE),
+                        st_index_t::from(capacity),
+                    );
+                    model = Model::default();
+                }
+                Op::Insert { key, value } => {
+                    apply(table, &mut model, Mutation::Insert { key, value });
+                }
+                Op::Delete { key } => apply(table, &mut model, Mutation::Delete { key }),
+                Op::Lookup { key } => {
+                    let key = st_data_t::from(key);
+                    let mut value = st_data_t::MAX;
+                    let found = st_lookup(table, key, ptr::addr_of_mut!(value));
+                    if let Some(expected) = model.get(key) {
+                        assert_eq!(found, 1, "st_lookup({key})");
+                        assert_eq!(value, expected, "st_lookup({key})");
+                    } else {
+                        assert_eq!(found, 0, "st_lookup({key})");
+                        assert_eq!(value, st_data_t::MAX, "st_lookup({key}) wrote a value");
+                    }
+                }
+                Op::Rehash => apply(table, &mut model, Mutation::Rehash),
+                Op::Update {
+                    key,
+                    ref mutations,
+                    action,
+                } => update(table, &mut model, key, mutations, action),
+                Op::Foreach { ref steps } => foreach(table, &mut model, steps),
+            }
+            check(table, &model);
+        }
+        st_free_table(table);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    use super::{run, Mutation, Op, Retval, Step, UpdateAction};
+
+    #[test]
+    fn callbacks_mutate_the_table() {
+        let fill = (0..20).map(|key| Op::Insert {
+            key,
+            value: u16::from(key) * 10,
+        });
+        let mut ops = fill.collect::<Vec<_>>();
+        ops.extend(vec![
+            Op::Update {
+                key: 3,
+                mutations: vec![
+                    Mutation::Delete { key: 3 },
+                    Mutation::Insert { key: 50, value: 1 },
+                ],
+                action: UpdateAction::Set(33),
+            },
+            Op::Update {
+                key: 60,
+                mutations: vec![Mutation::Insert { key: 60, value: 6 }],
+                action: UpdateAction::Delete,
+            },
+            Op::Update {
+                key: 4,
+                mutations: vec![Mutation::Delete { key: 4 }],
+                action: UpdateAction::Keep,
+            },
+            Op::Foreach {
+                steps: vec![
+                    Step {
+                        mutations: vec![Mutation::Delete { key: 1 }, Mutation::Delete { key: 2 }],
+                        retval: Retval::Delete,
+                    },
+                    // Rehash with dead slots ahead of the traversal.
+                    Step {
+                        mutations: vec![Mutation::Rehash],
+                        retval: Retval::Continue,
+                    },
+                    Step {
+                        mutations: (100..140)
+                            .map(|key| Mutation::Insert { key, value: 7 })
+                            .collect(),
+                        retval: Retval::Continue,
+                    },
+                    Step {
+                        mutations: vec![
+                            Mutation::Delete { key: 19 },
+                            Mutation::Insert { key: 19, value: 9 },
+                        ],
+                        retval: Retval::Delete,
+                    },
+                    Step {
+                        mutations: vec![],
+                        retval: Retval::Stop,
+                    },
+                ],
+            },
+            Op::Lookup { key: 19 },
+            Op::Reset { capacity: 4 },
+            Op::Insert { key: 1, value: 1 },
+        ]);
+        run(&ops);
+    }
+
+    #[test]
+    fn random_op_sequences() {
+        // Keep the number of sequences small enough to finish under Miri.
+        let sequences = if cfg!(miri) { 4 } else { 500 };
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut bytes = vec![0_u8; 4096];
+        for _ in 0..sequences {
+            for byte in &mut bytes {
+                // xorshift64
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                *byte = state.to_le_bytes()[0];
+            }
+            let mut u = Unstructured::new(&bytes);
+            let ops = (0..64)
+                .map(|_| Op::arbitrary(&mut u))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            run(&ops);
+        }
+    }
+}
@@ -45,6 +45,9 @@ typedef struct st_table st_table;
 
 typedef st_data_t st_index_t;
 
+/* The type of hashes.  */
+typedef st_index_t st_hash_t;
+
 typedef int st_compare_func(st_data_t, st_data_t);
 typedef st_index_t st_hash_func(st_data_t);
 
@@ -84,10 +87,12 @@ struct st_table {
 
 #define st_is_member(table, key) st_lookup((table), (key), (st_data_t *)0)
 
-enum st_retval { ST_CONTINUE, ST_STOP, ST_DELETE, ST_CHECK };
+enum st_retval { ST_CONTINUE, ST_STOP, ST_DELETE, ST_CHECK, ST_REPLACE };
 
 st_table *st_init_table(const struct st_hash_type *);
 st_table *st_init_table_with_size(const struct st_hash_type *, st_index_t);
+st_table *st_init_table_with_pow2_size(const struct st_hash_type *, st_index_t);
+st_table *st_init_existing_table(st_table *tab, const struct st_hash_type *type);
 st_table *st_init_numtable(void);
 st_table *st_init_numtable_with_size(st_index_t);
 st_table *st_init_strtable(void);
@@ -101,7 +106,11 @@ int st_delete_safe(st_table *, st_data_t *, st_data_t *, st_data_t);
 int st_shift(st_table *, st_data_t *, st_data_t *);
 int st_insert(st_table *, st_data_t, st_data_t);
 int st_insert2(st_table *, st_data_t, st_data_t, st_data_t (*)(st_data_t));
+int st_insert_with_hash(st_table *tab, st_data_t key, st_data_t value, st_hash_t hash);
+void st_insert_many(st_table *tab, const st_data_t *keys, const st_data_t *values, st_index_t n);
+int st_replace(st_table *tab, st_data_t key, st_data_t value, st_data_t *old_value);
 int st_lookup(st_table *, st_data_t, st_data_t *);
+int st_lookup_with_hash(st_table *tab, st_data_t key, st_hash_t hash, st_data_t *value);
 int st_get_key(st_table *, st_data_t, st_data_t *);
 typedef int st_update_callback_func(st_data_t *key, st_data_t *value, st_data_t arg, int existing);
 /* *key may be altered, but must equal to the old key, i.e., the
@@ -110,14 +119,25 @@ typedef int st_update_callback_func(st_data_t *key, st_data_t *value, st_data_t
 int st_update(st_table *table, st_data_t key, st_update_callback_func *func, st_data_t arg);
 int st_foreach(st_table *, int (*)(ANYARGS), st_data_t);
 int st_foreach_check(st_table *, int (*)(ANYARGS), st_data_t, st_data_t);
+typedef int st_foreach_check_callback_func(st_data_t, st_data_t, st_data_t, int);
+int st_foreach_with_replace(st_table *tab, st_foreach_check_callback_func *func,
+                            st_update_callback_func *replace, st_data_t arg);
+typedef int st_foreach_hash_callback_func(st_data_t, st_data_t, st_hash_t, st_data_t, int);
+int st_foreach_with_hash(st_table *tab, st_foreach_hash_callback_func *func, st_data_t arg);
 st_index_t st_keys(st_table *table, st_data_t *keys, st_index_t size);
 st_index_t st_keys_check(st_table *table, st_data_t *keys, st_index_t size, st_data_t never);
 st_index_t st_values(st_table *table, st_data_t *values, st_index_t size);
 st_index_t st_values_check(st_table *table, st_data_t *values, st_index_t size, st_data_t never);
+st_data_t *st_bulk_keys(st_table *table, st_index_t *len);
+st_data_t *st_bulk_values(st_table *table, st_index_t *len);
+void st_free_array(st_data_t *array);
 void st_add_direct(st_table *, st_data_t, st_data_t);
+void st_add_direct_with_hash(st_table *tab, st_data_t key, st_data_t value, st_hash_t hash);
 void st_free_table(st_table *);
 void st_cleanup_safe(st_table *, st_data_t);
+void st_rehash(st_table *);
 void st_clear(st_table *);
+void st_clear_and_shrink(st_table *);
 st_table *st_copy(st_table *);
 int st_numcmp(st_data_t, st_data_t);
 st_index_t st_numhash(st_data_t);
@@ -126,6 +146,7 @@ int st_locale_insensitive_strncasecmp(const char *s1, const char *s2, size_t n);
 #define st_strcasecmp st_locale_insensitive_strcasecmp
 #define st_strncasecmp st_locale_insensitive_strncasecmp
 size_t st_memsize(const st_table *);
+st_index_t st_compact(st_table *);
 st_index_t st_hash(const void *ptr, size_t len, st_index_t h);
 st_index_t st_hash_uint32(st_index_t h, uint32_t i);
 st_index_t st_hash_uint(st_index_t h, st_index_t i);
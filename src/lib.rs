@@ -119,6 +119,8 @@ rewritten by Vladimir Makarov <vmakarov@redhat.com>.  */
 // This approach is borrowed from tokio.
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![cfg_attr(docsrs, feature(doc_alias))]
+#![cfg_attr(feature = "nightly", feature(allocator_api, try_reserve_kind))]
+#![no_std]
 
 //! Insertion-ordered hash table suitable for embedding via FFI.
 //!
@@ -127,31 +129,61 @@ rewritten by Vladimir Makarov <vmakarov@redhat.com>.  */
 //!
 //! `StHashMap` is designed to implement the `st_hash` C API and be FFI-friendly.
 //!
-//! `StHashMap` is built on top of the high performance [`HashMap`] and [`Vec`]
-//! in Rust `std`.
+//! `StHashMap` is built on top of the high performance [`hashbrown`] hash table,
+//! which also backs the [`HashMap`] in Rust `std`, and a [`Vec`].
 //!
 //! `StHashMap`, and `StHashSet` which builds on top of it, support in-place updates
-//! of hash keys. No mutable iterators are provided.
+//! of hash keys and mutable iteration over values.
 //!
 //! The optional `api` and `capi` modules in `strudel` build on top of `StHashMap`
 //! to implement a compatible C API to `st_hash`. This API includes support for
 //! iterating over a mutable map and in-place updates of `(key, value)` pairs. These
 //! features distinguish it from the [`HashMap`] in Rust `std`.
 //!
+//! # Crate features
+//!
+//! - **std** - Enabled by default. Use `RandomState` from `std` as the default
+//!   hasher. Disabling this feature makes `strudel` `no_std` (it still requires
+//!   [`alloc`]); `StHashMap` then uses the default hasher of [`hashbrown`]. The
+//!   entry API, `try_reserve`, and the [`Hash`] implementations require this
+//!   feature.
+//! - **serde** - Implement `Serialize` and `Deserialize` for `StHashMap` and
+//!   `StHashSet`. Entries are serialized in insertion order and deserialized
+//!   collections preserve the serialized order.
+//! - **sync** - Provide `SyncStHashMap`, a `Send` and `Sync` handle to an
+//!   `StHashMap` guarded by a `Mutex`. Requires **std**.
+//! - **rayon** - Implement [`rayon`] parallel iterators over the entries of
+//!   `StHashMap`.
+//! - **nightly** - Add constructors like `StHashMap::new_in` which allocate
+//!   the hash table and insertion list of a map through a custom allocator.
+//!   Uses the unstable `allocator_api` and requires a nightly compiler.
+//!
 //! [ruby]: https://github.com/ruby/ruby
 //! [st.c]: https://github.com/ruby/ruby/blob/v2_6_3/st.c
 //! [hash]: https://ruby-doc.org/core-2.6.3/Hash.html
 //! [`hashmap`]: https://doc.rust-lang.org/std/collections/struct.HashMap.html
+//! [`alloc`]: https://doc.rust-lang.org/alloc/
+//! [`hashbrown`]: https://docs.rs/hashbrown
+//! [`Vec`]: alloc::vec::Vec
+//! [`rayon`]: https://docs.rs/rayon
+//! [`Hash`]: core::hash::Hash
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 mod st;
 
 pub use st::map::StHashMap;
 pub use st::set::StHashSet;
+#[cfg(feature = "sync")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sync")))]
+pub use st::sync::SyncStHashMap;
 
 pub mod st_hash_map {
     //! An insertion-ordered hash map implemented with [`HashMap`] and [`Vec`].
     //!
-    //! [`HashMap`]: std::collections::HashMap
+    //! [`HashMap`]: hashbrown::HashMap
     //! [`Vec`]: std::vec::Vec
 
     pub use super::st::map::*;
@@ -0,0 +1,568 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::mem;
+
+use allocator_api2::alloc::{Allocator, Global};
+use allocator_api2::vec::Vec;
+use hashbrown::hash_map::{OccupiedEntry as HashOccupiedEntry, VacantEntry as HashVacantEntry};
+
+use crate::st::map::{from_rank, to_rank, DefaultHashBuilder, InsertionEntry, Rank};
+
+/// A view into a single entry in a map, which may either be vacant or
+/// occupied.
+///
+/// This `enum` is constructed from the [`entry`] method on [`StHashMap`].
+///
+/// [`entry`]: crate::StHashMap::entry
+/// [`StHashMap`]: crate::StHashMap
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub enum Entry<'a, K, V, S = DefaultHashBuilder, A: Allocator = Global> {
+    /// An occupied entry.
+    Occupied(OccupiedEntry<'a, K, V, S, A>),
+    /// A vacant entry.
+    Vacant(VacantEntry<'a, K, V, S, A>),
+}
+
+/// A view into an occupied entry in a [`StHashMap`]. It is part of the
+/// [`Entry`] enum.
+///
+/// [`StHashMap`]: crate::StHashMap
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct OccupiedEntry<'a, K, V, S = DefaultHashBuilder, A: Allocator = Global> {
+    pub(crate) base: HashOccupiedEntry<'a, K, Rank, S, A>,
+    pub(crate) ordered: &'a mut Vec<InsertionEntry<K, V>, A>,
+}
+
+/// A view into a vacant entry in a [`StHashMap`]. It is part of the [`Entry`]
+/// enum.
+///
+/// [`StHashMap`]: crate::StHashMap
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct VacantEntry<'a, K, V, S = DefaultHashBuilder, A: Allocator = Global> {
+    pub(crate) base: HashVacantEntry<'a, K, Rank, S, A>,
+    pub(crate) ordered: &'a mut Vec<InsertionEntry<K, V>, A>,
+}
+
+/// The error returned by [`try_insert`] when the key already exists.
+///
+/// Contains the occupied entry, and the value that was not inserted.
+///
+/// [`try_insert`]: crate::StHashMap::try_insert
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct OccupiedError<'a, K, V, S = DefaultHashBuilder, A: Allocator = Global> {
+    /// The entry in the map that was already occupied.
+    pub entry: OccupiedEntry<'a, K, V, S, A>,
+    /// The value which was not inserted, because the entry was already
+    /// occupied.
+    pub value: V,
+}
+
+impl<K, V, S, A: Allocator> fmt::Display for OccupiedError<'_, K, V, S, A>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to insert {:?}, key {:?} already exists with value {:?}",
+            self.value,
+            self.entry.key(),
+            self.entry.get(),
+        )
+    }
+}
+
+impl<K, V, S, A: Allocator> std::error::Error for OccupiedError<'_, K, V, S, A>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+    S: fmt::Debug,
+    A: fmt::Debug,
+{
+}
+
+impl<K, V, S, A: Allocator> Entry<'_, K, V, S, A> {
+    /// Returns a reference to this entry's key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map: StHashMap<&str, u32> = StHashMap::new();
+    /// assert_eq!(map.entry("poneyland").key(), &"poneyland");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn key(&self) -> &K {
+        match self {
+            Self::Occupied(entry) => entry.key(),
+            Self::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any
+    /// potential inserts into the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map: StHashMap<&str, u32> = StHashMap::new();
+    ///
+    /// map.entry("poneyland").and_modify(|e| *e += 1).or_insert(42);
+    /// assert_eq!(map[&"poneyland"], 42);
+    ///
+    /// map.entry("poneyland").and_modify(|e| *e += 1).or_insert(42);
+    /// assert_eq!(map[&"poneyland"], 43);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        match self {
+            Self::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Self::Occupied(entry)
+            }
+            Self::Vacant(entry) => Self::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, K, V, S, A: Allocator> Entry<'a, K, V, S, A>
+where
+    K: Hash + Clone,
+    S: BuildHasher,
+{
+    /// Ensures a value is in the entry by inserting the default if empty, and
+    /// returns a mutable reference to the value in the entry.
+    ///
+    /// A newly inserted entry is ordered after all existing entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the entry is vacant and the map already holds `u32::MAX + 1`
+    /// entries. See [`StHashMap`](crate::StHashMap) for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map: StHashMap<&str, u32> = StHashMap::new();
+    ///
+    /// map.entry("poneyland").or_insert(3);
+    /// assert_eq!(map[&"poneyland"], 3);
+    ///
+    /// *map.entry("poneyland").or_insert(10) *= 2;
+    /// assert_eq!(map[&"poneyland"], 6);
+    /// ```
+    #[inline]
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Self::Occupied(entry) => entry.into_mut(),
+            Self::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default
+    /// function if empty, and returns a mutable reference to the value in the
+    /// entry.
+    ///
+    /// A newly inserted entry is ordered after all existing entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the entry is vacant and the map already holds `u32::MAX + 1`
+    /// entries. See [`StHashMap`](crate::StHashMap) for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map: StHashMap<&str, String> = StHashMap::new();
+    /// let s = "hoho".to_string();
+    ///
+    /// map.entry("poneyland").or_insert_with(|| s);
+    ///
+    /// assert_eq!(map[&"poneyland"], "hoho".to_string());
+    /// ```
+    #[inline]
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Self::Occupied(entry) => entry.into_mut(),
+            Self::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting, if empty, the result of
+    /// the default function. This method allows for generating key-derived
+    /// values for insertion by providing the default function a reference to
+    /// the key that was moved during the `.entry(key)` method call.
+    ///
+    /// A newly inserted entry is ordered after all existing entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the entry is vacant and the map already holds `u32::MAX + 1`
+    /// entries. See [`StHashMap`](crate::StHashMap) for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map: StHashMap<&str, usize> = StHashMap::new();
+    ///
+    /// map.entry("poneyland").or_insert_with_key(|key| key.chars().count());
+    ///
+    /// assert_eq!(map[&"poneyland"], 9);
+    /// ```
+    #[inline]
+    pub fn or_insert_with_key<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self {
+            Self::Occupied(entry) => entry.into_mut(),
+            Self::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+}
+
+impl<'a, K, V, S, A: Allocator> Entry<'a, K, V, S, A>
+where
+    K: Hash + Clone,
+    V: Default,
+    S: BuildHasher,
+{
+    /// Ensures a value is in the entry by inserting the default value if empty,
+    /// and returns a mutable reference to the value in the entry.
+    ///
+    /// A newly inserted entry is ordered after all existing entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the entry is vacant and the map already holds `u32::MAX + 1`
+    /// entries. See [`StHashMap`](crate::StHashMap) for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut counts: StHashMap<&str, i32> = StHashMap::new();
+    /// for word in "b a b c b a".split_whitespace() {
+    ///     *counts.entry(word).or_default() += 1;
+    /// }
+    /// assert_eq!(
+    ///     counts.iter().collect::<Vec<_>>(),
+    ///     vec![(&"b", &3), (&"a", &2), (&"c", &1)]
+    /// );
+    ///
+    /// assert_eq!(*counts.entry("d").or_default(), 0);
+    /// assert_eq!(counts.last(), Some((&"d", &0)));
+    /// ```
+    #[inline]
+    pub fn or_default(self) -> &'a mut V {
+        match self {
+            Self::Occupied(entry) => entry.into_mut(),
+            Self::Vacant(entry) => entry.insert(V::default()),
+        }
+    }
+}
+
+impl<'a, K, V, S, A: Allocator> OccupiedEntry<'a, K, V, S, A> {
+    /// Gets a reference to the key in the entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map: StHashMap<&str, u32> = StHashMap::new();
+    /// map.entry("poneyland").or_insert(12);
+    /// assert_eq!(map.entry("poneyland").key(), &"poneyland");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn key(&self) -> &K {
+        self.base.key()
+    }
+
+    /// Gets a reference to the value in the entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// use strudel::st_hash_map::Entry;
+    ///
+    /// let mut map: StHashMap<&str, u32> = StHashMap::new();
+    /// map.entry("poneyland").or_insert(12);
+    ///
+    /// if let Entry::Occupied(o) = map.entry("poneyland") {
+    ///     assert_eq!(o.get(), &12);
+    /// }
+    /// ```
+    #[inline]
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn get(&self) -> &V {
+        match &self.ordered[from_rank(*self.base.get())] {
+            InsertionEntry::Alive(_, value) => value,
+            InsertionEntry::Dead => panic!("occupied entry not alive in ordered storage"),
+        }
+    }
+
+    /// Gets a mutable reference to the value in the entry.
+    ///
+    /// If you need a reference to the `OccupiedEntry` which may outlive the
+    /// destruction of the `Entry` value, see [`into_mut`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// use strudel::st_hash_map::Entry;
+    ///
+    /// let mut map: StHashMap<&str, u32> = StHashMap::new();
+    /// map.entry("poneyland").or_insert(12);
+    ///
+    /// assert_eq!(map[&"poneyland"], 12);
+    /// if let Entry::Occupied(mut o) = map.entry("poneyland") {
+    ///     *o.get_mut() += 10;
+    ///     assert_eq!(*o.get(), 22);
+    ///
+    ///     // We can use the same Entry multiple times.
+    ///     *o.get_mut() += 2;
+    /// }
+    ///
+    /// assert_eq!(map[&"poneyland"], 24);
+    /// ```
+    ///
+    /// [`into_mut`]: Self::into_mut
+    #[inline]
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn get_mut(&mut self) -> &mut V {
+        let insert_rank = from_rank(*self.base.get());
+        match &mut self.ordered[insert_rank] {
+            InsertionEntry::Alive(_, value) => value,
+            InsertionEntry::Dead => panic!("occupied entry not alive in ordered storage"),
+        }
+    }
+
+    /// Converts the `OccupiedEntry` into a mutable reference to the value in
+    /// the entry with a lifetime bound to the map itself.
+    ///
+    /// If you need multiple references to the `OccupiedEntry`, see
+    /// [`get_mut`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// use strudel::st_hash_map::Entry;
+    ///
+    /// let mut map: StHashMap<&str, u32> = StHashMap::new();
+    /// map.entry("poneyland").or_insert(12);
+    ///
+    /// assert_eq!(map[&"poneyland"], 12);
+    /// if let Entry::Occupied(o) = map.entry("poneyland") {
+    ///     *o.into_mut() += 10;
+    /// }
+    ///
+    /// assert_eq!(map[&"poneyland"], 22);
+    /// ```
+    ///
+    /// [`get_mut`]: Self::get_mut
+    #[inline]
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn into_mut(self) -> &'a mut V {
+        let insert_rank = from_rank(*self.base.get());
+        match &mut self.ordered[insert_rank] {
+            InsertionEntry::Alive(_, value) => value,
+            InsertionEntry::Dead => panic!("occupied entry not alive in ordered storage"),
+        }
+    }
+
+    /// Sets the value of the entry, and returns the entry's old value.
+    ///
+    /// The insertion rank of the entry is not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// use strudel::st_hash_map::Entry;
+    ///
+    /// let mut map: StHashMap<&str, u32> = StHashMap::new();
+    /// map.entry("poneyland").or_insert(12);
+    ///
+    /// if let Entry::Occupied(mut o) = map.entry("poneyland") {
+    ///     assert_eq!(o.insert(15), 12);
+    /// }
+    ///
+    /// assert_eq!(map[&"poneyland"], 15);
+    /// ```
+    #[inline]
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Take the ownership of the key and value from the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// use strudel::st_hash_map::Entry;
+    ///
+    /// let mut map: StHashMap<&str, u32> = StHashMap::new();
+    /// map.entry("poneyland").or_insert(12);
+    ///
+    /// if let Entry::Occupied(o) = map.entry("poneyland") {
+    ///     // We delete the entry from the map.
+    ///     assert_eq!(o.remove_entry(), ("poneyland", 12));
+    /// }
+    ///
+    /// assert_eq!(map.contains_key(&"poneyland"), false);
+    /// ```
+    #[inline]
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn remove_entry(self) -> (K, V) {
+        let (_, insert_rank) = self.base.remove_entry();
+        match mem::replace(
+            &mut self.ordered[from_rank(insert_rank)],
+            InsertionEntry::Dead,
+        ) {
+            InsertionEntry::Alive(key, value) => (key, value),
+            InsertionEntry::Dead => panic!("removed pair not alive in ordered storage"),
+        }
+    }
+
+    /// Takes the value out of the entry, and returns it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// use strudel::st_hash_map::Entry;
+    ///
+    /// let mut map: StHashMap<&str, u32> = StHashMap::new();
+    /// map.entry("poneyland").or_insert(12);
+    ///
+    /// if let Entry::Occupied(o) = map.entry("poneyland") {
+    ///     assert_eq!(o.remove(), 12);
+    /// }
+    ///
+    /// assert_eq!(map.contains_key(&"poneyland"), false);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+}
+
+impl<K, V, S, A: Allocator> VacantEntry<'_, K, V, S, A> {
+    /// Gets a reference to the key that would be used when inserting a value
+    /// through the `VacantEntry`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map: StHashMap<&str, u32> = StHashMap::new();
+    /// assert_eq!(map.entry("poneyland").key(), &"poneyland");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn key(&self) -> &K {
+        self.base.key()
+    }
+
+    /// Take ownership of the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// use strudel::st_hash_map::Entry;
+    ///
+    /// let mut map: StHashMap<&str, u32> = StHashMap::new();
+    ///
+    /// if let Entry::Vacant(v) = map.entry("poneyland") {
+    ///     v.into_key();
+    /// }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn into_key(self) -> K {
+        self.base.into_key()
+    }
+}
+
+impl<'a, K, V, S, A: Allocator> VacantEntry<'a, K, V, S, A>
+where
+    K: Hash + Clone,
+    S: BuildHasher,
+{
+    /// Sets the value of the entry with the `VacantEntry`'s key, and returns a
+    /// mutable reference to it.
+    ///
+    /// The new entry is ordered after all existing entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map already holds `u32::MAX + 1` entries. See
+    /// [`StHashMap`](crate::StHashMap) for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// use strudel::st_hash_map::Entry;
+    ///
+    /// let mut map: StHashMap<&str, u32> = StHashMap::new();
+    /// map.insert("a", 1);
+    ///
+    /// if let Entry::Vacant(o) = map.entry("poneyland") {
+    ///     o.insert(37);
+    /// }
+    /// assert_eq!(map[&"poneyland"], 37);
+    /// assert_eq!(map.last(), Some((&"poneyland", &37)));
+    /// ```
+    #[inline]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn insert(self, value: V) -> &'a mut V {
+        let insert_rank = to_rank(self.ordered.len());
+        self.ordered
+            .push(InsertionEntry::Alive(self.base.key().clone(), value));
+        self.base.insert(insert_rank);
+        match self.ordered.last_mut() {
+            Some(InsertionEntry::Alive(_, value)) => value,
+            _ => panic!("inserted pair not alive in ordered storage"),
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use core::fmt;
+
+/// The error returned by the `TryFrom<Vec<(K, V)>>` conversion for
+/// [`StHashMap`] when a key appears more than once in the input.
+///
+/// Contains the position of the repeated pair in the input and the pair
+/// itself.
+///
+/// [`StHashMap`]: crate::StHashMap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKey<K, V> {
+    /// The index in the input of the pair whose key was already seen.
+    pub index: usize,
+    /// The repeated key.
+    pub key: K,
+    /// The value paired with the repeated key.
+    pub value: V,
+}
+
+impl<K, V> fmt::Display for DuplicateKey<K, V>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "duplicate key {:?} at index {}", self.key, self.index)
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K, V> std::error::Error for DuplicateKey<K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+}
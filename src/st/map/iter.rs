@@ -1,9 +1,14 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
 use core::iter::{FromIterator, FusedIterator};
+use core::mem;
 use core::ops::Range;
 use core::slice;
-use std::vec;
 
-use crate::st::map::{InsertionEntry, StHashMap};
+use allocator_api2::alloc::{Allocator, Global};
+use allocator_api2::vec;
+
+use crate::st::map::{DefaultHashBuilder, InsertionEntry, StHashMap};
 
 /// This struct is created by the [`iter`](StHashMap::iter) method on
 /// [`StHashMap`]. See its documentation for more.
@@ -18,7 +23,7 @@ impl<'a, K, V> Iterator for Iter<'a, K, V> {
         loop {
             match self.0.next() {
                 None => return None,
-                Some(InsertionEntry::Dead) => continue,
+                Some(InsertionEntry::Dead) => {}
                 Some(InsertionEntry::Alive(key, value)) => return Some((key, value)),
             }
         }
@@ -30,14 +35,53 @@ impl<'a, K, V> Iterator for Iter<'a, K, V> {
     }
 }
 
-impl<'a, K, V> FusedIterator for Iter<'a, K, V> {}
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
 
-impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
     fn next_back(&mut self) -> Option<Self::Item> {
         loop {
             match self.0.next_back() {
                 None => return None,
-                Some(InsertionEntry::Dead) => continue,
+                Some(InsertionEntry::Dead) => {}
+                Some(InsertionEntry::Alive(key, value)) => return Some((key, value)),
+            }
+        }
+    }
+}
+
+/// This struct is created by the [`iter_mut`](StHashMap::iter_mut) method on
+/// [`StHashMap`]. See its documentation for more.
+#[derive(Debug)]
+pub struct IterMut<'a, K, V>(pub(crate) slice::IterMut<'a, InsertionEntry<K, V>>);
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.0.next() {
+                None => return None,
+                Some(InsertionEntry::Dead) => {}
+                Some(InsertionEntry::Alive(key, value)) => return Some((key, value)),
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> FusedIterator for IterMut<'_, K, V> {}
+
+impl<K, V> DoubleEndedIterator for IterMut<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.0.next_back() {
+                None => return None,
+                Some(InsertionEntry::Dead) => {}
                 Some(InsertionEntry::Alive(key, value)) => return Some((key, value)),
             }
         }
@@ -48,9 +92,61 @@ impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
 /// documentation for more.
 #[derive(Debug)]
 #[allow(clippy::module_name_repetitions)]
-pub struct IntoIter<K, V>(vec::IntoIter<InsertionEntry<K, V>>);
+pub struct IntoIter<K, V, A: Allocator = Global> {
+    iter: vec::IntoIter<InsertionEntry<K, V>, A>,
+    remaining: usize,
+}
+
+impl<K, V, A: Allocator> Iterator for IntoIter<K, V, A> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.iter.next() {
+                None => return None,
+                Some(InsertionEntry::Dead) => {}
+                Some(InsertionEntry::Alive(key, value)) => {
+                    self.remaining -= 1;
+                    return Some((key, value));
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V, A: Allocator> FusedIterator for IntoIter<K, V, A> {}
+
+impl<K, V, A: Allocator> ExactSizeIterator for IntoIter<K, V, A> {}
+
+impl<K, V, A: Allocator> DoubleEndedIterator for IntoIter<K, V, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.iter.next_back() {
+                None => return None,
+                Some(InsertionEntry::Dead) => {}
+                Some(InsertionEntry::Alive(key, value)) => {
+                    self.remaining -= 1;
+                    return Some((key, value));
+                }
+            }
+        }
+    }
+}
+
+/// This struct is created by the [`drain`](StHashMap::drain) method on
+/// [`StHashMap`]. See its documentation for more.
+#[derive(Debug)]
+pub struct Drain<'a, K, V, A: Allocator = Global>(
+    pub(crate) vec::Drain<'a, InsertionEntry<K, V>, A>,
+);
 
-impl<K, V> Iterator for IntoIter<K, V> {
+impl<K, V, A: Allocator> Iterator for Drain<'_, K, V, A> {
     type Item = (K, V);
 
     #[inline]
@@ -58,7 +154,7 @@ impl<K, V> Iterator for IntoIter<K, V> {
         loop {
             match self.0.next() {
                 None => return None,
-                Some(InsertionEntry::Dead) => continue,
+                Some(InsertionEntry::Dead) => {}
                 Some(InsertionEntry::Alive(key, value)) => return Some((key, value)),
             }
         }
@@ -70,20 +166,79 @@ impl<K, V> Iterator for IntoIter<K, V> {
     }
 }
 
-impl<K, V> FusedIterator for IntoIter<K, V> {}
+impl<K, V, A: Allocator> FusedIterator for Drain<'_, K, V, A> {}
 
-impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+impl<K, V, A: Allocator> DoubleEndedIterator for Drain<'_, K, V, A> {
     fn next_back(&mut self) -> Option<Self::Item> {
         loop {
             match self.0.next_back() {
                 None => return None,
-                Some(InsertionEntry::Dead) => continue,
+                Some(InsertionEntry::Dead) => {}
                 Some(InsertionEntry::Alive(key, value)) => return Some((key, value)),
             }
         }
     }
 }
 
+/// This struct is created by the [`extract_if`](StHashMap::extract_if) method
+/// on [`StHashMap`]. See its documentation for more.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct ExtractIf<'a, K, V, F, S = DefaultHashBuilder, A: Allocator = Global> {
+    pub(crate) map: &'a mut StHashMap<K, V, S, A>,
+    pub(crate) rank: usize,
+    pub(crate) pred: F,
+}
+
+impl<K, V, F, S, A: Allocator> Iterator for ExtractIf<'_, K, V, F, S, A>
+where
+    K: Eq + Hash,
+    F: FnMut(&K, &mut V) -> bool,
+    S: BuildHasher,
+{
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(entry) = self.map.ordered.get_mut(self.rank) {
+            self.rank += 1;
+            let extract = match entry {
+                InsertionEntry::Alive(key, value) => (self.pred)(key, value),
+                InsertionEntry::Dead => false,
+            };
+            if extract {
+                if let InsertionEntry::Alive(key, value) = mem::replace(entry, InsertionEntry::Dead)
+                {
+                    self.map.map.remove(&key);
+                    return Some((key, value));
+                }
+            }
+        }
+        None
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.map.ordered.len().saturating_sub(self.rank);
+        (0, Some(remaining))
+    }
+}
+
+impl<K, V, F, S, A: Allocator> FusedIterator for ExtractIf<'_, K, V, F, S, A>
+where
+    K: Eq + Hash,
+    F: FnMut(&K, &mut V) -> bool,
+    S: BuildHasher,
+{
+}
+
+impl<K, V, F, S, A: Allocator> fmt::Debug for ExtractIf<'_, K, V, F, S, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtractIf")
+            .field("rank", &self.rank)
+            .finish()
+    }
+}
+
 /// This struct is created by the [`keys`](StHashMap::keys) method on
 /// [`StHashMap`]. See its documentation for more.
 #[derive(Debug, Clone)]
@@ -123,9 +278,9 @@ impl<'a, K, V> Iterator for Keys<'a, K, V> {
     }
 }
 
-impl<'a, K, V> FusedIterator for Keys<'a, K, V> {}
+impl<K, V> FusedIterator for Keys<'_, K, V> {}
 
-impl<'a, K, V> DoubleEndedIterator for Keys<'a, K, V> {
+impl<K, V> DoubleEndedIterator for Keys<'_, K, V> {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.0.next_back().map(|(key, _)| key)
     }
@@ -174,9 +329,60 @@ impl<'a, K, V> Iterator for Values<'a, K, V> {
     }
 }
 
-impl<'a, K, V> FusedIterator for Values<'a, K, V> {}
+impl<K, V> FusedIterator for Values<'_, K, V> {}
+
+impl<K, V> DoubleEndedIterator for Values<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(_, value)| value)
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        self.0.nth_back(n).map(|(_, value)| value)
+    }
+}
+
+/// This struct is created by the [`values_mut`](StHashMap::values_mut) method
+/// on [`StHashMap`]. See its documentation for more.
+#[derive(Debug)]
+pub struct ValuesMut<'a, K, V>(pub(crate) IterMut<'a, K, V>);
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, value)| value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+
+    #[inline]
+    fn last(self) -> Option<Self::Item> {
+        self.0.last().map(|(_, value)| value)
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.0.nth(n).map(|(_, value)| value)
+    }
+
+    #[inline]
+    fn collect<B: FromIterator<Self::Item>>(self) -> B {
+        self.0.map(|(_, value)| value).collect()
+    }
+}
+
+impl<K, V> FusedIterator for ValuesMut<'_, K, V> {}
 
-impl<'a, K, V> DoubleEndedIterator for Values<'a, K, V> {
+impl<K, V> DoubleEndedIterator for ValuesMut<'_, K, V> {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.0.next_back().map(|(_, value)| value)
     }
@@ -241,17 +447,274 @@ impl DoubleEndedIterator for InsertRanks {
     }
 }
 
-impl<K, V, S> IntoIterator for StHashMap<K, V, S> {
+/// A view of a run of consecutive live entries in an [`StHashMap`].
+///
+/// This struct is yielded by the [`Windows`] and [`Chunks`] iterators. It
+/// borrows the map's insertion list directly, so the run may span dead slots
+/// left by removals. [`Slice::len`] and [`Slice::iter`] only count and yield
+/// live entries.
+pub struct Slice<'a, K, V> {
+    entries: &'a [InsertionEntry<K, V>],
+    len: usize,
+}
+
+impl<'a, K, V> Slice<'a, K, V> {
+    /// Returns the number of live entries in the view.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the view contains no live entries.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// An iterator visiting the live key-value pairs of the view in insertion
+    /// order.
+    #[inline]
+    #[must_use]
+    pub fn iter(&self) -> Iter<'a, K, V> {
+        Iter(self.entries.iter())
+    }
+
+    /// Returns the first live key-value pair of the view.
+    #[inline]
+    #[must_use]
+    pub fn first(&self) -> Option<(&'a K, &'a V)> {
+        self.iter().next()
+    }
+
+    /// Returns the last live key-value pair of the view.
+    #[inline]
+    #[must_use]
+    pub fn last(&self) -> Option<(&'a K, &'a V)> {
+        self.iter().next_back()
+    }
+}
+
+impl<K, V> Clone for Slice<'_, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for Slice<'_, K, V> {}
+
+impl<K, V> fmt::Debug for Slice<'_, K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, K, V> IntoIterator for Slice<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &Slice<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// Return the index of the first live entry at or after `from`, or the length of
+// `entries` if there is none.
+fn next_alive<K, V>(entries: &[InsertionEntry<K, V>], from: usize) -> usize {
+    entries
+        .iter()
+        .skip(from)
+        .position(|entry| matches!(entry, InsertionEntry::Alive(_, _)))
+        .map_or(entries.len(), |offset| from + offset)
+}
+
+// Walk up to `count` live entries starting at index `from`. Return one past the
+// index of the last live entry visited and the number of live entries visited.
+fn take_alive<K, V>(entries: &[InsertionEntry<K, V>], from: usize, count: usize) -> (usize, usize) {
+    let mut end = from;
+    let mut live = 0;
+    while live < count {
+        let next = next_alive(entries, end);
+        if next == entries.len() {
+            break;
+        }
+        end = next + 1;
+        live += 1;
+    }
+    (end, live)
+}
+
+/// This struct is created by the [`windows`](StHashMap::windows) method on
+/// [`StHashMap`]. See its documentation for more.
+pub struct Windows<'a, K, V> {
+    entries: &'a [InsertionEntry<K, V>],
+    size: usize,
+    // Index of the first live entry of the next window.
+    start: usize,
+    // One past the index of the last live entry of the next window, or `None`
+    // once fewer than `size` live entries remain.
+    end: Option<usize>,
+}
+
+impl<'a, K, V> Windows<'a, K, V> {
+    pub(crate) fn new(entries: &'a [InsertionEntry<K, V>], size: usize) -> Self {
+        assert!(size != 0, "window size must be non-zero");
+        let start = next_alive(entries, 0);
+        let (end, live) = take_alive(entries, start, size);
+        let end = if live == size { Some(end) } else { None };
+        Self {
+            entries,
+            size,
+            start,
+            end,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Windows<'a, K, V> {
+    type Item = Slice<'a, K, V>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let end = self.end?;
+        let window = Slice {
+            entries: &self.entries[self.start..end],
+            len: self.size,
+        };
+        self.start = next_alive(self.entries, self.start + 1);
+        let next = next_alive(self.entries, end);
+        self.end = if next < self.entries.len() {
+            Some(next + 1)
+        } else {
+            None
+        };
+        Some(window)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.end {
+            Some(end) => (1, Some(self.entries.len() - end + 1)),
+            None => (0, Some(0)),
+        }
+    }
+}
+
+impl<K, V> FusedIterator for Windows<'_, K, V> {}
+
+impl<K, V> Clone for Windows<'_, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries,
+            size: self.size,
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for Windows<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Windows")
+            .field("size", &self.size)
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .finish_non_exhaustive()
+    }
+}
+
+/// This struct is created by the [`chunks`](StHashMap::chunks) method on
+/// [`StHashMap`]. See its documentation for more.
+pub struct Chunks<'a, K, V> {
+    entries: &'a [InsertionEntry<K, V>],
+    size: usize,
+}
+
+impl<'a, K, V> Chunks<'a, K, V> {
+    pub(crate) fn new(entries: &'a [InsertionEntry<K, V>], size: usize) -> Self {
+        assert!(size != 0, "chunk size must be non-zero");
+        Self { entries, size }
+    }
+}
+
+impl<'a, K, V> Iterator for Chunks<'a, K, V> {
+    type Item = Slice<'a, K, V>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = next_alive(self.entries, 0);
+        let (end, live) = take_alive(self.entries, start, self.size);
+        if live == 0 {
+            self.entries = &[];
+            return None;
+        }
+        let (chunk, rest) = self.entries.split_at(end);
+        self.entries = rest;
+        Some(Slice {
+            entries: &chunk[start..],
+            len: live,
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.entries.len().div_ceil(self.size)))
+    }
+}
+
+impl<K, V> FusedIterator for Chunks<'_, K, V> {}
+
+impl<K, V> Clone for Chunks<'_, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries,
+            size: self.size,
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for Chunks<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Chunks")
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, V, S, A: Allocator> IntoIterator for StHashMap<K, V, S, A> {
     type Item = (K, V);
-    type IntoIter = IntoIter<K, V>;
+    type IntoIter = IntoIter<K, V, A>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
-        IntoIter(self.ordered.into_iter())
+        let remaining = self.len();
+        IntoIter {
+            iter: self.ordered.into_iter(),
+            remaining,
+        }
     }
 }
 
-impl<'a, K: 'a, V: 'a, S> IntoIterator for &'a StHashMap<K, V, S> {
+impl<'a, K: 'a, V: 'a, S, A: Allocator> IntoIterator for &'a StHashMap<K, V, S, A> {
     type Item = (&'a K, &'a V);
     type IntoIter = Iter<'a, K, V>;
 
@@ -260,3 +723,13 @@ impl<'a, K: 'a, V: 'a, S> IntoIterator for &'a StHashMap<K, V, S> {
         self.iter()
     }
 }
+
+impl<'a, K: 'a, V: 'a, S, A: Allocator> IntoIterator for &'a mut StHashMap<K, V, S, A> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
@@ -1,105 +1,122 @@
 use core::borrow::Borrow;
-use core::hash::{BuildHasher, Hash, Hasher};
-use core::mem::size_of;
-use core::ops::Index;
-use std::collections::hash_map::{Entry as HashEntry, RandomState};
-use std::collections::HashMap;
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::fmt;
+#[cfg(feature = "std")]
+use core::hash::Hasher;
+use core::hash::{BuildHasher, Hash};
+use core::iter::FromIterator;
+use core::mem::{self, size_of};
+use core::ops::{Index, IndexMut, Range};
 
-mod iter;
-
-pub use iter::{InsertRanks, IntoIter, Iter, Keys, Values};
-
-// Tables with `LINEAR_SCAN_THRESHOLD` or fewer insertions will perform lookups
-// by scanning the `ordered` insertion list.
-const LINEAR_SCAN_THRESHOLD: usize = 8;
+use alloc::vec::Vec;
+use allocator_api2::alloc::{Allocator, Global};
+#[cfg(feature = "std")]
+use allocator_api2::collections::TryReserveErrorKind;
+use allocator_api2::vec::Vec as AllocVec;
+use hashbrown::hash_map::{Entry as HashEntry, HashMap};
+#[cfg(feature = "std")]
+use std::collections::hash_map::DefaultHasher;
 
-#[derive(Debug, Clone)]
-pub(crate) struct Key<T> {
-    inner: T,
-    insert_rank: usize,
-}
-
-impl<T> Key<T> {
-    #[inline]
-    #[must_use]
-    pub fn insert_rank(&self) -> usize {
-        self.insert_rank
-    }
+#[cfg(feature = "std")]
+mod entry;
+mod error;
+mod iter;
+#[cfg(feature = "rayon")]
+mod par;
+#[cfg(feature = "serde")]
+pub(crate) mod serde;
 
-    #[inline]
-    #[must_use]
-    pub fn inner(&self) -> &T {
-        &self.inner
-    }
+#[cfg(feature = "std")]
+pub use entry::{Entry, OccupiedEntry, OccupiedError, VacantEntry};
+pub use error::DuplicateKey;
+#[cfg(feature = "std")]
+pub use hashbrown::TryReserveError;
+pub use iter::{
+    Chunks, Drain, ExtractIf, InsertRanks, IntoIter, Iter, IterMut, Keys, Slice, Values, ValuesMut,
+    Windows,
+};
+#[cfg(feature = "rayon")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+pub use par::{IntoParIter, ParIter, ParValues};
 
-    #[inline]
-    #[must_use]
-    pub fn into_inner(self) -> T {
-        self.inner
-    }
-}
+/// The default hash builder for [`StHashMap`] and [`StHashSet`].
+///
+/// With the **std** feature enabled, this is [`RandomState`] from `std`, which
+/// provides resistance against HashDoS attacks. Otherwise, this is the default
+/// hash builder from [`hashbrown`].
+///
+/// [`StHashSet`]: crate::StHashSet
+/// [`RandomState`]: std::collections::hash_map::RandomState
+/// [`hashbrown`]: https://docs.rs/hashbrown
+#[cfg(feature = "std")]
+pub type DefaultHashBuilder = std::collections::hash_map::RandomState;
 
-impl<T> PartialEq for Key<T>
-where
-    T: PartialEq,
-{
-    #[inline]
-    fn eq(&self, other: &Self) -> bool {
-        self.inner() == other.inner()
-    }
-}
+/// The default hash builder for [`StHashMap`] and [`StHashSet`].
+///
+/// With the **std** feature enabled, this is `RandomState` from `std`, which
+/// provides resistance against HashDoS attacks. Otherwise, this is the default
+/// hash builder from [`hashbrown`].
+///
+/// [`StHashSet`]: crate::StHashSet
+/// [`hashbrown`]: https://docs.rs/hashbrown
+#[cfg(not(feature = "std"))]
+pub type DefaultHashBuilder = hashbrown::DefaultHashBuilder;
 
-impl<T> Eq for Key<T> where T: Eq {}
+// Tables with fewer than `LINEAR_SCAN_THRESHOLD` insertion slots perform
+// lookups with `get`, `get_key_value`, and `contains_key` by scanning the
+// `ordered` insertion list.
+const LINEAR_SCAN_THRESHOLD: usize = 8;
 
-impl<T> Hash for Key<T>
-where
-    T: Hash,
-{
-    #[inline]
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.inner().hash(state);
-    }
-}
+// Insertion ranks are stored in the `map` as `u32` rather than `usize`. For
+// keys of 4 bytes or less this halves the size of each `(K, rank)` bucket on
+// 64-bit targets. It limits the insertion list, live and dead slots, to
+// `u32::MAX + 1` entries. See `StHashMap` for how that limit is enforced.
+type Rank = u32;
 
-impl<T> Borrow<T> for Key<T> {
-    #[inline]
-    fn borrow(&self) -> &T {
-        self.inner()
-    }
+// The largest insertion rank that can be stored, as an index into `ordered`.
+#[cfg(not(all(test, feature = "std")))]
+#[inline]
+fn max_rank() -> usize {
+    from_rank(Rank::MAX)
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub(crate) struct Value<T> {
-    inner: T,
+// Tests lower the largest rank to exercise a full insertion list without
+// allocating `u32::MAX + 1` entries. See `tests::MAX_RANK`.
+#[cfg(all(test, feature = "std"))]
+fn max_rank() -> usize {
+    tests::MAX_RANK.with(core::cell::Cell::get)
 }
 
-impl<T> From<T> for Value<T> {
-    fn from(value: T) -> Self {
-        Self { inner: value }
+// Convert an index into the `ordered` insertion list to a stored rank.
+//
+// Panics if the index is larger than `max_rank()`.
+#[inline]
+fn to_rank(insert_rank: usize) -> Rank {
+    match Rank::try_from(insert_rank) {
+        Ok(rank) if insert_rank <= max_rank() => rank,
+        _ => panic!("StHashMap insertion list exceeds u32::MAX + 1 slots"),
     }
 }
 
-impl<T> Value<T> {
-    #[inline]
-    #[must_use]
-    pub fn inner(&self) -> &T {
-        &self.inner
-    }
-
-    #[inline]
-    #[must_use]
-    pub fn into_inner(self) -> T {
-        self.inner
-    }
+// Convert a stored rank back to an index into the `ordered` insertion list.
+#[inline]
+fn from_rank(rank: Rank) -> usize {
+    rank as usize
 }
 
-impl<T> Borrow<T> for Value<T> {
-    #[inline]
-    fn borrow(&self) -> &T {
-        self.inner()
+// Report a failure to grow the `ordered` insertion list with the same error
+// type as the `map`.
+#[cfg(feature = "std")]
+fn ordered_reserve_error(err: &allocator_api2::collections::TryReserveError) -> TryReserveError {
+    match err.kind() {
+        TryReserveErrorKind::CapacityOverflow => TryReserveError::CapacityOverflow,
+        TryReserveErrorKind::AllocError { layout, .. } => TryReserveError::AllocError { layout },
     }
 }
 
+// The `ordered` insertion list is the canonical storage for values. The `map`
+// indexes into `ordered` by storing the insertion rank of each key.
 #[derive(Debug, Clone)]
 pub(crate) enum InsertionEntry<K, V> {
     Alive(K, V),
@@ -112,25 +129,61 @@ pub(crate) enum InsertionEntry<K, V> {
 /// FFI-friendly.
 ///
 /// `StHashMap` is built on top of a hashing algorithm selected to provide
-/// resistance against HashDoS attacks. See [`RandomState`].
+/// resistance against HashDoS attacks. See [`DefaultHashBuilder`].
 ///
 /// `StHashMap` supports updating keys in place. See [`StHashMap::update`].
 ///
+/// Removing a key leaves a dead slot in the insertion list. When an insert
+/// would grow the insertion list and at least half of its slots are dead, the
+/// dead slots are reclaimed instead. This renumbers insertion ranks but
+/// preserves the relative insertion order of entries. See
+/// [`StHashMap::rebuilds`].
+///
+/// Insertion ranks are stored in the hash table as `u32`. On 64-bit targets
+/// this halves the hash table bucket of keys of 4 bytes or less, like `u32`.
+/// Keys aligned to 8 bytes, like `u64`, get no saving: their buckets are
+/// padded to 16 bytes, the same as with `usize` ranks.
+///
+/// The insertion list, including dead slots, holds at most `u32::MAX + 1`
+/// entries. An insert into a full insertion list reclaims its dead slots
+/// first. If no slots are dead, the insert panics, and
+/// [`StHashMap::try_reserve`] returns an error for space past this limit.
+///
+/// Each value is stored exactly once, so values need not implement [`Clone`].
+/// Keys are stored in both the hash table and the insertion list, so inserting
+/// requires `K: Clone`.
+///
+/// The hash table and the insertion list allocate through `A`, which defaults
+/// to the global allocator. With the **nightly** feature, constructors like
+/// `new_in` and `with_capacity_in` create a map which allocates through a
+/// custom allocator.
+///
 /// The optional `api` and `capi` modules in `strudel` build on top of
 /// `StHashMap` to implement a compatible C API to `st_hash`. This API includes
 /// support for iterating over a mutable map and inplace updates of
-/// `(key, value)` pairs. These features distinguish it from the [`HashMap`] in
+/// `(key, value)` pairs. These features distinguish it from the `HashMap` in
 /// Rust `std`.
-///
-/// [`RandomState`]: std::collections::hash_map::RandomState
-#[derive(Default, Debug, Clone)]
+#[derive(Debug)]
 #[allow(clippy::module_name_repetitions)]
-pub struct StHashMap<K, V, S = RandomState> {
-    map: HashMap<Key<K>, Value<V>, S>,
-    ordered: Vec<InsertionEntry<K, V>>,
+pub struct StHashMap<K, V, S = DefaultHashBuilder, A: Allocator = Global> {
+    // Maps each key to its insertion rank, an index into `ordered`.
+    map: HashMap<K, Rank, S, A>,
+    ordered: AllocVec<InsertionEntry<K, V>, A>,
+    rebuilds: usize,
+    compaction_threshold: Option<f64>,
+}
+
+impl<K, V, S> Default for StHashMap<K, V, S>
+where
+    S: Default,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
 }
 
-impl<K, V, S> PartialEq for StHashMap<K, V, S>
+impl<K, V, S, A: Allocator> PartialEq for StHashMap<K, V, S, A>
 where
     K: Eq + Hash,
     V: PartialEq,
@@ -138,11 +191,15 @@ where
 {
     fn eq(&self, other: &Self) -> bool {
         // Only map contents contribute to equality
-        self.map == other.map
+        if self.len() != other.len() {
+            return false;
+        }
+        self.iter()
+            .all(|(key, value)| other.get(key) == Some(value))
     }
 }
 
-impl<K, V, S> Eq for StHashMap<K, V, S>
+impl<K, V, S, A: Allocator> Eq for StHashMap<K, V, S, A>
 where
     K: Eq + Hash,
     V: PartialEq,
@@ -150,7 +207,132 @@ where
 {
 }
 
-impl<K, V, S> Index<&K> for StHashMap<K, V, S>
+impl<K, V, S, A: Allocator> fmt::Display for StHashMap<K, V, S, A>
+where
+    K: fmt::Display,
+    V: fmt::Display,
+{
+    /// Formats the entries of this map as `{k1: v1, k2: v2}` in insertion
+    /// order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.remove(&"b");
+    ///
+    /// assert_eq!(map.to_string(), "{a: 1, c: 3}");
+    /// assert_eq!(StHashMap::<i32, i32>::new().to_string(), "{}");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("{")?;
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{key}: {value}")?;
+        }
+        f.write_str("}")
+    }
+}
+
+impl<K, V, S, A: Allocator + Clone> Clone for StHashMap<K, V, S, A>
+where
+    K: Clone,
+    V: Clone,
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+            ordered: self.ordered.clone(),
+            rebuilds: self.rebuilds,
+            compaction_threshold: self.compaction_threshold,
+        }
+    }
+
+    /// Performs copy-assignment from `source`, reusing the allocations of
+    /// `self` where possible.
+    ///
+    /// The insertion list is copied exactly, including dead slots, so
+    /// insertion ranks and the rebuild counter of `self` match `source`
+    /// afterward. `self` also takes a clone of the hasher of `source`.
+    ///
+    /// The insertion list is reallocated only if `source` has more slots than
+    /// `self` has capacity for. The hash table is reallocated only if its
+    /// bucket count differs from `source`, so repeatedly cloning the same
+    /// source into the same destination does not allocate.
+    fn clone_from(&mut self, source: &Self) {
+        self.map.clone_from(&source.map);
+        self.ordered.clone_from(&source.ordered);
+        self.rebuilds = source.rebuilds;
+        self.compaction_threshold = source.compaction_threshold;
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K, V, S, A: Allocator> Hash for StHashMap<K, V, S, A>
+where
+    K: Hash,
+    V: Hash,
+{
+    /// Feeds the contents of this map into the given [`Hasher`].
+    ///
+    /// Like [`PartialEq`], hashing ignores insertion order: maps with the same
+    /// key-value pairs hash equally regardless of the order the pairs were
+    /// inserted in. Each pair is hashed individually with a fixed-key hasher
+    /// and the per-pair hashes are combined with XOR, which is commutative.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::{Hash, Hasher};
+    /// use strudel::StHashMap;
+    ///
+    /// fn hash_of<T: Hash>(value: &T) -> u64 {
+    ///     let mut hasher = DefaultHasher::new();
+    ///     value.hash(&mut hasher);
+    ///     hasher.finish()
+    /// }
+    ///
+    /// let mut forward = StHashMap::new();
+    /// forward.insert("a", 1);
+    /// forward.insert("b", 2);
+    /// forward.insert("c", 3);
+    ///
+    /// let mut backward = StHashMap::new();
+    /// backward.insert("c", 3);
+    /// backward.insert("b", 2);
+    /// backward.insert("a", 1);
+    ///
+    /// assert_eq!(forward, backward);
+    /// assert_eq!(hash_of(&forward), hash_of(&backward));
+    ///
+    /// backward.insert("a", 100);
+    /// assert_ne!(hash_of(&forward), hash_of(&backward));
+    /// ```
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Hash each pair with the same fixed keys so the combined hash does not
+        // depend on the map's `BuildHasher`, which does not participate in
+        // equality.
+        let combined = self.iter().fold(0_u64, |combined, pair| {
+            let mut hasher = DefaultHasher::new();
+            pair.hash(&mut hasher);
+            combined ^ hasher.finish()
+        });
+        state.write_usize(self.len());
+        state.write_u64(combined);
+    }
+}
+
+impl<K, V, S, A: Allocator> Index<&K> for StHashMap<K, V, S, A>
 where
     K: Eq + Hash,
     S: BuildHasher,
@@ -168,551 +350,767 @@ where
     }
 }
 
-impl<K, V> StHashMap<K, V, RandomState> {
-    /// Creates an empty `StHashMap`.
-    ///
-    /// The hash map is initially created with a capacity of 0, so it will not
-    /// allocate until it is first inserted into.
+impl<K, V, S, A: Allocator> IndexMut<&K> for StHashMap<K, V, S, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns a mutable reference to the value corresponding to the supplied
+    /// key.
     ///
     /// # Examples
     ///
     /// ```
     /// use strudel::StHashMap;
-    /// let mut map: StHashMap<&str, i32> = StHashMap::new();
-    /// assert_eq!(0, map.capacity());
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map[&"a"] += 1;
+    /// assert_eq!(map[&"a"], 2);
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not present in the `HashMap`.
     #[inline]
-    #[must_use]
-    pub fn new() -> Self {
-        let map = HashMap::new();
-        let ordered = Vec::new();
-        Self { map, ordered }
+    fn index_mut(&mut self, key: &K) -> &mut V {
+        self.get_mut(key).expect("no entry found for key")
     }
+}
 
-    /// Creates an empty `StHashMap` with the specified capacity.
+impl<K, V, S, A: Allocator> Extend<(K, V)> for StHashMap<K, V, S, A>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    /// Inserts all key-value pairs from the iterator in iteration order.
     ///
-    /// The hash map will be able to hold at least `capacity` elements without
-    /// reallocating. If `capacity` is 0, the hash map will not allocate.
+    /// Keys already present in the map have their values updated and keep
+    /// their insertion rank.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map would hold more than `u32::MAX + 1` entries. See
+    /// [`StHashMap`] for this limit.
     ///
     /// # Examples
     ///
     /// ```
     /// use strudel::StHashMap;
-    /// let mut map: StHashMap<&str, i32> = StHashMap::with_capacity(10);
-    /// assert!(map.capacity() >= 10);
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.extend(vec![("c", 3), ("a", 10), ("d", 4)]);
+    ///
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["a", "b", "c", "d"]);
+    /// assert_eq!(map.get(&"a"), Some(&10));
     /// ```
     #[inline]
-    #[must_use]
-    pub fn with_capacity(capacity: usize) -> Self {
-        let map = HashMap::with_capacity(capacity);
-        let ordered = Vec::with_capacity(capacity);
-        Self { map, ordered }
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (key, value) in iter {
+            let _ = self.insert(key, value);
+        }
     }
 }
 
-impl<K, V, S> StHashMap<K, V, S> {
-    /// Creates an empty `StHashMap` which will use the given hash builder to
-    /// hash keys.
+impl<'a, K, V, S, A: Allocator> Extend<(&'a K, &'a V)> for StHashMap<K, V, S, A>
+where
+    K: Eq + Hash + Copy,
+    V: Copy,
+    S: BuildHasher,
+{
+    /// Inserts copies of all key-value pairs from the iterator in iteration
+    /// order.
     ///
-    /// The created map has the default initial capacity.
+    /// Keys already present in the map have their values updated and keep
+    /// their insertion rank.
     ///
-    /// Warning: `hash_builder` is normally randomly generated, and is designed
-    /// to allow `StHashMap`s to be resistant to attacks that cause many
-    /// collisions and very poor performance. Setting it manually using this
-    /// function can expose a DoS attack vector.
+    /// # Panics
     ///
-    /// The `hash_builder` passed should implement the [`BuildHasher`] trait for
-    /// the `StHashMap` to be useful, see its documentation for details.
+    /// Panics if the map would hold more than `u32::MAX + 1` entries. See
+    /// [`StHashMap`] for this limit.
     ///
     /// # Examples
     ///
     /// ```
     /// use strudel::StHashMap;
-    /// use std::collections::hash_map::RandomState;
     ///
-    /// let s = RandomState::new();
-    /// let mut map = StHashMap::with_hasher(s);
-    /// assert_eq!(0, map.capacity());
-    /// map.insert(1, 2);
+    /// let mut map = StHashMap::new();
+    /// map.insert(1, 1);
+    /// let mut other = StHashMap::new();
+    /// other.insert(2, 2);
+    /// other.insert(1, 100);
+    /// map.extend(&other);
+    ///
+    /// assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&1, &100), (&2, &2)]);
     /// ```
     #[inline]
-    #[must_use]
-    pub fn with_hasher(hash_builder: S) -> Self {
-        let map = HashMap::with_hasher(hash_builder);
-        let ordered = Vec::new();
-        Self { map, ordered }
+    fn extend<T: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: T) {
+        self.extend(iter.into_iter().map(|(&key, &value)| (key, value)));
     }
+}
 
-    /// Creates an empty `StHashMap` with the specified capacity, using the
-    /// given hash builder to hash keys.
+impl<K, V, S> FromIterator<(K, V)> for StHashMap<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher + Default,
+{
+    /// Creates an `StHashMap` from an iterator of key-value pairs.
     ///
-    /// The hash map will be able to hold at least `capacity` elements without
-    /// reallocating. If `capacity` is 0, the hash map will not allocate.
+    /// Pairs are inserted in iteration order. If a key appears more than once,
+    /// the last value wins and the key keeps the insertion rank of its first
+    /// occurrence.
     ///
-    /// Warning: `hash_builder` is normally randomly generated, and is designed
-    /// to allow `StHashMap`s to be resistant to attacks that cause many
-    /// collisions and very poor performance. Setting it manually using this
-    /// function can expose a DoS attack vector.
+    /// # Panics
     ///
-    /// The `hash_builder` passed should implement the [`BuildHasher`] trait for
-    /// the `StHashMap` to be useful, see its documentation for details.
+    /// Panics if the map would hold more than `u32::MAX + 1` entries. See
+    /// [`StHashMap`] for this limit.
     ///
     /// # Examples
     ///
     /// ```
     /// use strudel::StHashMap;
-    /// use std::collections::hash_map::RandomState;
     ///
-    /// let s = RandomState::new();
-    /// let mut map = StHashMap::with_capacity_and_hasher(10, s);
-    /// assert!(map.capacity() >= 10);
-    /// map.insert(1, 2);
+    /// let map = vec![("a", 1), ("b", 2), ("a", 3)]
+    ///     .into_iter()
+    ///     .collect::<StHashMap<_, _>>();
+    /// assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&"a", &3), (&"b", &2)]);
     /// ```
     #[inline]
-    #[must_use]
-    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
-        let map = HashMap::with_capacity_and_hasher(capacity, hash_builder);
-        let ordered = Vec::with_capacity(capacity);
-        Self { map, ordered }
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = Self::with_hasher(S::default());
+        map.extend(iter);
+        map
     }
+}
 
-    /// Returns the number of elements the map can hold without reallocating.
+impl<K, V, S> TryFrom<Vec<(K, V)>> for StHashMap<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher + Default,
+{
+    type Error = DuplicateKey<K, V>;
+
+    /// Creates an `StHashMap` from a `Vec` of key-value pairs, rejecting
+    /// repeated keys.
     ///
-    /// This number is a lower bound; the `StHashMap` might be able to hold
-    /// more, but is guaranteed to be able to hold at least this many.
+    /// Pairs are inserted in `Vec` order. Unlike [`FromIterator`], which lets
+    /// the last value win, this conversion fails with a [`DuplicateKey`] error
+    /// for the first pair whose key was already inserted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map would hold more than `u32::MAX + 1` entries. See
+    /// [`StHashMap`] for this limit.
     ///
     /// # Examples
     ///
     /// ```
+    /// use std::convert::TryFrom;
     /// use strudel::StHashMap;
-    /// let mut map: StHashMap<&str, i32> = StHashMap::with_capacity(100);
-    /// assert!(map.capacity() >= 100);
+    ///
+    /// let map = StHashMap::<_, _>::try_from(vec![("b", 2), ("a", 1)]).unwrap();
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["b", "a"]);
+    ///
+    /// let err = StHashMap::<_, _>::try_from(vec![("b", 2), ("a", 1), ("b", 3)]).unwrap_err();
+    /// assert_eq!(err.index, 2);
+    /// assert_eq!(err.key, "b");
+    /// assert_eq!(err.value, 3);
     /// ```
-    #[inline]
-    #[must_use]
-    pub fn capacity(&self) -> usize {
-        self.map.capacity().min(self.ordered.capacity())
+    fn try_from(pairs: Vec<(K, V)>) -> Result<Self, Self::Error> {
+        let mut map = Self::with_capacity_and_hasher(pairs.len(), S::default());
+        for (index, (key, value)) in pairs.into_iter().enumerate() {
+            if map.contains_key(&key) {
+                return Err(DuplicateKey { index, key, value });
+            }
+            let _ = map.insert(key, value);
+        }
+        Ok(map)
     }
+}
 
-    /// An iterator visiting all keys in insertion order. The iterator element
-    /// type is `&'a K`.
+impl<K, V, const N: usize> From<[(K, V); N]> for StHashMap<K, V, DefaultHashBuilder>
+where
+    K: Eq + Hash + Clone,
+{
+    /// Creates an `StHashMap` from an array of key-value pairs.
+    ///
+    /// Pairs are inserted in array order. If a key appears more than once, the
+    /// last value wins and the key keeps the insertion rank of its first
+    /// occurrence.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map would hold more than `u32::MAX + 1` entries. See
+    /// [`StHashMap`] for this limit.
     ///
     /// # Examples
     ///
     /// ```
     /// use strudel::StHashMap;
     ///
-    /// let mut map = StHashMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
-    /// map.insert("c", 3);
+    /// let map: StHashMap<_, _> = [("a", 1), ("b", 2)].into();
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["a", "b"]);
     ///
-    /// for key in map.keys() {
-    ///     println!("key: {}", key);
-    /// }
+    /// let map: StHashMap<_, _> = [("a", 1), ("b", 2), ("a", 3)].into();
+    /// assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&"a", &3), (&"b", &2)]);
     /// ```
     #[inline]
-    #[must_use]
-    pub fn keys(&self) -> Keys<'_, K, V> {
-        Keys(self.iter())
+    fn from(arr: [(K, V); N]) -> Self {
+        IntoIterator::into_iter(arr).collect()
     }
+}
 
-    /// An iterator visiting all values in insertion order. The iterator element
-    /// type is `&'a V`.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K, V, S> From<std::collections::HashMap<K, V, S>> for StHashMap<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    /// Creates an `StHashMap` from a [`HashMap`], reusing its hasher.
+    ///
+    /// Pairs are inserted in the iteration order of the given [`HashMap`],
+    /// which is arbitrary but deterministic for a particular [`HashMap`]
+    /// instance.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map would hold more than `u32::MAX + 1` entries. See
+    /// [`StHashMap`] for this limit.
     ///
     /// # Examples
     ///
     /// ```
+    /// use std::collections::HashMap;
     /// use strudel::StHashMap;
     ///
-    /// let mut map = StHashMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
-    /// map.insert("c", 3);
+    /// let mut hash_map = HashMap::new();
+    /// hash_map.insert("a", 1);
+    /// hash_map.insert("b", 2);
+    /// let expected = hash_map.keys().copied().collect::<Vec<_>>();
     ///
-    /// for val in map.values() {
-    ///     println!("val: {}", val);
-    /// }
+    /// let map = StHashMap::from(hash_map);
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), expected);
+    /// assert_eq!(map[&"a"], 1);
+    /// assert_eq!(map[&"b"], 2);
     /// ```
+    ///
+    /// [`HashMap`]: std::collections::HashMap
     #[inline]
-    #[must_use]
-    pub fn values(&self) -> Values<'_, K, V> {
-        Values(self.iter())
+    fn from(map: std::collections::HashMap<K, V, S>) -> Self {
+        let mut st_map = Self::with_capacity_and_hasher(map.len(), map.hasher().clone());
+        for (key, value) in map {
+            let _ = st_map.insert(key, value);
+        }
+        st_map
     }
+}
 
-    /// An iterator for visiting all key-value pairs in insertion order. The
-    /// iterator element type is `(&'a K, &'a V)`.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K, V, S, A: Allocator> From<StHashMap<K, V, S, A>> for std::collections::HashMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    /// Creates a [`HashMap`] from an `StHashMap`, reusing its hasher.
+    ///
+    /// Insertion order is not preserved. Use [`StHashMap::into_vec`] for a
+    /// lossless conversion.
     ///
     /// # Examples
     ///
     /// ```
+    /// use std::collections::HashMap;
     /// use strudel::StHashMap;
     ///
     /// let mut map = StHashMap::new();
     /// map.insert("a", 1);
     /// map.insert("b", 2);
-    /// map.insert("c", 3);
     ///
-    /// for (key, val) in map.iter() {
-    ///     println!("key: {} val: {}", key, val);
-    /// }
+    /// let hash_map = HashMap::from(map);
+    /// assert_eq!(hash_map.len(), 2);
+    /// assert_eq!(hash_map[&"a"], 1);
+    /// assert_eq!(hash_map[&"b"], 2);
     /// ```
+    ///
+    /// [`HashMap`]: std::collections::HashMap
     #[inline]
-    #[must_use]
-    pub fn iter(&self) -> Iter<'_, K, V> {
-        Iter(self.ordered.iter())
+    fn from(map: StHashMap<K, V, S, A>) -> Self {
+        let mut hash_map = Self::with_capacity_and_hasher(map.len(), map.hasher().clone());
+        hash_map.extend(map);
+        hash_map
     }
+}
 
-    /// An iterator for visiting all insertion counters in insertion order
-    /// starting from the given rank. The iterator element type is `usize`.
-    ///
-    /// This iterator may return insertion counters to dead elements.
-    ///
-    /// The yielded elements may be passed to [`get_nth`] to retrieve the
-    /// `(key, value)` pair in the nth insertion slot.
+impl<K, V> StHashMap<K, V, DefaultHashBuilder> {
+    /// Creates an empty `StHashMap`.
     ///
-    /// This API can be used to build a mutable iterator over the map that can
-    /// safely be invalidated. This is safe because new inserts always have
-    /// higher insert rank. See `api::st_foreach`.
+    /// The hash map is initially created with a capacity of 0, so it will not
+    /// allocate until it is first inserted into.
     ///
     /// # Examples
     ///
     /// ```
     /// use strudel::StHashMap;
-    ///
-    /// let mut map = StHashMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
-    /// map.insert("c", 3);
-    ///
-    /// map.remove(&"a");
-    /// map.insert("b", 100);
-    ///
-    /// let insert_ranks = map.insert_ranks_from(0).collect::<Vec<_>>();
-    /// assert_eq!(vec![0, 1, 2], insert_ranks);
-    ///
-    /// assert_eq!(None, map.get_nth(0));
-    /// assert_eq!(Some((&"b", &100)), map.get_nth(1));
-    /// assert_eq!(Some((&"c", &3)), map.get_nth(2));
-    /// assert_eq!(None, map.get_nth(4));
-    ///
-    /// assert_eq!(0, map.insert_ranks_from(100).count());
+    /// let mut map: StHashMap<&str, i32> = StHashMap::new();
+    /// assert_eq!(0, map.capacity());
     /// ```
-    ///
-    /// [`get_nth`]: StHashMap::get_nth
     #[inline]
     #[must_use]
-    pub fn insert_ranks_from(&self, rank: usize) -> InsertRanks {
-        InsertRanks(rank..self.ordered.len())
+    pub fn new() -> Self {
+        Self::with_hasher(DefaultHashBuilder::default())
     }
 
-    /// Returns the first key-value pair in the map. The key in this pair is
-    /// equal to the key inserted earliest into the map.
+    /// Creates an empty `StHashMap` with capacity for at least `size` elements
+    /// rounded up to the next power of two.
     ///
-    /// Key-value pairs are ordered by insertion order. Insertion order is
-    /// maintained if there are deletions. Insertion order is by slot, so
-    /// [in-place updates to keys] maintain the same insertion position.
+    /// This mirrors MRI's `st.c`, which sizes entry arrays as
+    /// `2^entry_power` slots. Both the hash table and the insertion list are
+    /// sized to hold the rounded capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the next power of two of `size` overflows `usize`.
     ///
     /// # Examples
     ///
     /// ```
     /// use strudel::StHashMap;
-    ///
-    /// let mut map = StHashMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
-    /// map.insert("c", 3);
-    /// assert_eq!(Some((&"a", &1)), map.first());
-    ///
-    /// map.remove(&"a");
-    /// map.insert("b", 100);
-    /// assert_eq!(Some((&"b", &100)), map.first());
+    /// let map: StHashMap<&str, i32> = StHashMap::with_pow2_capacity(5);
+    /// assert!(map.capacity() >= 8);
+    /// assert_eq!(map.ordered_capacity(), 8);
     /// ```
-    ///
-    /// [in-place updates to keys]: StHashMap::update
     #[inline]
     #[must_use]
-    pub fn first(&self) -> Option<(&K, &V)> {
-        self.iter().next()
+    pub fn with_pow2_capacity(size: usize) -> Self {
+        Self::with_pow2_capacity_and_hasher(size, DefaultHashBuilder::default())
     }
 
-    /// Returns the last key-value pair in the map. The key in this pair is
-    /// equal to the key inserted most recently into the map.
+    /// Creates an empty `StHashMap` which compacts itself on removal once more
+    /// than `ratio` of its insertion list slots are dead.
     ///
-    /// Key-value pairs are ordered by insertion order. Insertion order is
-    /// maintained if there are deletions. Insertion order is by slot, so
-    /// [in-place updates to keys] maintain the same insertion position.
+    /// See [`StHashMap::set_compaction_threshold`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ratio` is not between `0.0` and `1.0`.
     ///
     /// # Examples
     ///
     /// ```
     /// use strudel::StHashMap;
     ///
-    /// let mut map = StHashMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
-    /// map.insert("c", 3);
-    /// assert_eq!(Some((&"c", &3)), map.last());
+    /// let mut map = StHashMap::with_compaction_threshold(0.5);
+    /// for i in 0..4 {
+    ///     map.insert(i, i);
+    /// }
+    /// map.remove(&0);
+    /// map.remove(&1);
+    /// assert_eq!(map.dead_slots(), 2);
     ///
-    /// map.remove(&"a");
-    /// map.insert("b", 100);
-    /// assert_eq!(Some((&"c", &3)), map.last());
+    /// // Three of four slots are dead, which exceeds the threshold.
+    /// map.remove(&2);
+    /// assert_eq!(map.dead_slots(), 0);
+    /// assert_eq!(map.get_nth(0), Some((&3, &3)));
     /// ```
-    ///
-    /// [in-place updates to keys]: StHashMap::update
     #[inline]
     #[must_use]
-    pub fn last(&self) -> Option<(&K, &V)> {
-        self.iter().last()
+    pub fn with_compaction_threshold(ratio: f64) -> Self {
+        let mut map = Self::new();
+        map.set_compaction_threshold(Some(ratio));
+        map
     }
 
-    /// Returns the nth key-value pair in the map. The key in this pair is
-    /// equal to the key inserted nth earliest into the map.
+    /// Creates an empty `StHashMap` with the specified capacity.
     ///
-    /// Key-value pairs are ordered by insertion order. Insertion order is
-    /// maintained if there are deletions. Insertion order is by slot, so
-    /// [in-place updates to keys] maintain the same insertion position.
+    /// The hash map will be able to hold at least `capacity` elements without
+    /// reallocating. If `capacity` is 0, the hash map will not allocate.
     ///
     /// # Examples
     ///
     /// ```
     /// use strudel::StHashMap;
+    /// let mut map: StHashMap<&str, i32> = StHashMap::with_capacity(10);
+    /// assert!(map.capacity() >= 10);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, DefaultHashBuilder::default())
+    }
+}
+
+impl<K, V, S> StHashMap<K, V, S> {
+    /// Creates an empty `StHashMap` which will use the given hash builder to
+    /// hash keys.
     ///
-    /// let mut map = StHashMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
-    /// map.insert("c", 3);
+    /// The created map has the default initial capacity.
     ///
-    /// map.remove(&"a");
-    /// map.insert("b", 100);
+    /// Warning: `hash_builder` is normally randomly generated, and is designed
+    /// to allow `StHashMap`s to be resistant to attacks that cause many
+    /// collisions and very poor performance. Setting it manually using this
+    /// function can expose a DoS attack vector.
     ///
-    /// let insert_ranks = map.insert_ranks_from(0).collect::<Vec<_>>();
-    /// assert_eq!(vec![0, 1, 2], insert_ranks);
+    /// The `hash_builder` passed should implement the [`BuildHasher`] trait for
+    /// the `StHashMap` to be useful, see its documentation for details.
     ///
-    /// assert_eq!(None, map.get_nth(0));
-    /// assert_eq!(Some((&"b", &100)), map.get_nth(1));
-    /// assert_eq!(Some((&"c", &3)), map.get_nth(2));
-    /// assert_eq!(None, map.get_nth(4));
+    /// # Examples
     ///
-    /// assert_eq!(0, map.insert_ranks_from(100).count());
     /// ```
+    /// use strudel::StHashMap;
+    /// use std::collections::hash_map::RandomState;
     ///
-    /// [in-place updates to keys]: StHashMap::update
+    /// let s = RandomState::new();
+    /// let mut map = StHashMap::with_hasher(s);
+    /// assert_eq!(0, map.capacity());
+    /// map.insert(1, 2);
+    /// ```
     #[inline]
     #[must_use]
-    pub fn get_nth(&self, n: usize) -> Option<(&K, &V)> {
-        match self.ordered.get(n) {
-            None | Some(InsertionEntry::Dead) => None,
-            Some(InsertionEntry::Alive(key, value)) => Some((key, value)),
+    pub fn with_hasher(hash_builder: S) -> Self {
+        let map = HashMap::with_hasher(hash_builder);
+        let ordered = AllocVec::new();
+        Self {
+            map,
+            ordered,
+            rebuilds: 0,
+            compaction_threshold: None,
         }
     }
 
-    /// Insertion counter for the [first](StHashMap::first) key-value pair in
-    /// the map.
+    /// Creates an empty `StHashMap` with the specified capacity, using the
+    /// given hash builder to hash keys.
+    ///
+    /// The hash map will be able to hold at least `capacity` elements without
+    /// reallocating. If `capacity` is 0, the hash map will not allocate.
+    ///
+    /// Warning: `hash_builder` is normally randomly generated, and is designed
+    /// to allow `StHashMap`s to be resistant to attacks that cause many
+    /// collisions and very poor performance. Setting it manually using this
+    /// function can expose a DoS attack vector.
+    ///
+    /// The `hash_builder` passed should implement the [`BuildHasher`] trait for
+    /// the `StHashMap` to be useful, see its documentation for details.
     ///
     /// # Examples
     ///
     /// ```
     /// use strudel::StHashMap;
+    /// use std::collections::hash_map::RandomState;
     ///
-    /// let mut map = StHashMap::new();
-    /// assert_eq!(0, map.min_insert_rank());
-    ///
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
-    /// map.insert("c", 3);
-    /// assert_eq!(0, map.min_insert_rank());
-    ///
-    /// map.remove(&"a");
-    /// map.insert("b", 100);
-    /// assert_eq!(1, map.min_insert_rank());
+    /// let s = RandomState::new();
+    /// let mut map = StHashMap::with_capacity_and_hasher(10, s);
+    /// assert!(map.capacity() >= 10);
+    /// map.insert(1, 2);
     /// ```
     #[inline]
     #[must_use]
-    pub fn min_insert_rank(&self) -> usize {
-        for (idx, pair) in self.ordered.iter().enumerate() {
-            if let InsertionEntry::Alive(_, _) = pair {
-                return idx;
-            }
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        let map = HashMap::with_capacity_and_hasher(capacity, hash_builder);
+        let ordered = AllocVec::with_capacity(capacity);
+        Self {
+            map,
+            ordered,
+            rebuilds: 0,
+            compaction_threshold: None,
         }
-        0
     }
 
-    /// Insertion counter for the [last](StHashMap::last) key-value pair in the
-    /// map.
+    /// Creates an empty `StHashMap` which will use the default value of its
+    /// hash builder type to hash keys.
+    ///
+    /// Unlike [`StHashMap::new`], this constructor is available for any hash
+    /// builder which implements [`Default`], so generic code can create a map
+    /// without naming the hash builder.
     ///
     /// # Examples
     ///
     /// ```
+    /// use std::collections::hash_map::RandomState;
     /// use strudel::StHashMap;
     ///
-    /// let mut map = StHashMap::new();
-    /// assert_eq!(0, map.max_insert_rank());
-    ///
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
-    /// map.insert("c", 3);
-    /// assert_eq!(2, map.max_insert_rank());
-    ///
-    /// map.remove(&"a");
-    /// map.insert("b", 100);
-    /// assert_eq!(2, map.max_insert_rank());
+    /// let map: StHashMap<&str, i32, RandomState> = StHashMap::with_default_hasher();
+    /// assert_eq!(0, map.capacity());
     /// ```
     #[inline]
     #[must_use]
-    pub fn max_insert_rank(&self) -> usize {
-        for (idx, pair) in self.ordered.iter().enumerate().rev() {
-            if let InsertionEntry::Alive(_, _) = pair {
-                return idx;
-            }
-        }
-        0
+    pub fn with_default_hasher() -> Self
+    where
+        S: Default,
+    {
+        Self::with_hasher(S::default())
     }
 
-    /// Returns the number of elements in the map.
+    /// Creates an empty `StHashMap` with the specified capacity which will use
+    /// the default value of its hash builder type to hash keys.
+    ///
+    /// Unlike [`StHashMap::with_capacity`], this constructor is available for
+    /// any hash builder which implements [`Default`].
     ///
     /// # Examples
     ///
     /// ```
+    /// use std::collections::hash_map::RandomState;
     /// use strudel::StHashMap;
     ///
-    /// let mut map = StHashMap::new();
-    /// assert_eq!(0, map.len());
-    /// map.insert(1, "a");
-    /// assert_eq!(1, map.len());
+    /// let map: StHashMap<&str, i32, RandomState> =
+    ///     StHashMap::with_capacity_and_default_hasher(10);
+    /// assert!(map.capacity() >= 10);
     /// ```
     #[inline]
     #[must_use]
-    pub fn len(&self) -> usize {
-        self.map.len()
+    pub fn with_capacity_and_default_hasher(capacity: usize) -> Self
+    where
+        S: Default,
+    {
+        Self::with_capacity_and_hasher(capacity, S::default())
     }
 
-    /// Returns `true` if the map contains no elements.
+    /// Creates an empty `StHashMap` with capacity for at least `size` elements
+    /// rounded up to the next power of two, using `hash_builder` to hash keys.
+    ///
+    /// See [`StHashMap::with_pow2_capacity`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the next power of two of `size` overflows `usize`.
     ///
     /// # Examples
     ///
     /// ```
     /// use strudel::StHashMap;
+    /// use std::collections::hash_map::RandomState;
     ///
-    /// let mut map = StHashMap::new();
-    /// assert!(map.is_empty());
-    /// map.insert(1, "a");
-    /// assert!(!map.is_empty());
+    /// let s = RandomState::new();
+    /// let mut map = StHashMap::with_pow2_capacity_and_hasher(5, s);
+    /// assert!(map.capacity() >= 8);
+    /// map.insert(1, 2);
     /// ```
     #[inline]
     #[must_use]
-    pub fn is_empty(&self) -> bool {
-        self.map.is_empty()
+    pub fn with_pow2_capacity_and_hasher(size: usize, hash_builder: S) -> Self {
+        let capacity = size.checked_next_power_of_two().expect("capacity overflow");
+        let map = HashMap::with_capacity_and_hasher(capacity, hash_builder);
+        let ordered = AllocVec::with_capacity(capacity);
+        Self {
+            map,
+            ordered,
+            rebuilds: 0,
+            compaction_threshold: None,
+        }
     }
+}
 
-    /// Clears the map, removing all key-value pairs. Keeps the allocated memory
-    /// for reuse.
+#[cfg(feature = "nightly")]
+#[cfg_attr(docsrs, doc(cfg(feature = "nightly")))]
+impl<K, V, A> StHashMap<K, V, DefaultHashBuilder, A>
+where
+    A: Allocator + Clone,
+{
+    /// Creates an empty `StHashMap` which allocates through `alloc`.
+    ///
+    /// Both the hash table and the insertion list allocate through `alloc`.
+    /// The hash map is initially created with a capacity of 0, so it will not
+    /// allocate until it is first inserted into.
     ///
     /// # Examples
     ///
     /// ```
+    /// #![feature(allocator_api)]
+    /// use std::alloc::System;
     /// use strudel::StHashMap;
     ///
-    /// let mut map = StHashMap::new();
-    /// map.insert(1, "a");
-    /// map.clear();
-    /// assert!(map.is_empty());
+    /// let mut map = StHashMap::new_in(System);
+    /// assert_eq!(0, map.capacity());
+    /// map.insert("a", 1);
     /// ```
     #[inline]
-    pub fn clear(&mut self) {
-        self.map.clear();
-        self.ordered.clear();
+    #[must_use]
+    pub fn new_in(alloc: A) -> Self {
+        Self::with_hasher_in(DefaultHashBuilder::default(), alloc)
     }
 
-    /// Returns a reference to the map's [`BuildHasher`].
+    /// Creates an empty `StHashMap` with the specified capacity which
+    /// allocates through `alloc`.
+    ///
+    /// The hash map will be able to hold at least `capacity` elements without
+    /// reallocating. If `capacity` is 0, the hash map will not allocate.
     ///
     /// # Examples
     ///
     /// ```
+    /// #![feature(allocator_api)]
+    /// use std::alloc::System;
     /// use strudel::StHashMap;
-    /// use std::collections::hash_map::RandomState;
     ///
-    /// let hasher = RandomState::new();
-    /// let map: StHashMap<i32, i32> = StHashMap::with_hasher(hasher);
-    /// let hasher: &RandomState = map.hasher();
+    /// let map: StHashMap<&str, i32, _, _> = StHashMap::with_capacity_in(10, System);
+    /// assert!(map.capacity() >= 10);
     /// ```
     #[inline]
     #[must_use]
-    pub fn hasher(&self) -> &S {
-        self.map.hasher()
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        Self::with_capacity_and_hasher_in(capacity, DefaultHashBuilder::default(), alloc)
     }
+}
 
-    /// Return an estimate of the byte size of memory allocted for this map.
+#[cfg(feature = "nightly")]
+#[cfg_attr(docsrs, doc(cfg(feature = "nightly")))]
+impl<K, V, S, A> StHashMap<K, V, S, A>
+where
+    A: Allocator + Clone,
+{
+    /// Creates an empty `StHashMap` which will use the given hash builder to
+    /// hash keys and allocates through `alloc`.
+    ///
+    /// See [`StHashMap::with_hasher`] and [`StHashMap::new_in`].
     ///
     /// # Examples
     ///
     /// ```
+    /// #![feature(allocator_api)]
+    /// use std::alloc::System;
+    /// use std::collections::hash_map::RandomState;
     /// use strudel::StHashMap;
-    /// let empty: StHashMap<i32, i32> = StHashMap::with_capacity(0);
-    /// let map: StHashMap<i32, i32> = StHashMap::with_capacity(100);
-    /// assert!(map.estimated_memsize() > empty.estimated_memsize());
+    ///
+    /// let s = RandomState::new();
+    /// let mut map = StHashMap::with_hasher_in(s, System);
+    /// assert_eq!(0, map.capacity());
+    /// map.insert(1, 2);
     /// ```
     #[inline]
     #[must_use]
-    pub fn estimated_memsize(&self) -> usize {
-        let stack_size = size_of::<Self>();
-        let hashmap_size = (size_of::<Key<K>>() + size_of::<V>()) * self.map.capacity();
-        let vec_size = size_of::<InsertionEntry<K, V>>() * self.ordered.len();
+    pub fn with_hasher_in(hash_builder: S, alloc: A) -> Self {
+        let map = HashMap::with_hasher_in(hash_builder, alloc.clone());
+        let ordered = AllocVec::new_in(alloc);
+        Self {
+            map,
+            ordered,
+            rebuilds: 0,
+            compaction_threshold: None,
+        }
+    }
 
-        stack_size + hashmap_size + vec_size
+    /// Creates an empty `StHashMap` with the specified capacity, using
+    /// `hash_builder` to hash keys and allocating through `alloc`.
+    ///
+    /// See [`StHashMap::with_capacity_and_hasher`] and
+    /// [`StHashMap::with_capacity_in`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(allocator_api)]
+    /// use std::alloc::System;
+    /// use std::collections::hash_map::RandomState;
+    /// use strudel::StHashMap;
+    ///
+    /// let s = RandomState::new();
+    /// let mut map = StHashMap::with_capacity_and_hasher_in(10, s, System);
+    /// assert!(map.capacity() >= 10);
+    /// map.insert(1, 2);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_capacity_and_hasher_in(capacity: usize, hash_builder: S, alloc: A) -> Self {
+        let map = HashMap::with_capacity_and_hasher_in(capacity, hash_builder, alloc.clone());
+        let ordered = AllocVec::with_capacity_in(capacity, alloc);
+        Self {
+            map,
+            ordered,
+            rebuilds: 0,
+            compaction_threshold: None,
+        }
     }
 }
 
-impl<K, V, S> StHashMap<K, V, S>
-where
-    K: Eq + Hash,
-    S: BuildHasher,
-{
-    /// Reserves capacity for at least `additional` more elements to be inserted
-    /// in the `StHashMap`. The collection may reserve more space to avoid
-    /// frequent reallocations.
+impl<K, V, S, A: Allocator> StHashMap<K, V, S, A> {
+    /// Returns a reference to the allocator of this map.
     ///
-    /// # Panics
+    /// # Examples
     ///
-    /// Panics if the new allocation size overflows `usize`.
+    /// ```
+    /// #![feature(allocator_api)]
+    /// use std::alloc::System;
+    /// use strudel::StHashMap;
+    ///
+    /// let map: StHashMap<&str, i32, _, _> = StHashMap::new_in(System);
+    /// let _: &System = map.allocator();
+    /// ```
+    #[cfg(feature = "nightly")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "nightly")))]
+    #[inline]
+    #[must_use]
+    pub fn allocator(&self) -> &A {
+        self.map.allocator()
+    }
+
+    /// Returns the number of elements the map can hold without reallocating.
+    ///
+    /// This number is a lower bound; the `StHashMap` might be able to hold
+    /// more, but is guaranteed to be able to hold at least this many.
     ///
     /// # Examples
     ///
     /// ```
     /// use strudel::StHashMap;
-    /// let mut map: StHashMap<&str, i32> = StHashMap::new();
-    /// assert_eq!(0, map.capacity());
-    /// map.reserve(10);
-    /// assert!(map.capacity() >= 10);
+    /// let mut map: StHashMap<&str, i32> = StHashMap::with_capacity(100);
+    /// assert!(map.capacity() >= 100);
     /// ```
     #[inline]
-    pub fn reserve(&mut self, additional: usize) {
-        self.map.reserve(additional);
-        self.ordered.reserve(additional);
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.map.capacity().min(self.ordered.capacity())
     }
 
-    /// Shrinks the capacity of the map as much as possible. It will drop down
-    /// as much as possible while maintaining the internal rules and possibly
-    /// leaving some space in accordance with the resize policy.
+    /// Returns the number of keys the hash table can hold without
+    /// reallocating.
+    ///
+    /// The hash table and the insertion list grow independently.
+    /// [`StHashMap::capacity`] is the smaller of this and
+    /// [`StHashMap::ordered_capacity`].
     ///
     /// # Examples
     ///
     /// ```
     /// use strudel::StHashMap;
-    /// let mut map: StHashMap<i32, i32> = StHashMap::with_capacity(100);
-    /// map.insert(1, 2);
-    /// map.insert(3, 4);
-    /// assert!(map.capacity() >= 100);
-    /// map.shrink_to_fit();
-    /// assert!(map.capacity() >= 2);
+    /// let map: StHashMap<&str, i32> = StHashMap::with_capacity(100);
+    /// assert!(map.map_capacity() >= 100);
+    /// assert!(map.map_capacity() >= map.capacity());
     /// ```
     #[inline]
-    pub fn shrink_to_fit(&mut self) {
-        self.map.shrink_to_fit();
-        self.ordered.shrink_to_fit();
+    #[must_use]
+    pub fn map_capacity(&self) -> usize {
+        self.map.capacity()
     }
 
-    /// Returns `true` if the map contains a value for the specified key.
+    /// Returns the number of slots the insertion list can hold without
+    /// reallocating.
+    ///
+    /// Removals leave dead slots in the insertion list until they are
+    /// reclaimed, so this may greatly exceed [`len`](StHashMap::len) and
+    /// [`StHashMap::map_capacity`] after many removals.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// let map: StHashMap<&str, i32> = StHashMap::with_capacity(100);
+    /// assert!(map.ordered_capacity() >= 100);
+    /// assert!(map.ordered_capacity() >= map.capacity());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn ordered_capacity(&self) -> usize {
+        self.ordered.capacity()
+    }
+
+    /// An iterator visiting all keys in insertion order. The iterator element
+    /// type is `&'a K`.
     ///
     /// # Examples
     ///
@@ -720,17 +1118,22 @@ where
     /// use strudel::StHashMap;
     ///
     /// let mut map = StHashMap::new();
-    /// map.insert(1, "a");
-    /// assert_eq!(map.contains_key(&1), true);
-    /// assert_eq!(map.contains_key(&2), false);
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// for key in map.keys() {
+    ///     println!("key: {}", key);
+    /// }
     /// ```
     #[inline]
     #[must_use]
-    pub fn contains_key(&self, key: &K) -> bool {
-        self.map.contains_key(key)
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys(self.iter())
     }
 
-    /// Returns a reference to the value corresponding to the key.
+    /// An iterator visiting all values in insertion order. The iterator element
+    /// type is `&'a V`.
     ///
     /// # Examples
     ///
@@ -738,26 +1141,22 @@ where
     /// use strudel::StHashMap;
     ///
     /// let mut map = StHashMap::new();
-    /// map.insert(1, "a");
-    /// assert_eq!(map.get(&1), Some(&"a"));
-    /// assert_eq!(map.get(&2), None);
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// for val in map.values() {
+    ///     println!("val: {}", val);
+    /// }
     /// ```
     #[inline]
     #[must_use]
-    pub fn get(&self, key: &K) -> Option<&V> {
-        if self.ordered.len() < LINEAR_SCAN_THRESHOLD {
-            self.ordered.iter().find_map(|entry| match entry {
-                InsertionEntry::Alive(entry_key, entry_value) if entry_key == key => {
-                    Some(entry_value)
-                }
-                _ => None,
-            })
-        } else {
-            self.map.get(key).map(Value::inner)
-        }
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values(self.iter())
     }
 
-    /// Returns the key-value pair corresponding to the supplied key.
+    /// An iterator visiting all values mutably in insertion order. The iterator
+    /// element type is `&'a mut V`.
     ///
     /// # Examples
     ///
@@ -765,105 +1164,76 @@ where
     /// use strudel::StHashMap;
     ///
     /// let mut map = StHashMap::new();
-    /// map.insert(1, "a");
-    /// assert_eq!(map.get_key_value(&1), Some((&1, &"a")));
-    /// assert_eq!(map.get_key_value(&2), None);
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.remove(&"b");
+    ///
+    /// for val in map.values_mut() {
+    ///     *val += 10;
+    /// }
+    ///
+    /// assert_eq!(map.get(&"a"), Some(&11));
+    /// assert_eq!(map.get(&"b"), None);
+    /// assert_eq!(map.get(&"c"), Some(&13));
+    /// assert_eq!(map.values().collect::<Vec<_>>(), vec![&11, &13]);
     /// ```
     #[inline]
     #[must_use]
-    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
-        let (key, value) = self.map.get_key_value(key)?;
-        Some((key.inner(), value.inner()))
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut(self.iter_mut())
     }
-}
 
-impl<K, V, S> StHashMap<K, V, S>
-where
-    K: Eq + Hash + Clone,
-    V: PartialEq + Clone,
-    S: BuildHasher,
-{
-    /// Inserts a key-value pair into the map.
+    /// Clones all keys into a `Vec` in insertion order.
     ///
-    /// If the map did not have this key present, [`None`] is returned.
+    /// # Examples
     ///
-    /// If the map did have this key present, the value is updated, and the old
-    /// value is returned. The key is not updated, though. To update the key
-    /// in-place, use [`StHashMap::update`].
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.remove(&"b");
+    ///
+    /// assert_eq!(map.keys_vec(), vec!["a", "c"]);
+    /// ```
     #[inline]
     #[must_use]
-    #[allow(clippy::missing_panics_doc)]
-    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
-        let insert_rank = self.ordered.len();
-
-        let key = Key {
-            inner: key,
-            insert_rank,
-        };
-
-        match self.map.entry(key) {
-            HashEntry::Occupied(mut base) => {
-                let insert_rank = base.key().insert_rank();
-                // Maintain insert rank with new key-value pair.
-                if let Some(InsertionEntry::Alive(_, stored_value)) =
-                    self.ordered.get_mut(insert_rank)
-                {
-                    if *stored_value != value {
-                        *stored_value = value.clone();
-                    }
-                } else {
-                    panic!("already inserted pair not alive in ordered storage");
-                }
-                Some(base.insert(value.into()).into_inner())
-            }
-            HashEntry::Vacant(base) => {
-                self.ordered.push(InsertionEntry::Alive(
-                    base.key().inner().clone(),
-                    value.clone(),
-                ));
-                base.insert(value.into());
-                None
-            }
-        }
+    pub fn keys_vec(&self) -> Vec<K>
+    where
+        K: Clone,
+    {
+        self.keys().cloned().collect()
     }
 
-    /// Inserts a key-value pair into the map and update the key in place if an
-    /// entry is already present.
+    /// Clones all values into a `Vec` in insertion order.
     ///
-    /// This function maintains the insertion rank of the key-value pair.
+    /// # Examples
     ///
-    /// If you do not wish to update the key in-place, use
-    /// [`StHashMap::insert`].
-    #[allow(clippy::missing_panics_doc)]
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.remove(&"b");
+    ///
+    /// assert_eq!(map.values_vec(), vec![1, 3]);
+    /// ```
     #[inline]
-    pub fn update(&mut self, key: K, value: V) {
-        if let Some((entry_key, _)) = self.map.remove_entry(&key) {
-            let insert_rank = entry_key.insert_rank();
-            // Maintain insert rank with new key-value pair.
-            if let Some(InsertionEntry::Alive(stored_key, stored_value)) =
-                self.ordered.get_mut(insert_rank)
-            {
-                if *stored_key != key {
-                    *stored_key = key.clone();
-                }
-                if *stored_value != value {
-                    *stored_value = value.clone();
-                }
-            } else {
-                panic!("already inserted pair not alive in ordered storage");
-            }
-            let key = Key {
-                inner: key,
-                insert_rank,
-            };
-            self.map.insert(key, value.into());
-        } else {
-            let _ = self.insert(key, value);
-        }
+    #[must_use]
+    pub fn values_vec(&self) -> Vec<V>
+    where
+        V: Clone,
+    {
+        self.values().cloned().collect()
     }
 
-    /// Removes a key from the map, returning the stored key if the key was
-    /// previously in the map.
+    /// An iterator for visiting all key-value pairs in insertion order. The
+    /// iterator element type is `(&'a K, &'a V)`.
     ///
     /// # Examples
     ///
@@ -871,18 +1241,34 @@ where
     /// use strudel::StHashMap;
     ///
     /// let mut map = StHashMap::new();
-    /// map.insert(1, "a");
-    /// assert_eq!(map.remove(&1), Some("a"));
-    /// assert_eq!(map.remove(&1), None);
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// for (key, val) in map.iter() {
+    ///     println!("key: {} val: {}", key, val);
+    /// }
     /// ```
     #[inline]
     #[must_use]
-    pub fn remove(&mut self, key: &K) -> Option<V> {
-        self.remove_entry(key).map(|(_, value)| value)
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter(self.ordered.iter())
     }
 
-    /// Removes a key from the map, returning the stored key and value if the
-    /// key was previously in the map.
+    /// An iterator over all overlapping windows of `size` consecutive live
+    /// entries in insertion order. Dead slots left by removals are skipped, so
+    /// every window holds exactly `size` key-value pairs.
+    ///
+    /// The windows are [`Slice`] views that borrow the map's insertion list
+    /// directly. No entries are copied or compacted, which lets `windows` take
+    /// `&self`, but a window may span dead slots internally.
+    ///
+    /// If the map has fewer than `size` entries, the iterator yields no
+    /// windows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
     ///
     /// # Examples
     ///
@@ -890,15 +1276,4573 @@ where
     /// use strudel::StHashMap;
     ///
     /// let mut map = StHashMap::new();
-    /// map.insert(1, "a");
-    /// assert_eq!(map.remove_entry(&1), Some((1, "a")));
-    /// assert_eq!(map.remove(&1), None);
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.insert("d", 4);
+    /// map.remove(&"b");
+    ///
+    /// let sums = map
+    ///     .windows(2)
+    ///     .map(|window| window.iter().map(|(_, value)| value).sum::<i32>())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(sums, vec![4, 7]);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn windows(&self, size: usize) -> Windows<'_, K, V> {
+        Windows::new(&self.ordered, size)
+    }
+
+    /// An iterator over `size` live entries at a time in insertion order,
+    /// starting at the first entry. Dead slots left by removals are skipped.
+    ///
+    /// The chunks do not overlap. If the number of entries is not a multiple
+    /// of `size`, the last chunk holds the remainder. Like
+    /// [`windows`](StHashMap::windows), each chunk is a [`Slice`] view of the
+    /// map's insertion list and nothing is copied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = (0..6).map(|i| (i, i * 10)).collect::<StHashMap<_, _>>();
+    /// map.remove(&1);
+    ///
+    /// let keys = map
+    ///     .chunks(2)
+    ///     .map(|chunk| chunk.iter().map(|(&key, _)| key).collect::<Vec<_>>())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(keys, vec![vec![0, 2], vec![3, 4], vec![5]]);
     /// ```
     #[inline]
     #[must_use]
-    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
-        let (key, value) = self.map.remove_entry(key)?;
-        self.ordered[key.insert_rank()] = InsertionEntry::Dead;
-        Some((key.into_inner(), value.into_inner()))
+    pub fn chunks(&self, size: usize) -> Chunks<'_, K, V> {
+        Chunks::new(&self.ordered, size)
+    }
+
+    /// An iterator for visiting all key-value pairs in insertion order, with
+    /// mutable references to the values. The iterator element type is
+    /// `(&'a K, &'a mut V)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// for (key, val) in (0..10).zip(0..10) {
+    ///     map.insert(key, val);
+    /// }
+    /// map.remove(&3);
+    ///
+    /// for (key, val) in map.iter_mut() {
+    ///     *val *= 2;
+    ///     *val += key;
+    /// }
+    ///
+    /// assert_eq!(map.get(&0), Some(&0));
+    /// assert_eq!(map.get(&3), None);
+    /// assert_eq!(map.get(&9), Some(&27));
+    /// assert_eq!(
+    ///     map.values().copied().collect::<Vec<_>>(),
+    ///     vec![0, 3, 6, 12, 15, 18, 21, 24, 27]
+    /// );
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut(self.ordered.iter_mut())
+    }
+
+    /// An iterator for visiting all insertion counters in insertion order
+    /// starting from the given rank. The iterator element type is `usize`.
+    ///
+    /// This iterator may return insertion counters to dead elements.
+    ///
+    /// The yielded elements may be passed to [`get_nth`] to retrieve the
+    /// `(key, value)` pair in the nth insertion slot.
+    ///
+    /// This API can be used to build an iterator over the map that tolerates
+    /// mutation, like `st_foreach` in `strudel-ffi`. Removing an entry leaves
+    /// a dead slot behind and a new entry is ranked after all existing
+    /// entries, but an insert may also reclaim dead slots, which renumbers
+    /// every insertion rank. Compare [`rebuilds`] before and after each
+    /// mutation. If it changed, find the new rank of the last visited key and
+    /// resume from the rank after it. Otherwise entries may be skipped or
+    /// visited twice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// map.remove(&"a");
+    /// map.insert("b", 100);
+    ///
+    /// let insert_ranks = map.insert_ranks_from(0).collect::<Vec<_>>();
+    /// assert_eq!(vec![0, 1, 2], insert_ranks);
+    ///
+    /// assert_eq!(None, map.get_nth(0));
+    /// assert_eq!(Some((&"b", &100)), map.get_nth(1));
+    /// assert_eq!(Some((&"c", &3)), map.get_nth(2));
+    /// assert_eq!(None, map.get_nth(4));
+    ///
+    /// assert_eq!(0, map.insert_ranks_from(100).count());
+    /// ```
+    ///
+    /// [`get_nth`]: StHashMap::get_nth
+    /// [`rebuilds`]: StHashMap::rebuilds
+    #[inline]
+    #[must_use]
+    pub fn insert_ranks_from(&self, rank: usize) -> InsertRanks {
+        InsertRanks(rank..self.ordered.len())
+    }
+
+    /// Returns the first key-value pair in the map. The key in this pair is
+    /// equal to the key inserted earliest into the map.
+    ///
+    /// Key-value pairs are ordered by insertion order. Insertion order is
+    /// maintained if there are deletions. Insertion order is by slot, so
+    /// [in-place updates to keys] maintain the same insertion position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// assert_eq!(Some((&"a", &1)), map.first());
+    ///
+    /// map.remove(&"a");
+    /// map.insert("b", 100);
+    /// assert_eq!(Some((&"b", &100)), map.first());
+    /// ```
+    ///
+    /// [in-place updates to keys]: StHashMap::update
+    #[inline]
+    #[must_use]
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    /// Returns the last key-value pair in the map. The key in this pair is
+    /// equal to the key inserted most recently into the map.
+    ///
+    /// Key-value pairs are ordered by insertion order. Insertion order is
+    /// maintained if there are deletions. Insertion order is by slot, so
+    /// [in-place updates to keys] maintain the same insertion position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// assert_eq!(Some((&"c", &3)), map.last());
+    ///
+    /// map.remove(&"a");
+    /// map.insert("b", 100);
+    /// assert_eq!(Some((&"c", &3)), map.last());
+    /// ```
+    ///
+    /// [in-place updates to keys]: StHashMap::update
+    #[inline]
+    #[must_use]
+    pub fn last(&self) -> Option<(&K, &V)> {
+        self.iter().last()
+    }
+
+    /// Returns the nth key-value pair in the map. The key in this pair is
+    /// equal to the key inserted nth earliest into the map.
+    ///
+    /// Key-value pairs are ordered by insertion order. Insertion order is
+    /// maintained if there are deletions. Insertion order is by slot, so
+    /// [in-place updates to keys] maintain the same insertion position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// map.remove(&"a");
+    /// map.insert("b", 100);
+    ///
+    /// let insert_ranks = map.insert_ranks_from(0).collect::<Vec<_>>();
+    /// assert_eq!(vec![0, 1, 2], insert_ranks);
+    ///
+    /// assert_eq!(None, map.get_nth(0));
+    /// assert_eq!(Some((&"b", &100)), map.get_nth(1));
+    /// assert_eq!(Some((&"c", &3)), map.get_nth(2));
+    /// assert_eq!(None, map.get_nth(4));
+    ///
+    /// assert_eq!(0, map.insert_ranks_from(100).count());
+    /// ```
+    ///
+    /// [in-place updates to keys]: StHashMap::update
+    #[inline]
+    #[must_use]
+    pub fn get_nth(&self, n: usize) -> Option<(&K, &V)> {
+        match self.ordered.get(n) {
+            None | Some(InsertionEntry::Dead) => None,
+            Some(InsertionEntry::Alive(key, value)) => Some((key, value)),
+        }
+    }
+
+    /// Attempts to get mutable references to the values at `N` insertion
+    /// ranks at once.
+    ///
+    /// Returns an array of length `N` with the values in the same order as
+    /// `indices`. For soundness, at most one mutable reference will be
+    /// returned to any value. [`None`] will be returned if any of the indices
+    /// are duplicates, out of bounds, or refer to removed entries.
+    ///
+    /// See [`get_nth`](StHashMap::get_nth) for how insertion ranks are
+    /// assigned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// if let Some([a, c]) = map.get_disjoint_indices_mut([0, 2]) {
+    ///     core::mem::swap(a, c);
+    /// }
+    /// assert_eq!(map.get(&"a"), Some(&3));
+    /// assert_eq!(map.get(&"c"), Some(&1));
+    ///
+    /// // Duplicate and out of bounds indices result in None
+    /// assert_eq!(map.get_disjoint_indices_mut([1, 1]), None);
+    /// assert_eq!(map.get_disjoint_indices_mut([1, 3]), None);
+    ///
+    /// // Removed entries result in None
+    /// map.remove(&"a");
+    /// assert_eq!(map.get_disjoint_indices_mut([0, 1]), None);
+    /// ```
+    #[inline]
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn get_disjoint_indices_mut<const N: usize>(
+        &mut self,
+        indices: [usize; N],
+    ) -> Option<[&mut V; N]> {
+        let all_alive = indices
+            .iter()
+            .all(|&n| matches!(self.ordered.get(n), Some(InsertionEntry::Alive(..))));
+        if !all_alive {
+            return None;
+        }
+        let entries = self.ordered.get_disjoint_mut(indices).ok()?;
+        let values = entries.map(|entry| match entry {
+            InsertionEntry::Alive(_, value) => value,
+            InsertionEntry::Dead => panic!("checked pair not alive in ordered storage"),
+        });
+        Some(values)
+    }
+
+    /// Insertion counter for the [first](StHashMap::first) key-value pair in
+    /// the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// assert_eq!(0, map.min_insert_rank());
+    ///
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// assert_eq!(0, map.min_insert_rank());
+    ///
+    /// map.remove(&"a");
+    /// map.insert("b", 100);
+    /// assert_eq!(1, map.min_insert_rank());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn min_insert_rank(&self) -> usize {
+        self.min_insert_rank_opt().unwrap_or_default()
+    }
+
+    /// Insertion counter for the [first](StHashMap::first) key-value pair in
+    /// the map, or [`None`] if the map is empty.
+    ///
+    /// Unlike [`StHashMap::min_insert_rank`], this distinguishes an empty map
+    /// from a map whose first live pair has rank 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// assert_eq!(None, map.min_insert_rank_opt());
+    ///
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// assert_eq!(Some(0), map.min_insert_rank_opt());
+    ///
+    /// map.remove(&"a");
+    /// assert_eq!(Some(1), map.min_insert_rank_opt());
+    ///
+    /// map.remove(&"b");
+    /// assert_eq!(None, map.min_insert_rank_opt());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn min_insert_rank_opt(&self) -> Option<usize> {
+        self.ordered
+            .iter()
+            .position(|pair| matches!(pair, InsertionEntry::Alive(_, _)))
+    }
+
+    /// Insertion counter for the [last](StHashMap::last) key-value pair in the
+    /// map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// assert_eq!(0, map.max_insert_rank());
+    ///
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// assert_eq!(2, map.max_insert_rank());
+    ///
+    /// map.remove(&"a");
+    /// map.insert("b", 100);
+    /// assert_eq!(2, map.max_insert_rank());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn max_insert_rank(&self) -> usize {
+        self.max_insert_rank_opt().unwrap_or_default()
+    }
+
+    /// Insertion counter for the [last](StHashMap::last) key-value pair in the
+    /// map, or [`None`] if the map is empty.
+    ///
+    /// Unlike [`StHashMap::max_insert_rank`], this distinguishes an empty map
+    /// from a map whose last live pair has rank 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// assert_eq!(None, map.max_insert_rank_opt());
+    ///
+    /// map.insert("a", 1);
+    /// assert_eq!(Some(0), map.max_insert_rank_opt());
+    ///
+    /// map.insert("b", 2);
+    /// assert_eq!(Some(1), map.max_insert_rank_opt());
+    ///
+    /// map.remove(&"a");
+    /// map.remove(&"b");
+    /// assert_eq!(None, map.max_insert_rank_opt());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn max_insert_rank_opt(&self) -> Option<usize> {
+        self.ordered
+            .iter()
+            .rposition(|pair| matches!(pair, InsertionEntry::Alive(_, _)))
+    }
+
+    /// Returns the number of times insertion ranks were renumbered.
+    ///
+    /// Ranks are renumbered when dead slots in the insertion list are
+    /// reclaimed and when pairs are moved to other positions, for example by
+    /// [`swap_remove`](StHashMap::swap_remove) or
+    /// [`sort_by`](StHashMap::sort_by). Ranks obtained before a change in this
+    /// counter must not be passed to [`get_nth`](StHashMap::get_nth) or
+    /// [`insert_ranks_from`](StHashMap::insert_ranks_from). This counter
+    /// mirrors `rebuilds_num` in `st.c`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::with_capacity(4);
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.insert("d", 4);
+    /// assert_eq!(0, map.rebuilds());
+    ///
+    /// map.remove(&"a");
+    /// map.remove(&"b");
+    /// map.insert("e", 5);
+    /// assert_eq!(1, map.rebuilds());
+    ///
+    /// assert_eq!(Some((&"c", &3)), map.get_nth(0));
+    /// assert_eq!(Some((&"e", &5)), map.get_nth(2));
+    /// assert_eq!(vec![&"c", &"d", &"e"], map.keys().collect::<Vec<_>>());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn rebuilds(&self) -> usize {
+        self.rebuilds
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// assert_eq!(0, map.len());
+    /// map.insert(1, "a");
+    /// assert_eq!(1, map.len());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// assert!(map.is_empty());
+    /// map.insert(1, "a");
+    /// assert!(!map.is_empty());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Clears the map, removing all key-value pairs. Keeps the allocated memory
+    /// for reuse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert(1, "a");
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// ```
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.ordered.clear();
+    }
+
+    /// Clears the map, returning all key-value pairs as an iterator in
+    /// insertion order. Keeps the allocated memory for reuse.
+    ///
+    /// If the returned iterator is dropped before being fully consumed, it
+    /// drops the remaining key-value pairs. The map is empty either way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::with_capacity(10);
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.remove(&"b");
+    ///
+    /// let drained = map.drain().collect::<Vec<_>>();
+    /// assert_eq!(drained, vec![("a", 1), ("c", 3)]);
+    /// assert!(map.is_empty());
+    /// assert!(map.capacity() >= 10);
+    ///
+    /// map.insert("d", 4);
+    /// map.insert("e", 5);
+    /// assert_eq!(map.drain().next(), Some(("d", 4)));
+    /// assert!(map.is_empty());
+    /// assert_eq!(map.get(&"e"), None);
+    /// ```
+    #[inline]
+    pub fn drain(&mut self) -> Drain<'_, K, V, A> {
+        self.map.clear();
+        Drain(self.ordered.drain(..))
+    }
+
+    /// Consumes the map, returning its key-value pairs in insertion order.
+    ///
+    /// Unlike converting into a [`HashMap`], this conversion is lossless: the
+    /// map can be rebuilt with the same iteration order by collecting the
+    /// returned pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("c", 3);
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.remove(&"a");
+    ///
+    /// let pairs = map.into_vec();
+    /// assert_eq!(pairs, vec![("c", 3), ("b", 2)]);
+    ///
+    /// let map = pairs.into_iter().collect::<StHashMap<_, _>>();
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["c", "b"]);
+    /// ```
+    ///
+    /// [`HashMap`]: std::collections::HashMap
+    #[inline]
+    #[must_use]
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.into_iter().collect()
+    }
+
+    /// Returns `true` if both maps contain the same key-value pairs in the
+    /// same insertion order.
+    ///
+    /// `==` ignores insertion order. This method compares entries pairwise in
+    /// iteration order, so dead slots and the maps' hashers do not affect the
+    /// result.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut forward = StHashMap::new();
+    /// forward.insert("a", 1);
+    /// forward.insert("b", 2);
+    ///
+    /// let mut backward = StHashMap::new();
+    /// backward.insert("b", 2);
+    /// backward.insert("a", 1);
+    ///
+    /// assert_eq!(forward, backward);
+    /// assert!(!forward.eq_ordered(&backward));
+    /// assert!(forward.eq_ordered(&forward.clone()));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn eq_ordered(&self, other: &Self) -> bool
+    where
+        K: PartialEq,
+        V: PartialEq,
+    {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+
+    /// Rebuilds the insertion list without the slots left behind by removed
+    /// entries, releases the memory they occupied, and returns the number of
+    /// slots reclaimed.
+    ///
+    /// Iteration order is unchanged. Insertion ranks are renumbered densely
+    /// starting from zero, so ranks obtained before compacting must not be
+    /// reused. If any slots are reclaimed, [`rebuilds`](StHashMap::rebuilds)
+    /// is incremented.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// for i in 0..100 {
+    ///     map.insert(i, i * 10);
+    /// }
+    /// for i in 0..90 {
+    ///     map.remove(&i);
+    /// }
+    /// let churned = map.estimated_memsize();
+    ///
+    /// assert_eq!(map.compact(), 90);
+    /// assert!(map.estimated_memsize() < churned);
+    /// assert_eq!(map.compact(), 0);
+    ///
+    /// assert_eq!(map.min_insert_rank(), 0);
+    /// assert_eq!(map.get_nth(0), Some((&90, &900)));
+    /// assert_eq!(map.get_nth(9), Some((&99, &990)));
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), (90..100).collect::<Vec<_>>());
+    /// ```
+    #[inline]
+    pub fn compact(&mut self) -> usize {
+        let dead_slots = self.dead_slots();
+        if dead_slots > 0 {
+            self.reclaim_dead_slots();
+            self.ordered.shrink_to_fit();
+        }
+        dead_slots
+    }
+
+    /// Returns the auto-compaction threshold of this map, or `None` if the map
+    /// never compacts itself.
+    ///
+    /// See [`StHashMap::set_compaction_threshold`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let map: StHashMap<i32, i32> = StHashMap::new();
+    /// assert_eq!(map.compaction_threshold(), None);
+    ///
+    /// let map: StHashMap<i32, i32> = StHashMap::with_compaction_threshold(0.25);
+    /// assert_eq!(map.compaction_threshold(), Some(0.25));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn compaction_threshold(&self) -> Option<f64> {
+        self.compaction_threshold
+    }
+
+    /// Sets the auto-compaction threshold of this map.
+    ///
+    /// With `Some(ratio)`, [`remove`](StHashMap::remove) and
+    /// [`remove_entry`](StHashMap::remove_entry) [`compact`] the map whenever
+    /// more than `ratio` of its insertion list slots are dead afterward. This
+    /// bounds the memory held by dead slots under remove-heavy workloads.
+    ///
+    /// Like [`compact`], auto-compaction renumbers insertion ranks, so with a
+    /// threshold set, ranks obtained before a removal must not be reused
+    /// after it.
+    ///
+    /// With `None`, which is the default, the map never compacts itself on
+    /// removal.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ratio` is not between `0.0` and `1.0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// for i in 0..10 {
+    ///     map.insert(i, i);
+    /// }
+    /// map.set_compaction_threshold(Some(0.0));
+    /// map.remove(&4);
+    /// assert_eq!(map.dead_slots(), 0);
+    /// assert_eq!(map.get_nth(4), Some((&5, &5)));
+    ///
+    /// map.set_compaction_threshold(None);
+    /// map.remove(&5);
+    /// assert_eq!(map.dead_slots(), 1);
+    /// ```
+    ///
+    /// [`compact`]: StHashMap::compact
+    #[inline]
+    pub fn set_compaction_threshold(&mut self, ratio: Option<f64>) {
+        if let Some(ratio) = ratio {
+            assert!(
+                (0.0..=1.0).contains(&ratio),
+                "compaction threshold must be between 0.0 and 1.0, got {}",
+                ratio
+            );
+        }
+        self.compaction_threshold = ratio;
+    }
+
+    /// Returns the number of insertion list slots that were vacated by
+    /// removals and not yet reclaimed.
+    ///
+    /// Dead slots are reclaimed by [`compact`](StHashMap::compact),
+    /// [`shrink_to_fit`](StHashMap::shrink_to_fit), and automatically by
+    /// inserts once at least half of a full insertion list is dead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.remove(&"a");
+    /// map.remove(&"c");
+    /// assert_eq!(map.dead_slots(), 2);
+    ///
+    /// map.compact();
+    /// assert_eq!(map.dead_slots(), 0);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn dead_slots(&self) -> usize {
+        self.ordered.len() - self.map.len()
+    }
+
+    /// Returns the fraction of insertion list slots which hold live entries.
+    ///
+    /// This is `1.0` for a map with no dead slots, including an empty map, and
+    /// approaches `0.0` as removals leave dead slots behind. Embedders can use
+    /// it to decide when to call [`compact`](StHashMap::compact).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// assert_eq!(map.load_factor(), 1.0);
+    /// for i in 0..4 {
+    ///     map.insert(i, i);
+    /// }
+    /// map.remove(&0);
+    /// assert_eq!(map.load_factor(), 0.75);
+    ///
+    /// map.compact();
+    /// assert_eq!(map.load_factor(), 1.0);
+    /// ```
+    #[inline]
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn load_factor(&self) -> f64 {
+        if self.ordered.is_empty() {
+            return 1.0;
+        }
+        self.map.len() as f64 / self.ordered.len() as f64
+    }
+
+    // Compact the insertion list if the map opted in to auto-compaction and
+    // too many of its slots are dead.
+    #[inline]
+    #[allow(clippy::cast_precision_loss)]
+    fn compact_if_over_threshold(&mut self) {
+        if let Some(ratio) = self.compaction_threshold {
+            if self.dead_slots() as f64 > ratio * self.ordered.len() as f64 {
+                let _ = self.compact();
+            }
+        }
+    }
+
+    // Dead slots are reclaimed once the insertion list is full and at least
+    // half of it is dead. Reclaiming at this point keeps compaction amortized
+    // O(1) per insert and bounds the insertion list to twice the peak number of
+    // live entries.
+    //
+    // Dead slots are also reclaimed, however few there are, once every rank
+    // that fits in a `Rank` has been handed out.
+    #[inline]
+    fn should_reclaim_dead_slots(&self) -> bool {
+        let dead_slots = self.dead_slots();
+        if dead_slots == 0 {
+            return false;
+        }
+        if self.ordered.len() > max_rank() {
+            return true;
+        }
+        self.ordered.len() == self.ordered.capacity() && dead_slots >= self.map.len()
+    }
+
+    // Rebuild the insertion list without dead slots and rewrite the insertion
+    // rank stored for each key. Relative insertion order is preserved.
+    fn reclaim_dead_slots(&mut self) {
+        let mut new_ranks = Vec::with_capacity(self.ordered.len());
+        let mut next_rank = 0;
+        for entry in &self.ordered {
+            new_ranks.push(next_rank);
+            if let InsertionEntry::Alive(_, _) = entry {
+                next_rank += 1;
+            }
+        }
+        self.ordered
+            .retain(|entry| matches!(entry, InsertionEntry::Alive(_, _)));
+        for insert_rank in self.map.values_mut() {
+            *insert_rank = to_rank(new_ranks[from_rank(*insert_rank)]);
+        }
+        self.renumbered();
+    }
+
+    // Record that insertion ranks were renumbered. See `StHashMap::rebuilds`.
+    #[inline]
+    fn renumbered(&mut self) {
+        self.rebuilds = self.rebuilds.wrapping_add(1);
+    }
+
+    /// Returns a reference to the map's [`BuildHasher`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let hasher = RandomState::new();
+    /// let map: StHashMap<i32, i32, RandomState> = StHashMap::with_hasher(hasher);
+    /// let hasher: &RandomState = map.hasher();
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    /// Return an estimate of the byte size of memory allocted for this map.
+    ///
+    /// The estimate is based on the allocated capacity of the map, including
+    /// slots left behind by removed entries, not only its live entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// let empty: StHashMap<i32, i32> = StHashMap::with_capacity(0);
+    /// let map: StHashMap<i32, i32> = StHashMap::with_capacity(100);
+    /// assert!(map.estimated_memsize() > empty.estimated_memsize());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn estimated_memsize(&self) -> usize {
+        let stack_size = size_of::<Self>();
+        let hashmap_size = (size_of::<K>() + size_of::<Rank>()) * self.map.capacity();
+        let vec_size = size_of::<InsertionEntry<K, V>>() * self.ordered.capacity();
+
+        stack_size + hashmap_size + vec_size
+    }
+
+    /// Consumes the map and returns a new map with each key transformed by
+    /// `f`.
+    ///
+    /// Insertion order is preserved. If `f` maps several keys to equal keys,
+    /// the entry keeps the insertion rank of the earliest of them and the
+    /// value of the latest, as if the pairs were inserted in order with
+    /// [`StHashMap::insert`].
+    ///
+    /// The new map is built with a default hasher and keeps this map's
+    /// allocator and [compaction threshold](StHashMap::set_compaction_threshold).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("banana", 2);
+    /// map.insert("avocado", 3);
+    ///
+    /// let by_initial = map.map_keys(|key| key.chars().next());
+    /// assert_eq!(by_initial.len(), 2);
+    /// assert_eq!(
+    ///     by_initial.into_iter().collect::<Vec<_>>(),
+    ///     vec![(Some('a'), 3), (Some('b'), 2)]
+    /// );
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn map_keys<L, F>(self, mut f: F) -> StHashMap<L, V, S, A>
+    where
+        L: Eq + Hash + Clone,
+        S: Default + BuildHasher,
+        A: Clone,
+        F: FnMut(K) -> L,
+    {
+        let alloc = self.map.allocator().clone();
+        let mut map = StHashMap {
+            map: HashMap::with_capacity_and_hasher_in(self.len(), S::default(), alloc.clone()),
+            ordered: AllocVec::with_capacity_in(self.len(), alloc),
+            rebuilds: 0,
+            compaction_threshold: self.compaction_threshold,
+        };
+        for (key, value) in self {
+            let _ = map.insert(f(key), value);
+        }
+        map
+    }
+}
+
+impl<K, V, S, A: Allocator> StHashMap<K, V, S, A>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Reserves capacity for at least `additional` more elements to be inserted
+    /// in the `StHashMap`. The collection may reserve more space to avoid
+    /// frequent reallocations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new allocation size overflows `usize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// let mut map: StHashMap<&str, i32> = StHashMap::new();
+    /// assert_eq!(0, map.capacity());
+    /// map.reserve(10);
+    /// assert!(map.capacity() >= 10);
+    /// ```
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional);
+        self.ordered.reserve(additional);
+    }
+
+    /// Reserves the minimum capacity for at least `additional` more elements to
+    /// be inserted in the `StHashMap`.
+    ///
+    /// Unlike [`reserve`](StHashMap::reserve), the insertion list does not
+    /// deliberately over-allocate, which avoids wasted capacity when the final
+    /// size of the map is known. The hash table has no exact reservation API,
+    /// so it may still reserve more space than requested.
+    ///
+    /// Dead slots left behind by removals occupy the insertion list, so they
+    /// count against the reserved capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new allocation size overflows `usize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// let mut map: StHashMap<&str, i32> = StHashMap::new();
+    /// map.reserve_exact(10);
+    /// assert!(map.capacity() >= 10);
+    /// assert_eq!(map.ordered_capacity(), 10);
+    /// ```
+    #[inline]
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.map.reserve(additional);
+        self.ordered.reserve_exact(additional);
+    }
+
+    /// Tries to reserve capacity for at least `additional` more elements to be
+    /// inserted in the `StHashMap`. The collection may reserve more space to
+    /// avoid frequent reallocations.
+    ///
+    /// If reserving space in the insertion list fails after the hash table has
+    /// grown, the hash table is shrunk back to its previous capacity. The
+    /// contents of the map are unchanged either way.
+    ///
+    /// # Errors
+    ///
+    /// If the capacity overflows, or the allocator reports a failure, then an
+    /// error is returned. Reserving space for more than `u32::MAX + 1` entries
+    /// is a capacity overflow. See [`StHashMap`] for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map: StHashMap<&str, i32> = StHashMap::new();
+    /// map.try_reserve(10).expect("why is the test harness OOMing on 10 elements?");
+    /// assert!(map.capacity() >= 10);
+    ///
+    /// map.insert("a", 1);
+    /// assert!(map.try_reserve(usize::MAX).is_err());
+    /// assert_eq!(map.len(), 1);
+    /// assert_eq!(map.get(&"a"), Some(&1));
+    /// ```
+    #[inline]
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        // Dead slots are reclaimed before the insertion list runs out of
+        // ranks, so only live entries count towards the limit.
+        match self.len().checked_add(additional) {
+            Some(len) if len.saturating_sub(1) <= max_rank() => {}
+            _ => return Err(TryReserveError::CapacityOverflow),
+        }
+        let map_capacity = self.map.capacity();
+        self.map.try_reserve(additional)?;
+        if let Err(err) = self.ordered.try_reserve(additional) {
+            self.map.shrink_to(map_capacity);
+            return Err(ordered_reserve_error(&err));
+        }
+        Ok(())
+    }
+
+    /// Shrinks the capacity of the map as much as possible. It will drop down
+    /// as much as possible while maintaining the internal rules and possibly
+    /// leaving some space in accordance with the resize policy.
+    ///
+    /// Slots left behind by removed entries are reclaimed first, as with
+    /// [`StHashMap::compact`], so that their memory can be released. This
+    /// renumbers insertion ranks, so ranks obtained before shrinking must not
+    /// be reused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// let mut map: StHashMap<i32, i32> = StHashMap::with_capacity(100);
+    /// map.insert(1, 2);
+    /// map.insert(3, 4);
+    /// assert!(map.capacity() >= 100);
+    /// map.shrink_to_fit();
+    /// assert!(map.capacity() >= 2);
+    ///
+    /// map.remove(&1);
+    /// map.shrink_to_fit();
+    /// assert_eq!(map.get_nth(0), Some((&3, &4)));
+    /// ```
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        if self.dead_slots() > 0 {
+            self.reclaim_dead_slots();
+        }
+        self.map.shrink_to_fit();
+        self.ordered.shrink_to_fit();
+    }
+
+    /// Shrinks the capacity of the map with a lower limit. It will drop down no
+    /// lower than the supplied limit while maintaining the internal rules and
+    /// possibly leaving some space in accordance with the resize policy.
+    ///
+    /// If the current capacity is less than the lower limit, this is a no-op.
+    /// The capacity never drops below the number of slots in use, including
+    /// slots left behind by removed entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    /// let mut map: StHashMap<i32, i32> = StHashMap::with_capacity(100);
+    /// map.insert(1, 2);
+    /// map.insert(3, 4);
+    /// assert!(map.capacity() >= 100);
+    /// map.shrink_to(10);
+    /// assert!(map.capacity() >= 10);
+    /// map.shrink_to(0);
+    /// assert!(map.capacity() >= 2);
+    /// ```
+    #[inline]
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.map.shrink_to(min_capacity);
+        self.ordered.shrink_to(min_capacity);
+    }
+
+    /// Clears the map, removing all key-value pairs, and releases the
+    /// allocated memory.
+    ///
+    /// Unlike [`clear`](StHashMap::clear), which keeps the allocated memory
+    /// for reuse, the capacity of the map drops to 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = (0..100).map(|i| (i, i)).collect::<StHashMap<_, _>>();
+    /// map.clear();
+    /// assert!(map.capacity() >= 100);
+    ///
+    /// map.insert(1, 1);
+    /// map.clear_and_shrink();
+    /// assert!(map.is_empty());
+    /// assert_eq!(map.capacity(), 0);
+    /// ```
+    #[inline]
+    pub fn clear_and_shrink(&mut self) {
+        self.clear();
+        self.map.shrink_to_fit();
+        self.ordered.shrink_to_fit();
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all pairs `(k, v)` such that `f(&k, &mut v)`
+    /// returns `false`. The elements are visited in insertion order.
+    ///
+    /// Insertion ranks of retained elements are not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// for i in 0..10 {
+    ///     map.insert(i, i * 10);
+    /// }
+    /// map.retain(|&k, _| k % 2 == 0);
+    ///
+    /// assert_eq!(map.len(), 5);
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec![0, 2, 4, 6, 8]);
+    /// assert_eq!(map.get_nth(1), None);
+    /// assert_eq!(map.get_nth(2), Some((&2, &20)));
+    /// assert_eq!(map.get(&3), None);
+    /// assert_eq!(map.get(&4), Some(&40));
+    /// ```
+    #[inline]
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        for entry in &mut self.ordered {
+            if let InsertionEntry::Alive(key, value) = entry {
+                if !f(key, value) {
+                    self.map.remove(key);
+                    *entry = InsertionEntry::Dead;
+                }
+            }
+        }
+    }
+
+    /// Retains only the elements specified by the predicate, which may also
+    /// mutate the values of retained elements.
+    ///
+    /// This is equivalent to [`StHashMap::retain`], which also passes each
+    /// value mutably. It is provided for parity with [`Vec::retain_mut`].
+    /// Mutations are written to the map in place and insertion ranks of
+    /// retained elements are not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut ttls = StHashMap::new();
+    /// ttls.insert("a", 2);
+    /// ttls.insert("b", 1);
+    /// ttls.insert("c", 3);
+    ///
+    /// ttls.retain_mut(|_, ttl| {
+    ///     *ttl -= 1;
+    ///     *ttl > 0
+    /// });
+    /// assert_eq!(ttls.iter().collect::<Vec<_>>(), vec![(&"a", &1), (&"c", &2)]);
+    /// ```
+    ///
+    /// [`Vec::retain_mut`]: alloc::vec::Vec::retain_mut
+    #[inline]
+    pub fn retain_mut<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.retain(f);
+    }
+
+    /// Creates an iterator which uses a closure to determine if an element
+    /// should be removed.
+    ///
+    /// If the closure returns `true`, the element is removed from the map and
+    /// yielded. If the closure returns `false`, the element remains in the map
+    /// and is not yielded. Elements are visited in insertion order and the
+    /// closure may mutate the values of elements it keeps.
+    ///
+    /// Removals happen as the iterator advances and leave dead slots like
+    /// [`StHashMap::remove`], so insertion ranks of retained elements are not
+    /// changed. If the iterator is dropped before being fully consumed, the
+    /// elements it already yielded stay removed and the remaining elements are
+    /// kept. Use [`StHashMap::retain`] with a negated predicate if you do not
+    /// need the removed elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = (0..8).map(|i| (i, i * 10)).collect::<StHashMap<_, _>>();
+    /// let evens = map.extract_if(|k, _| k % 2 == 0).collect::<Vec<_>>();
+    /// assert_eq!(evens, vec![(0, 0), (2, 20), (4, 40), (6, 60)]);
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec![1, 3, 5, 7]);
+    /// ```
+    #[inline]
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, F, S, A>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        ExtractIf {
+            map: self,
+            rank: 0,
+            pred,
+        }
+    }
+
+    /// Sorts the map's key-value pairs by key.
+    ///
+    /// Dead slots left by removals are dropped and insertion ranks are
+    /// renumbered densely in the new order, so ranks obtained before sorting
+    /// must not be reused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// for i in [3, 9, 1, 7, 5] {
+    ///     map.insert(i, i * 10);
+    /// }
+    /// map.remove(&7);
+    /// map.sort_keys();
+    ///
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec![1, 3, 5, 9]);
+    /// assert_eq!(map.first(), Some((&1, &10)));
+    /// assert_eq!(map.last(), Some((&9, &90)));
+    /// assert_eq!(map.get_nth(2), Some((&5, &50)));
+    /// ```
+    #[inline]
+    pub fn sort_keys(&mut self)
+    where
+        K: Ord,
+        A: Clone,
+    {
+        self.sort_by(|(left, _), (right, _)| left.cmp(right));
+    }
+
+    /// Sorts the map's key-value pairs in place using the comparison function
+    /// `cmp`.
+    ///
+    /// The sort is stable. Dead slots left by removals are dropped and
+    /// insertion ranks are renumbered densely in the new order, so ranks
+    /// obtained before sorting must not be reused. See
+    /// [`StHashMap::rebuilds`].
+    ///
+    /// If `cmp` panics, the map is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 3);
+    /// map.insert("b", 1);
+    /// map.insert("c", 2);
+    /// map.sort_by(|(_, left), (_, right)| right.cmp(left));
+    ///
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["a", "c", "b"]);
+    /// assert_eq!(map.get_nth(0), Some((&"a", &3)));
+    /// assert_eq!(map.get(&"b"), Some(&1));
+    /// ```
+    #[inline]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn sort_by<F>(&mut self, mut cmp: F)
+    where
+        F: FnMut((&K, &V), (&K, &V)) -> Ordering,
+        A: Clone,
+    {
+        // Sort the live insertion ranks rather than the pairs so a panicking
+        // `cmp` leaves both stores untouched.
+        let mut sorted_ranks = self
+            .ordered
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| matches!(entry, InsertionEntry::Alive(_, _)))
+            .map(|(insert_rank, _)| insert_rank)
+            .collect::<Vec<_>>();
+        let ordered = &self.ordered;
+        sorted_ranks.sort_by(|&left, &right| match (&ordered[left], &ordered[right]) {
+            (InsertionEntry::Alive(lk, lv), InsertionEntry::Alive(rk, rv)) => {
+                cmp((lk, lv), (rk, rv))
+            }
+            _ => panic!("sorted pair not alive in ordered storage"),
+        });
+
+        // Rewrite ranks by position rather than by key so no user `Hash` or
+        // `Eq` impl runs while the two stores disagree.
+        let mut new_ranks = alloc::vec![0; self.ordered.len()];
+        for (new_rank, &old_rank) in sorted_ranks.iter().enumerate() {
+            new_ranks[old_rank] = new_rank;
+        }
+        let capacity = self.ordered.capacity();
+        let alloc = self.ordered.allocator().clone();
+        let mut old = mem::replace(
+            &mut self.ordered,
+            AllocVec::with_capacity_in(capacity, alloc),
+        );
+        self.ordered.extend(
+            sorted_ranks
+                .iter()
+                .map(|&old_rank| mem::replace(&mut old[old_rank], InsertionEntry::Dead)),
+        );
+        for insert_rank in self.map.values_mut() {
+            *insert_rank = to_rank(new_ranks[from_rank(*insert_rank)]);
+        }
+        self.renumbered();
+    }
+
+    /// Swaps the positions of the pairs at positions `a` and `b`.
+    ///
+    /// Positions count live pairs in insertion order. Dead slots left by
+    /// removals are reclaimed first, so after this call positions are also
+    /// insertion ranks and insertion ranks obtained before calling this method
+    /// must not be reused. See [`StHashMap::rebuilds`]. Without dead slots,
+    /// this is O(1).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` or `b` is not less than the length of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// map.swap_indices(0, 2);
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["c", "b", "a"]);
+    /// assert_eq!(map.get_index_of(&"a"), Some(2));
+    /// ```
+    #[inline]
+    pub fn swap_indices(&mut self, a: usize, b: usize) {
+        let len = self.len();
+        assert!(
+            a < len && b < len,
+            "index out of bounds: the len is {} but the indices are {} and {}",
+            len,
+            a,
+            b
+        );
+        if self.dead_slots() > 0 {
+            self.reclaim_dead_slots();
+        }
+        if a != b {
+            self.ordered.swap(a, b);
+            self.reindex(a..a + 1);
+            self.reindex(b..b + 1);
+            self.renumbered();
+        }
+    }
+
+    /// Moves the pair at position `from` to position `to`, shifting the pairs
+    /// in between by one position.
+    ///
+    /// Positions count live pairs in insertion order. Dead slots left by
+    /// removals are reclaimed first, so after this call positions are also
+    /// insertion ranks and insertion ranks obtained before calling this method
+    /// must not be reused. See [`StHashMap::rebuilds`]. This is O(n) in the
+    /// distance moved.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` or `to` is not less than the length of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// for key in ["a", "b", "c", "d"] {
+    ///     map.insert(key, ());
+    /// }
+    ///
+    /// map.move_index(0, 2);
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["b", "c", "a", "d"]);
+    /// map.move_index(3, 0);
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["d", "b", "c", "a"]);
+    /// assert_eq!(map.get_index_of(&"d"), Some(0));
+    /// ```
+    #[inline]
+    pub fn move_index(&mut self, from: usize, to: usize) {
+        let len = self.len();
+        assert!(
+            from < len && to < len,
+            "index out of bounds: the len is {} but the indices are {} and {}",
+            len,
+            from,
+            to
+        );
+        if self.dead_slots() > 0 {
+            self.reclaim_dead_slots();
+        }
+        self.move_rank(from, to);
+    }
+
+    // Move the pair at insertion rank `from` to rank `to`, shifting the pairs
+    // in between by one rank toward `from`.
+    fn move_rank(&mut self, from: usize, to: usize) {
+        if from < to {
+            self.ordered[from..=to].rotate_left(1);
+            self.reindex(from..to + 1);
+            self.renumbered();
+        } else if to < from {
+            self.ordered[to..=from].rotate_right(1);
+            self.reindex(to..from + 1);
+            self.renumbered();
+        }
+    }
+
+    // Rewrite the insertion rank stored in the hash table for the pairs at
+    // `insert_ranks` after they have been moved within the insertion list.
+    fn reindex(&mut self, insert_ranks: Range<usize>) {
+        for insert_rank in insert_ranks {
+            if let InsertionEntry::Alive(key, _) = &self.ordered[insert_rank] {
+                match self.map.get_mut(key) {
+                    Some(rank) => *rank = to_rank(insert_rank),
+                    None => panic!("moved pair not present in hash table"),
+                }
+            }
+        }
+    }
+
+    /// Gets the given key's corresponding entry in the map for in-place
+    /// manipulation.
+    ///
+    /// Values inserted through a vacant entry are ordered after all existing
+    /// entries, the same as [`StHashMap::insert`]. Getting the entry for a new
+    /// key may reclaim dead slots left by removals, which renumbers insertion
+    /// ranks. See [`StHashMap::rebuilds`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut letters = StHashMap::new();
+    ///
+    /// for ch in "a short treatise on fungi".chars() {
+    ///     letters.entry(ch).and_modify(|counter| *counter += 1).or_insert(1);
+    /// }
+    ///
+    /// assert_eq!(letters[&'s'], 2);
+    /// assert_eq!(letters[&'t'], 3);
+    /// assert_eq!(letters[&'u'], 1);
+    /// assert_eq!(letters.get(&'y'), None);
+    /// assert_eq!(letters.keys().collect::<String>(), "a shorteinfug");
+    ///
+    /// letters.entry('y').or_insert_with_key(|&ch| ch as u32);
+    /// assert_eq!(letters.last(), Some((&'y', &121)));
+    /// assert_eq!(letters.first(), Some((&'a', &2)));
+    /// ```
+    #[inline]
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S, A> {
+        if self.should_reclaim_dead_slots() && !self.map.contains_key(&key) {
+            self.reclaim_dead_slots();
+        }
+        match self.map.entry(key) {
+            HashEntry::Occupied(base) => Entry::Occupied(OccupiedEntry {
+                base,
+                ordered: &mut self.ordered,
+            }),
+            HashEntry::Vacant(base) => Entry::Vacant(VacantEntry {
+                base,
+                ordered: &mut self.ordered,
+            }),
+        }
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but [`Hash`] and
+    /// [`Eq`] on the borrowed form *must* match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&2), false);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.get_key_value(key).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but [`Hash`] and
+    /// [`Eq`] on the borrowed form *must* match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.get(&1), Some(&"a"));
+    /// assert_eq!(map.get(&2), None);
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert(String::from("key"), 1);
+    /// assert_eq!(map.get("key"), Some(&1));
+    /// assert_eq!(map.get("missing"), None);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let (_, value) = self.get_key_value(key)?;
+        Some(value)
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    ///
+    /// The insertion rank of the key-value pair is not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert(1, "a");
+    /// if let Some(x) = map.get_mut(&1) {
+    ///     *x = "b";
+    /// }
+    /// assert_eq!(map[&1], "b");
+    /// assert_eq!(map.get_mut(&2), None);
+    /// ```
+    #[inline]
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let insert_rank = from_rank(*self.map.get(key)?);
+        match &mut self.ordered[insert_rank] {
+            InsertionEntry::Alive(_, value) => Some(value),
+            InsertionEntry::Dead => panic!("found pair not alive in ordered storage"),
+        }
+    }
+
+    /// Returns the stored key and a mutable reference to the value
+    /// corresponding to the supplied key.
+    ///
+    /// The returned key is the instance stored in the map, which may differ
+    /// from the supplied key in ways its [`Eq`] implementation ignores. The
+    /// insertion rank of the key-value pair is not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert(1, "a");
+    /// if let Some((key, value)) = map.get_key_value_mut(&1) {
+    ///     assert_eq!(key, &1);
+    ///     *value = "b";
+    /// }
+    /// assert_eq!(map[&1], "b");
+    /// assert_eq!(map.get_key_value_mut(&2), None);
+    /// ```
+    #[inline]
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn get_key_value_mut<Q>(&mut self, key: &Q) -> Option<(&K, &mut V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let insert_rank = from_rank(*self.map.get(key)?);
+        match &mut self.ordered[insert_rank] {
+            InsertionEntry::Alive(key, value) => Some((&*key, value)),
+            InsertionEntry::Dead => panic!("found pair not alive in ordered storage"),
+        }
+    }
+
+    /// Attempts to get mutable references to `N` values in the map at once.
+    ///
+    /// Returns an array of length `N` with the results of each query. For
+    /// soundness, at most one mutable reference will be returned to any value.
+    /// [`None`] will be returned if any of the keys are duplicates or missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut libraries = StHashMap::new();
+    /// libraries.insert("Bodleian Library", 1602);
+    /// libraries.insert("Athenæum", 1807);
+    /// libraries.insert("Herzogin-Anna-Amalia-Bibliothek", 1691);
+    /// libraries.insert("Library of Congress", 1800);
+    ///
+    /// let got = libraries.get_many_mut([&"Athenæum", &"Library of Congress"]);
+    /// assert_eq!(got, Some([&mut 1807, &mut 1800]));
+    ///
+    /// // Missing keys result in None
+    /// let got = libraries.get_many_mut([&"Athenæum", &"New York Public Library"]);
+    /// assert_eq!(got, None);
+    ///
+    /// // Duplicate keys result in None
+    /// let got = libraries.get_many_mut([&"Athenæum", &"Athenæum"]);
+    /// assert_eq!(got, None);
+    ///
+    /// // The returned references can be mutated independently
+    /// if let Some([bodleian, congress]) =
+    ///     libraries.get_many_mut([&"Bodleian Library", &"Library of Congress"])
+    /// {
+    ///     core::mem::swap(bodleian, congress);
+    ///     *congress += 1;
+    /// }
+    /// assert_eq!(libraries.get(&"Bodleian Library"), Some(&1800));
+    /// assert_eq!(libraries.get(&"Library of Congress"), Some(&1603));
+    /// ```
+    #[inline]
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn get_many_mut<const N: usize>(&mut self, keys: [&K; N]) -> Option<[&mut V; N]> {
+        let mut insert_ranks = [0; N];
+        for (insert_rank, key) in insert_ranks.iter_mut().zip(keys) {
+            *insert_rank = from_rank(*self.map.get(key)?);
+        }
+        // Equal keys share an insertion rank, so disjoint ranks guarantee that
+        // no value is borrowed mutably more than once.
+        let entries = self.ordered.get_disjoint_mut(insert_ranks).ok()?;
+        let values = entries.map(|entry| match entry {
+            InsertionEntry::Alive(_, value) => value,
+            InsertionEntry::Dead => panic!("inserted pair not alive in ordered storage"),
+        });
+        Some(values)
+    }
+
+    /// Returns the key-value pair corresponding to the supplied key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.get_key_value(&1), Some((&1, &"a")));
+    /// assert_eq!(map.get_key_value(&2), None);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        if self.ordered.len() < LINEAR_SCAN_THRESHOLD {
+            self.iter()
+                .find(|&(entry_key, _)| entry_key.borrow() == key)
+        } else {
+            let &insert_rank = self.map.get(key)?;
+            self.get_nth(from_rank(insert_rank))
+        }
+    }
+
+    /// Returns the insertion rank and key-value pair corresponding to the
+    /// supplied key.
+    ///
+    /// The returned insertion rank can be passed to
+    /// [`get_nth`](StHashMap::get_nth). Insertion ranks do not change when
+    /// other entries are removed, but inserting a new key may renumber them.
+    /// See [`StHashMap::rebuilds`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// assert_eq!(map.get_full(&"c"), Some((2, &"c", &3)));
+    /// assert_eq!(map.get_full(&"d"), None);
+    ///
+    /// map.remove(&"a");
+    /// let (rank, key, value) = map.get_full(&"c").unwrap();
+    /// assert_eq!(rank, 2);
+    /// assert_eq!(map.get_nth(rank), Some((key, value)));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn get_full<Q>(&self, key: &Q) -> Option<(usize, &K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let insert_rank = self.get_index_of(key)?;
+        let (key, value) = self.get_nth(insert_rank)?;
+        Some((insert_rank, key, value))
+    }
+
+    /// Returns the insertion rank of the supplied key.
+    ///
+    /// This is the reverse of [`get_nth`](StHashMap::get_nth). Insertion ranks
+    /// do not shift when other entries are removed, but inserting a new key
+    /// may renumber them. See [`StHashMap::rebuilds`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// assert_eq!(map.get_index_of(&"b"), Some(1));
+    /// assert_eq!(map.get_index_of(&"d"), None);
+    ///
+    /// map.remove(&"a");
+    /// assert_eq!(map.get_index_of(&"a"), None);
+    /// assert_eq!(map.get_index_of(&"b"), Some(1));
+    /// assert_eq!(map.get_index_of(&"c"), Some(2));
+    /// assert_eq!(map.get_nth(2), Some((&"c", &3)));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn get_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.map.get(key).copied().map(from_rank)
+    }
+
+    /// Removes a key from the map, returning the stored key if the key was
+    /// previously in the map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but [`Hash`] and
+    /// [`Eq`] on the borrowed form *must* match those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.remove(&1), Some("a"));
+    /// assert_eq!(map.remove(&1), None);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Removes a key from the map, returning the stored key and value if the
+    /// key was previously in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.remove_entry(&1), Some((1, "a")));
+    /// assert_eq!(map.remove(&1), None);
+    /// ```
+    #[inline]
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let insert_rank = from_rank(self.map.remove(key)?);
+        let entry = match mem::replace(&mut self.ordered[insert_rank], InsertionEntry::Dead) {
+            InsertionEntry::Alive(key, value) => (key, value),
+            InsertionEntry::Dead => panic!("removed pair not alive in ordered storage"),
+        };
+        self.compact_if_over_threshold();
+        Some(entry)
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key
+    /// was previously in the map.
+    ///
+    /// Unlike [`remove`](StHashMap::remove), this does not leave a dead slot
+    /// behind. The last entry in insertion order is moved into the position of
+    /// the removed entry and takes over its insertion rank. This perturbs the
+    /// order of that one entry, but is O(1).
+    ///
+    /// Moving the last entry renumbers its insertion rank. See
+    /// [`StHashMap::rebuilds`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.insert("d", 4);
+    ///
+    /// assert_eq!(map.swap_remove(&"b"), Some(2));
+    /// assert_eq!(map.swap_remove(&"b"), None);
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["a", "d", "c"]);
+    /// assert_eq!(map.get_nth(1), Some((&"d", &4)));
+    /// assert_eq!(map.get(&"d"), Some(&4));
+    /// ```
+    #[inline]
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn swap_remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let insert_rank = from_rank(self.map.remove(key)?);
+        // Drop trailing dead slots so the entry swapped into `insert_rank` is
+        // the last live entry. The removed entry is still alive in `ordered`,
+        // which bounds this loop.
+        while let Some(InsertionEntry::Dead) = self.ordered.last() {
+            self.ordered.pop();
+        }
+        let removed = self.ordered.swap_remove(insert_rank);
+        if let Some(InsertionEntry::Alive(moved_key, _)) = self.ordered.get(insert_rank) {
+            match self.map.get_mut::<K>(moved_key) {
+                Some(moved_rank) => *moved_rank = to_rank(insert_rank),
+                None => panic!("swapped pair not present in hash table"),
+            }
+            self.renumbered();
+        }
+        match removed {
+            InsertionEntry::Alive(_, value) => Some(value),
+            InsertionEntry::Dead => panic!("removed pair not alive in ordered storage"),
+        }
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key
+    /// was previously in the map.
+    ///
+    /// Unlike [`remove`](StHashMap::remove), this does not leave a dead slot
+    /// behind. All later entries are shifted down by one insertion rank,
+    /// preserving their relative order. This is O(n).
+    ///
+    /// Shifting later entries renumbers their insertion ranks. See
+    /// [`StHashMap::rebuilds`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.insert("d", 4);
+    ///
+    /// assert_eq!(map.shift_remove(&"b"), Some(2));
+    /// assert_eq!(map.shift_remove(&"b"), None);
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["a", "c", "d"]);
+    /// assert_eq!(map.get_nth(1), Some((&"c", &3)));
+    /// assert_eq!(map.get_nth(2), Some((&"d", &4)));
+    /// assert_eq!(map.get(&"d"), Some(&4));
+    /// ```
+    #[inline]
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn shift_remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let removed_rank = self.map.remove(key)?;
+        let insert_rank = from_rank(removed_rank);
+        let removed = self.ordered.remove(insert_rank);
+        if insert_rank < self.ordered.len() {
+            for rank in self.map.values_mut() {
+                if *rank > removed_rank {
+                    *rank -= 1;
+                }
+            }
+            self.renumbered();
+        }
+        match removed {
+            InsertionEntry::Alive(_, value) => Some(value),
+            InsertionEntry::Dead => panic!("removed pair not alive in ordered storage"),
+        }
+    }
+
+    /// Removes the last key-value pair in insertion order and returns it, or
+    /// [`None`] if the map is empty.
+    ///
+    /// This is the pair reported by [`last`](StHashMap::last). Dead slots at
+    /// the end of the insertion list are dropped, so popping is amortized O(1).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.remove(&"c");
+    ///
+    /// assert_eq!(map.pop(), Some(("b", 2)));
+    /// assert_eq!(map.pop(), Some(("a", 1)));
+    /// assert_eq!(map.pop(), None);
+    /// assert!(map.is_empty());
+    /// ```
+    #[inline]
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        loop {
+            match self.ordered.pop()? {
+                InsertionEntry::Dead => {}
+                InsertionEntry::Alive(key, value) => {
+                    assert!(
+                        self.map.remove(&key).is_some(),
+                        "popped pair not present in hash table"
+                    );
+                    while let Some(InsertionEntry::Dead) = self.ordered.last() {
+                        self.ordered.pop();
+                    }
+                    return Some((key, value));
+                }
+            }
+        }
+    }
+
+    /// Shortens the map, keeping the first `len` key-value pairs in insertion
+    /// order and dropping the rest.
+    ///
+    /// Dead slots left by removals do not count toward `len`. If `len` is
+    /// greater than or equal to the map's current length, this has no effect.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// for i in 0..6 {
+    ///     map.insert(i, i * 10);
+    /// }
+    /// map.remove(&1);
+    /// map.remove(&2);
+    ///
+    /// map.truncate(3);
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec![0, 3, 4]);
+    /// assert_eq!(map.get(&5), None);
+    /// assert_eq!(map.last(), Some((&4, &40)));
+    ///
+    /// map.truncate(10);
+    /// assert_eq!(map.len(), 3);
+    /// ```
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.map.len() {
+            return;
+        }
+        let mut live = 0;
+        let mut split = self.ordered.len();
+        for (insert_rank, entry) in self.ordered.iter().enumerate() {
+            if let InsertionEntry::Alive(_, _) = entry {
+                if live == len {
+                    split = insert_rank;
+                    break;
+                }
+                live += 1;
+            }
+        }
+        for entry in self.ordered.drain(split..) {
+            if let InsertionEntry::Alive(key, _) = entry {
+                self.map.remove(&key);
+            }
+        }
+        while let Some(InsertionEntry::Dead) = self.ordered.last() {
+            self.ordered.pop();
+        }
+    }
+}
+
+impl<K, V, S, A: Allocator> StHashMap<K, V, S, A>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the map did not have this key present, [`None`] is returned.
+    ///
+    /// If the map did have this key present, the value is updated, and the old
+    /// value is returned. The key is not updated, though. To update the key
+    /// in-place, use [`StHashMap::update`].
+    ///
+    /// Inserting a new key may reclaim dead slots left by removals, which
+    /// renumbers insertion ranks. See [`StHashMap::rebuilds`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the map and the map already holds
+    /// `u32::MAX + 1` entries. See [`StHashMap`] for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::env;
+    /// use std::fs::File;
+    /// use strudel::StHashMap;
+    ///
+    /// // Values need not be `Clone`.
+    /// let mut map: StHashMap<String, File> = StHashMap::new();
+    /// let file = File::open(env::current_exe()?)?;
+    /// assert!(map.insert(String::from("exe"), file).is_none());
+    ///
+    /// let file = File::open(env::current_exe()?)?;
+    /// assert!(map.insert(String::from("exe"), file).is_some());
+    /// assert_eq!(map.len(), 1);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let (_, old_value) = self.insert_full(key, value);
+        old_value
+    }
+
+    /// Inserts a key-value pair into the map and returns its insertion rank.
+    ///
+    /// If the map did not have this key present, the pair is ordered after all
+    /// existing entries and [`None`] is returned with its new insertion rank.
+    ///
+    /// If the map did have this key present, the value is updated, and the
+    /// insertion rank of the existing entry is returned with the old value.
+    /// The key is not updated.
+    ///
+    /// The returned insertion rank can be passed to
+    /// [`get_nth`](StHashMap::get_nth). Inserting a new key may reclaim dead
+    /// slots left by removals, which renumbers insertion ranks. See
+    /// [`StHashMap::rebuilds`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the map and the map already holds
+    /// `u32::MAX + 1` entries. See [`StHashMap`] for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// assert_eq!(map.insert_full("a", 1), (0, None));
+    /// assert_eq!(map.insert_full("b", 2), (1, None));
+    ///
+    /// map.remove(&"a");
+    /// assert_eq!(map.insert_full("b", 20), (1, Some(2)));
+    /// assert_eq!(map.insert_full("c", 3), (2, None));
+    /// assert_eq!(map.get_nth(2), Some((&"c", &3)));
+    /// ```
+    #[inline]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn insert_full(&mut self, key: K, value: V) -> (usize, Option<V>) {
+        let reclaim = self.should_reclaim_dead_slots();
+        match self.map.entry(key) {
+            HashEntry::Occupied(base) => {
+                let insert_rank = from_rank(*base.get());
+                // Maintain insert rank with new key-value pair.
+                if let Some(InsertionEntry::Alive(_, stored_value)) =
+                    self.ordered.get_mut(insert_rank)
+                {
+                    (insert_rank, Some(mem::replace(stored_value, value)))
+                } else {
+                    panic!("already inserted pair not alive in ordered storage");
+                }
+            }
+            HashEntry::Vacant(base) if reclaim => {
+                let key = base.into_key();
+                self.reclaim_dead_slots();
+                let insert_rank = self.ordered.len();
+                let rank = to_rank(insert_rank);
+                self.ordered.push(InsertionEntry::Alive(key.clone(), value));
+                self.map.insert(key, rank);
+                (insert_rank, None)
+            }
+            HashEntry::Vacant(base) => {
+                let insert_rank = self.ordered.len();
+                let rank = to_rank(insert_rank);
+                self.ordered
+                    .push(InsertionEntry::Alive(base.key().clone(), value));
+                base.insert(rank);
+                (insert_rank, None)
+            }
+        }
+    }
+
+    /// Inserts a key-value pair into the map and update the key in place if an
+    /// entry is already present.
+    ///
+    /// This function maintains the insertion rank of the key-value pair.
+    ///
+    /// If you do not wish to update the key in-place, use
+    /// [`StHashMap::insert`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the map and the map already holds
+    /// `u32::MAX + 1` entries. See [`StHashMap`] for this limit.
+    #[allow(clippy::missing_panics_doc)]
+    #[inline]
+    pub fn update(&mut self, key: K, value: V) {
+        if let Some((_, insert_rank)) = self.map.remove_entry(&key) {
+            // Maintain insert rank with new key-value pair.
+            if let Some(entry @ InsertionEntry::Alive(_, _)) =
+                self.ordered.get_mut(from_rank(insert_rank))
+            {
+                *entry = InsertionEntry::Alive(key.clone(), value);
+            } else {
+                panic!("already inserted pair not alive in ordered storage");
+            }
+            self.map.insert(key, insert_rank);
+        } else {
+            let _ = self.insert(key, value);
+        }
+    }
+
+    /// Returns a new map with the entries for which `f` returns [`Some`],
+    /// mapped to the returned values.
+    ///
+    /// Surviving entries keep their relative insertion order. The new map
+    /// clones this map's hasher and allocator and keeps its
+    /// [compaction threshold](StHashMap::set_compaction_threshold).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", "1");
+    /// map.insert("b", "two");
+    /// map.insert("c", "3");
+    ///
+    /// let numbers = map.filter_map(|_, value| value.parse::<i32>().ok());
+    /// assert_eq!(numbers.into_iter().collect::<Vec<_>>(), vec![("a", 1), ("c", 3)]);
+    /// assert_eq!(map.len(), 3);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn filter_map<W, F>(&self, mut f: F) -> StHashMap<K, W, S, A>
+    where
+        S: Clone,
+        A: Clone,
+        F: FnMut(&K, &V) -> Option<W>,
+    {
+        let alloc = self.map.allocator().clone();
+        let mut map = StHashMap {
+            map: HashMap::with_hasher_in(self.hasher().clone(), alloc.clone()),
+            ordered: AllocVec::new_in(alloc),
+            rebuilds: 0,
+            compaction_threshold: self.compaction_threshold,
+        };
+        for (key, value) in self {
+            if let Some(value) = f(key, value) {
+                let _ = map.insert(key.clone(), value);
+            }
+        }
+        map
+    }
+
+    /// Replaces the key `old` with `new`, keeping the value and insertion rank
+    /// of the key-value pair.
+    ///
+    /// Unlike [`StHashMap::update`], `new` need not be equal to `old` and may
+    /// hash differently. Returns `false` and leaves the map unchanged if `old`
+    /// is not present or `new` is already present, including when `new` is
+    /// equal to `old`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// assert!(map.rename_key(&"b", "z"));
+    /// assert_eq!(map.get(&"b"), None);
+    /// assert_eq!(map.get(&"z"), Some(&2));
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["a", "z", "c"]);
+    ///
+    /// assert!(!map.rename_key(&"b", "y"));
+    /// assert!(!map.rename_key(&"a", "c"));
+    /// ```
+    #[inline]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn rename_key(&mut self, old: &K, new: K) -> bool {
+        if self.map.contains_key(&new) {
+            return false;
+        }
+        let Some(insert_rank) = self.map.remove(old) else {
+            return false;
+        };
+        // Maintain insert rank with the renamed key.
+        if let Some(InsertionEntry::Alive(stored_key, _)) =
+            self.ordered.get_mut(from_rank(insert_rank))
+        {
+            *stored_key = new.clone();
+        } else {
+            panic!("renamed pair not alive in ordered storage");
+        }
+        self.map.insert(new, insert_rank);
+        true
+    }
+
+    /// Rebuilds the hash table from the live entries of the map, recomputing
+    /// the hash of every key.
+    ///
+    /// Call this after mutating keys through interior mutability in a way that
+    /// changes their hash or equality, which otherwise leaves the mutated
+    /// entries unreachable by lookups. Insertion order and insertion ranks are
+    /// unchanged.
+    ///
+    /// If several keys are equal after rehashing, the pair with the lowest
+    /// insertion rank keeps its rank and takes the value of the last such
+    /// pair. The other pairs are removed.
+    ///
+    /// If hashing or comparing a key panics, the map is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    /// use std::hash::{Hash, Hasher};
+    /// use std::rc::Rc;
+    /// use strudel::StHashMap;
+    ///
+    /// #[derive(Clone, PartialEq, Eq)]
+    /// struct Key(Rc<Cell<u32>>);
+    ///
+    /// impl Hash for Key {
+    ///     fn hash<H: Hasher>(&self, state: &mut H) {
+    ///         self.0.get().hash(state);
+    ///     }
+    /// }
+    ///
+    /// let key = Key(Rc::new(Cell::new(1)));
+    /// let mut map = StHashMap::new();
+    /// map.insert(key.clone(), "a");
+    ///
+    /// key.0.set(2);
+    /// map.rehash();
+    /// assert_eq!(map.get(&Key(Rc::new(Cell::new(2)))), Some(&"a"));
+    /// ```
+    #[inline]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn rehash(&mut self)
+    where
+        S: Clone,
+        A: Clone,
+    {
+        // Rebuild into a local table so a panicking `Hash` or `Eq` impl leaves
+        // the map untouched.
+        let mut map = HashMap::with_capacity_and_hasher_in(
+            self.map.len(),
+            self.hasher().clone(),
+            self.map.allocator().clone(),
+        );
+        let mut duplicates = Vec::new();
+        for (insert_rank, entry) in self.ordered.iter().enumerate() {
+            let key = match entry {
+                InsertionEntry::Alive(key, _) => key.clone(),
+                InsertionEntry::Dead => continue,
+            };
+            match map.entry(key) {
+                HashEntry::Occupied(base) => duplicates.push((insert_rank, *base.get())),
+                HashEntry::Vacant(base) => {
+                    base.insert(to_rank(insert_rank));
+                }
+            }
+        }
+        self.map = map;
+
+        // Merge each duplicate into the first pair with its key. Replaced
+        // pairs are dropped only once the map is consistent again.
+        let mut merged = Vec::with_capacity(duplicates.len());
+        for (insert_rank, first_rank) in duplicates {
+            let (key, value) =
+                match mem::replace(&mut self.ordered[insert_rank], InsertionEntry::Dead) {
+                    InsertionEntry::Alive(key, value) => (key, value),
+                    InsertionEntry::Dead => panic!("rehashed pair not alive in ordered storage"),
+                };
+            match &mut self.ordered[from_rank(first_rank)] {
+                InsertionEntry::Alive(_, first_value) => {
+                    merged.push((key, mem::replace(first_value, value)));
+                }
+                InsertionEntry::Dead => panic!("rehashed pair not alive in ordered storage"),
+            }
+        }
+    }
+
+    /// Tries to insert a key-value pair into the map, and returns a mutable
+    /// reference to the value in the entry.
+    ///
+    /// If the map already had this key present, nothing is updated, and an
+    /// error containing the occupied entry and the value is returned.
+    ///
+    /// A newly inserted pair is ordered after all existing entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the map and the map already holds
+    /// `u32::MAX + 1` entries. See [`StHashMap`] for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// assert_eq!(map.try_insert(37, "a").unwrap(), &"a");
+    ///
+    /// let err = map.try_insert(37, "b").unwrap_err();
+    /// assert_eq!(err.entry.key(), &37);
+    /// assert_eq!(err.entry.get(), &"a");
+    /// assert_eq!(err.value, "b");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the key is already present in the map, an [`OccupiedError`] is
+    /// returned and the map is not modified.
+    #[inline]
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn try_insert(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<&mut V, OccupiedError<'_, K, V, S, A>> {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Err(OccupiedError { entry, value }),
+            Entry::Vacant(entry) => Ok(entry.insert(value)),
+        }
+    }
+
+    /// Moves all key-value pairs from `other` into `self`, leaving `other`
+    /// empty.
+    ///
+    /// Pairs are moved in the insertion order of `other`. Keys already present
+    /// in `self` have their value replaced and keep their insertion rank, the
+    /// same as [`StHashMap::insert`]. New keys are ordered after all existing
+    /// entries. `other` keeps its allocated memory for reuse.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map would hold more than `u32::MAX + 1` entries. See
+    /// [`StHashMap`] for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut a = StHashMap::new();
+    /// a.insert("a", 1);
+    /// a.insert("b", 2);
+    ///
+    /// let mut b = StHashMap::new();
+    /// b.insert("c", 3);
+    /// b.insert("a", 10);
+    /// b.insert("d", 4);
+    ///
+    /// a.append(&mut b);
+    /// assert!(b.is_empty());
+    /// assert_eq!(
+    ///     a.iter().collect::<Vec<_>>(),
+    ///     vec![(&"a", &10), (&"b", &2), (&"c", &3), (&"d", &4)]
+    /// );
+    /// ```
+    #[inline]
+    pub fn append(&mut self, other: &mut Self) {
+        self.reserve(other.len());
+        for (key, value) in other.drain() {
+            let _ = self.insert(key, value);
+        }
+    }
+
+    /// Moves all key-value pairs from `other` into `self`, combining values for
+    /// keys present in both maps.
+    ///
+    /// Pairs are visited in the insertion order of `other`. If a key is not
+    /// present in `self`, its value is inserted and ordered after all existing
+    /// entries. Otherwise, `combine` is called with the key, the existing value,
+    /// and the incoming value, and the existing entry keeps its insertion rank.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map would hold more than `u32::MAX + 1` entries. See
+    /// [`StHashMap`] for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut totals = StHashMap::new();
+    /// totals.insert("apples", 3);
+    /// totals.insert("pears", 1);
+    ///
+    /// let mut delivery = StHashMap::new();
+    /// delivery.insert("plums", 6);
+    /// delivery.insert("apples", 2);
+    ///
+    /// totals.merge_with(delivery, |_, total, count| *total += count);
+    /// assert_eq!(
+    ///     totals.iter().collect::<Vec<_>>(),
+    ///     vec![(&"apples", &5), (&"pears", &1), (&"plums", &6)]
+    /// );
+    /// ```
+    #[allow(clippy::missing_panics_doc)]
+    #[inline]
+    pub fn merge_with<F>(&mut self, other: Self, mut combine: F)
+    where
+        F: FnMut(&K, &mut V, V),
+    {
+        for (key, value) in other {
+            if let Some(&insert_rank) = self.map.get(&key) {
+                match &mut self.ordered[from_rank(insert_rank)] {
+                    InsertionEntry::Alive(_, existing) => combine(&key, existing, value),
+                    InsertionEntry::Dead => panic!("merged pair not alive in ordered storage"),
+                }
+            } else {
+                let _ = self.insert(key, value);
+            }
+        }
+    }
+
+    /// Inserts a key-value pair into the map immediately before the pair at
+    /// position `index`, returning the position of the pair and the previous
+    /// value if the key was already present.
+    ///
+    /// Positions count live pairs in insertion order, so an `index` equal to
+    /// the length of the map inserts the pair last. If the key is already
+    /// present, its value is replaced and the pair is moved, not duplicated.
+    /// Pairs between the old and new position shift by one.
+    ///
+    /// Dead slots left by removals are reclaimed first, so after this call the
+    /// returned position is also the insertion rank of the pair. Insertion
+    /// ranks obtained before calling this method must not be reused. See
+    /// [`StHashMap::rebuilds`]. This is O(n).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length of the map.
+    ///
+    /// Panics if `key` is not in the map and the map already holds
+    /// `u32::MAX + 1` entries. See [`StHashMap`] for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// assert_eq!(map.insert_before(0, "z", 26), (0, None));
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["z", "a", "b", "c"]);
+    ///
+    /// // Moving "c" in front of "a" replaces its value.
+    /// assert_eq!(map.insert_before(1, "c", 30), (1, Some(3)));
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["z", "c", "a", "b"]);
+    /// assert_eq!(map.get_index_of(&"c"), Some(1));
+    /// ```
+    #[inline]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn insert_before(&mut self, index: usize, key: K, value: V) -> (usize, Option<V>) {
+        let len = self.len();
+        assert!(
+            index <= len,
+            "index out of bounds: the len is {} but the index is {}",
+            len,
+            index
+        );
+        if self.dead_slots() > 0 {
+            self.reclaim_dead_slots();
+        }
+        if let Some(&insert_rank) = self.map.get(&key) {
+            let insert_rank = from_rank(insert_rank);
+            let old = match &mut self.ordered[insert_rank] {
+                InsertionEntry::Alive(_, existing) => mem::replace(existing, value),
+                InsertionEntry::Dead => panic!("moved pair not alive in ordered storage"),
+            };
+            let to = if insert_rank < index {
+                index - 1
+            } else {
+                index
+            };
+            self.move_rank(insert_rank, to);
+            (to, Some(old))
+        } else {
+            // Shifting entries moves the last one to rank `ordered.len()`,
+            // which must fit before anything is moved.
+            let _ = to_rank(self.ordered.len());
+            self.ordered
+                .insert(index, InsertionEntry::Alive(key.clone(), value));
+            let _ = self.map.insert(key, to_rank(index));
+            if index + 1 < self.ordered.len() {
+                self.reindex(index + 1..self.ordered.len());
+                self.renumbered();
+            }
+            (index, None)
+        }
+    }
+
+    /// Inserts a key-value pair into the map immediately after the pair at
+    /// position `index`, returning the position of the pair and the previous
+    /// value if the key was already present.
+    ///
+    /// See [`StHashMap::insert_before`] for how positions, existing keys, and
+    /// insertion ranks are handled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than the length of the map.
+    ///
+    /// Panics if `key` is not in the map and the map already holds
+    /// `u32::MAX + 1` entries. See [`StHashMap`] for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// assert_eq!(map.insert_after(2, "z", 26), (3, None));
+    /// assert_eq!(map.insert_after(0, "c", 30), (1, Some(3)));
+    /// assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["a", "c", "b", "z"]);
+    /// ```
+    #[inline]
+    pub fn insert_after(&mut self, index: usize, key: K, value: V) -> (usize, Option<V>) {
+        let len = self.len();
+        assert!(
+            index < len,
+            "index out of bounds: the len is {} but the index is {}",
+            len,
+            index
+        );
+        self.insert_before(index + 1, key, value)
+    }
+
+    /// Creates a new map with the same keys, in the same insertion order, and
+    /// values transformed by `f`.
+    ///
+    /// `f` is called once for each value in insertion order. The new map does
+    /// not contain slots left behind by removed entries, so its insertion ranks
+    /// are dense and match [`StHashMap::get_index_of`] on the source only if
+    /// the source has no such slots.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("b", 2);
+    /// map.insert("a", 1);
+    /// map.insert("c", 3);
+    ///
+    /// let doubled = map.map_values(|value| value * 2);
+    /// assert_eq!(
+    ///     doubled.iter().collect::<Vec<_>>(),
+    ///     vec![(&"b", &4), (&"a", &2), (&"c", &6)]
+    /// );
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn map_values<W, F>(&self, mut f: F) -> StHashMap<K, W, S, A>
+    where
+        F: FnMut(&V) -> W,
+        S: Clone,
+        A: Clone,
+    {
+        let alloc = self.map.allocator().clone();
+        let mut map =
+            HashMap::with_capacity_and_hasher_in(self.len(), self.hasher().clone(), alloc);
+        let alloc = self.ordered.allocator().clone();
+        let mut ordered = AllocVec::with_capacity_in(self.len(), alloc);
+        for (key, value) in self {
+            let _ = map.insert(key.clone(), to_rank(ordered.len()));
+            ordered.push(InsertionEntry::Alive(key.clone(), f(value)));
+        }
+        StHashMap {
+            map,
+            ordered,
+            rebuilds: 0,
+            compaction_threshold: self.compaction_threshold,
+        }
+    }
+
+    /// Returns the entry for the first key-value pair in the map for in-place
+    /// manipulation, or `None` if the map is empty.
+    ///
+    /// The first pair is the live pair with the lowest insertion rank, the same
+    /// pair returned by [`StHashMap::first`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// assert!(map.first_entry().is_none());
+    ///
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.remove(&"a");
+    ///
+    /// if let Some(mut entry) = map.first_entry() {
+    ///     assert_eq!(entry.key(), &"b");
+    ///     *entry.get_mut() += 10;
+    /// }
+    /// assert_eq!(map.first(), Some((&"b", &12)));
+    /// ```
+    #[inline]
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn first_entry(&mut self) -> Option<OccupiedEntry<'_, K, V, S, A>> {
+        let key = self.first()?.0.clone();
+        self.occupied_entry(key)
+    }
+
+    /// Returns the entry for the last key-value pair in the map for in-place
+    /// manipulation, or `None` if the map is empty.
+    ///
+    /// The last pair is the live pair with the highest insertion rank, the same
+    /// pair returned by [`StHashMap::last`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// assert!(map.last_entry().is_none());
+    ///
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// if let Some(entry) = map.last_entry() {
+    ///     assert_eq!(entry.remove_entry(), ("c", 3));
+    /// }
+    /// assert_eq!(map.last(), Some((&"b", &2)));
+    /// ```
+    #[inline]
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn last_entry(&mut self) -> Option<OccupiedEntry<'_, K, V, S, A>> {
+        let key = self.last()?.0.clone();
+        self.occupied_entry(key)
+    }
+
+    #[inline]
+    #[cfg(feature = "std")]
+    fn occupied_entry(&mut self, key: K) -> Option<OccupiedEntry<'_, K, V, S, A>> {
+        match self.map.entry(key) {
+            HashEntry::Occupied(base) => Some(OccupiedEntry {
+                base,
+                ordered: &mut self.ordered,
+            }),
+            HashEntry::Vacant(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::hash::{Hash, Hasher};
+    use core::mem::size_of;
+
+    use alloc::vec::Vec;
+
+    use super::{from_rank, to_rank, InsertionEntry, Rank, StHashMap, LINEAR_SCAN_THRESHOLD};
+
+    #[cfg(feature = "std")]
+    std::thread_local! {
+        // The largest insertion rank this test thread may store. See
+        // `super::max_rank`.
+        pub(super) static MAX_RANK: Cell<usize> = Cell::new(from_rank(Rank::MAX));
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", target_pointer_width = "64"))]
+    fn try_reserve_rolls_back_hash_table_growth() {
+        // The hash table only stores keys and ranks, so it can grow to hold
+        // these entries while the insertion list overflows `isize::MAX` bytes.
+        let mut map: StHashMap<u8, [u8; 1 << 54]> = StHashMap::new();
+        assert!(map.try_reserve(1024).is_err());
+        assert_eq!(map.map.capacity(), 0);
+        assert_eq!(map.ordered.capacity(), 0);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn lookups_agree_across_linear_scan_threshold() {
+        for size in [1, LINEAR_SCAN_THRESHOLD - 1, LINEAR_SCAN_THRESHOLD, 64] {
+            let mut map = StHashMap::new();
+            for i in 0..size {
+                let _ = map.insert(i, i * 10);
+            }
+            let _ = map.remove(&0);
+            for key in 0..size + 2 {
+                let expected = if key == 0 || key >= size {
+                    None
+                } else {
+                    Some(key * 10)
+                };
+                assert_eq!(map.get(&key).copied(), expected);
+                assert_eq!(
+                    map.get_key_value(&key),
+                    expected.as_ref().map(|v| (&key, v))
+                );
+                assert_eq!(map.contains_key(&key), expected.is_some());
+                assert_eq!(map.clone().remove_entry(&key), expected.map(|v| (key, v)));
+            }
+        }
+    }
+
+    #[test]
+    fn churn_reclaims_dead_slots() {
+        let mut map = StHashMap::new();
+        for i in 0..16 {
+            let _ = map.insert(i, i);
+        }
+        for i in 16..1_000_000 {
+            let _ = map.insert(i, i);
+            assert_eq!(map.remove(&(i - 16)), Some(i - 16));
+        }
+        assert_eq!(map.len(), 16);
+        assert!(map.ordered.capacity() <= 64);
+        assert!(map.rebuilds() > 0);
+
+        let keys = map.keys().copied().collect::<Vec<_>>();
+        assert_eq!(keys, (1_000_000 - 16..1_000_000).collect::<Vec<_>>());
+        let first_rank = map.min_insert_rank();
+        assert_eq!(map.first(), map.get_nth(first_rank));
+        assert_eq!(map.last(), Some((&999_999, &999_999)));
+        assert_eq!(map.last(), map.get_nth(map.max_insert_rank()));
+        for (rank, key) in map.insert_ranks_from(first_rank).zip(keys) {
+            assert_eq!(map.get_nth(rank), Some((&key, &key)));
+            assert_eq!(map.get(&key), Some(&key));
+        }
+    }
+
+    #[test]
+    fn swap_and_shift_remove_skip_dead_slots() {
+        let mut map = StHashMap::new();
+        for i in 0..32 {
+            let _ = map.insert(i, i * 10);
+        }
+        // Leave dead slots in the middle and at the tail of the insertion list.
+        for i in [5, 6, 29, 30, 31] {
+            let _ = map.remove(&i);
+        }
+
+        assert_eq!(map.swap_remove(&3), Some(30));
+        assert_eq!(map.get_index_of(&28), Some(3));
+        assert_eq!(map.shift_remove(&10), Some(100));
+        assert_eq!(map.ordered.len(), 27);
+        assert_eq!(map.len(), 25);
+
+        let mut expected = (0..29)
+            .filter(|&i| ![3, 5, 6, 10].contains(&i))
+            .collect::<Vec<_>>();
+        expected.retain(|&i| i != 28);
+        expected.insert(3, 28);
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), expected);
+        for key in expected {
+            let rank = map.get_index_of(&key).unwrap();
+            assert_eq!(map.get_nth(rank), Some((&key, &(key * 10))));
+        }
+    }
+
+    // Resume a walk over insertion ranks after `f` and report whether
+    // `rebuilds` flagged the saved rank as stale.
+    fn resume_after(
+        map: &mut StHashMap<i32, i32>,
+        f: impl FnOnce(&mut StHashMap<i32, i32>),
+    ) -> bool {
+        let rebuilds = map.rebuilds();
+        f(map);
+        map.rebuilds() != rebuilds
+    }
+
+    #[test]
+    fn swap_and_shift_remove_renumber_ranks() {
+        let mut map = StHashMap::new();
+        for i in 0..6 {
+            let _ = map.insert(i, i * 10);
+        }
+        // A walk paused at rank 1 would resume at rank 2 and never see 5,
+        // which is swapped into rank 1.
+        assert!(resume_after(&mut map, |map| {
+            assert_eq!(map.swap_remove(&1), Some(10));
+        }));
+        assert_eq!(map.insert_ranks_from(2).collect::<Vec<_>>(), [2, 3, 4]);
+        assert_eq!(map.get_nth(1), Some((&5, &50)));
+
+        // A walk paused at rank 2 would resume at rank 3 and skip 3, which is
+        // shifted into rank 2.
+        assert!(resume_after(&mut map, |map| {
+            assert_eq!(map.shift_remove(&2), Some(20));
+        }));
+        assert_eq!(map.get_nth(2), Some((&3, &30)));
+
+        // Removing the last entry does not move any other entry.
+        assert!(!resume_after(&mut map, |map| {
+            assert_eq!(map.swap_remove(&4), Some(40));
+        }));
+        assert!(!resume_after(&mut map, |map| {
+            assert_eq!(map.shift_remove(&3), Some(30));
+        }));
+        assert_eq!(map.iter().collect::<Vec<_>>(), [(&0, &0), (&5, &50)]);
+    }
+
+    #[test]
+    fn sort_keys_orders_shuffled_map() {
+        let mut map = StHashMap::new();
+        // 37 is coprime with 101, so this visits every key in 0..101 once.
+        for i in 0..101 {
+            let key = i * 37 % 101;
+            let _ = map.insert(key, key * 10);
+        }
+        for key in (0..101).step_by(10) {
+            let _ = map.remove(&key);
+        }
+        map.sort_keys();
+
+        let expected = (0..101).filter(|key| key % 10 != 0).collect::<Vec<_>>();
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), expected);
+        assert_eq!(map.ordered.len(), map.len());
+        for (rank, key) in expected.into_iter().enumerate() {
+            assert_eq!(map.get_index_of(&key), Some(rank));
+            assert_eq!(map.get_nth(rank), Some((&key, &(key * 10))));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn sort_by_panicking_comparator_leaves_map_unchanged() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mut map = StHashMap::new();
+        for i in (1..=20_usize).rev() {
+            let _ = map.insert(i, i * 10);
+        }
+        let _ = map.remove(&20);
+        let rebuilds = map.rebuilds();
+
+        let mut calls = 0;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            map.sort_by(|(left, _), (right, _)| {
+                calls += 1;
+                assert!(calls < 10, "comparator panicked");
+                left.cmp(right)
+            });
+        }));
+        assert!(result.is_err());
+
+        assert_eq!(map.rebuilds(), rebuilds);
+        assert_eq!(map.len(), 19);
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            (1..=19).rev().collect::<Vec<_>>()
+        );
+        for i in 1..=19 {
+            assert_eq!(map.get(&i), Some(&(i * 10)));
+        }
+        assert_eq!(map.get(&20), None);
+
+        map.sort_keys();
+        assert_eq!(map.rebuilds(), rebuilds + 1);
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            (1..=19).collect::<Vec<_>>()
+        );
+        for i in 1..=19 {
+            assert_eq!(map.get(&i), Some(&(i * 10)));
+            assert_eq!(map.get_index_of(&i), Some(i - 1));
+        }
+    }
+
+    #[test]
+    fn pop_is_lifo() {
+        let mut map = StHashMap::new();
+        for i in 0..64 {
+            let _ = map.insert(i, i * 10);
+        }
+        for i in (1..64).step_by(3) {
+            let _ = map.remove(&i);
+        }
+        let mut expected = (0..64).filter(|i| i % 3 != 1).collect::<Vec<_>>();
+        while let Some(key) = expected.pop() {
+            assert_eq!(map.last(), Some((&key, &(key * 10))));
+            assert_eq!(map.pop(), Some((key, key * 10)));
+            assert_eq!(map.get(&key), None);
+            assert_eq!(map.len(), expected.len());
+        }
+        assert_eq!(map.pop(), None);
+        assert!(map.ordered.is_empty());
+    }
+
+    #[test]
+    fn truncate_skips_dead_slots() {
+        let mut map = StHashMap::new();
+        for i in 0..32 {
+            let _ = map.insert(i, i * 10);
+        }
+        for i in (0..32).filter(|i| i % 2 == 1) {
+            let _ = map.remove(&i);
+        }
+
+        map.truncate(5);
+        assert_eq!(map.len(), 5);
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), [0, 2, 4, 6, 8]);
+        assert_eq!(map.ordered.len(), 9);
+        for i in 10..32 {
+            assert_eq!(map.get(&i), None);
+        }
+        assert_eq!(map.last(), Some((&8, &80)));
+
+        map.truncate(0);
+        assert!(map.is_empty());
+        assert!(map.ordered.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn first_and_last_entry_follow_insertion_order() {
+        let mut map = StHashMap::new();
+        for i in 0..16 {
+            let _ = map.insert(i, i * 10);
+        }
+        for i in [0, 1, 15, 14] {
+            let _ = map.remove(&i);
+        }
+
+        let mut first = map.first_entry().unwrap();
+        assert_eq!(first.key(), &2);
+        assert_eq!(first.insert(200), 20);
+        assert_eq!(map.first(), Some((&2, &200)));
+        assert_eq!(map.get_index_of(&2), Some(2));
+
+        let last = map.last_entry().unwrap();
+        assert_eq!(last.remove_entry(), (13, 130));
+        assert_eq!(map.last(), Some((&12, &120)));
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            (2..13).collect::<Vec<_>>()
+        );
+
+        while let Some(entry) = map.last_entry() {
+            let _ = entry.remove();
+        }
+        assert!(map.is_empty());
+        assert!(map.first_entry().is_none());
+    }
+
+    #[test]
+    fn rebuild_counter_wraps_and_keeps_order() {
+        let mut map = StHashMap::new();
+        for i in 0..32 {
+            let _ = map.insert(i, i * 10);
+        }
+        for i in (0..32).filter(|i| i % 4 != 0) {
+            let _ = map.remove(&i);
+        }
+        map.rebuilds = usize::MAX;
+
+        assert_eq!(map.compact(), 24);
+        assert_eq!(map.rebuilds(), 0);
+        assert_eq!(
+            map.insert_ranks_from(0).collect::<Vec<_>>(),
+            (0..8).collect::<Vec<_>>()
+        );
+        for (rank, key) in (0..32).step_by(4).enumerate() {
+            assert_eq!(map.get_nth(rank), Some((&key, &(key * 10))));
+            assert_eq!(map.get_index_of(&key), Some(rank));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn hash_map_conversions_keep_all_entries() {
+        use std::collections::HashMap;
+
+        let mut map = StHashMap::new();
+        for i in 0..64 {
+            let _ = map.insert(i, i * 10);
+        }
+        for i in (0..64).step_by(5) {
+            let _ = map.remove(&i);
+        }
+
+        let hash_map = HashMap::from(map.clone());
+        assert_eq!(hash_map.len(), map.len());
+        for (key, value) in &map {
+            assert_eq!(hash_map.get(key), Some(value));
+        }
+
+        let order = hash_map.keys().copied().collect::<Vec<_>>();
+        let roundtrip = StHashMap::from(hash_map);
+        assert_eq!(roundtrip, map);
+        assert_eq!(roundtrip.keys().copied().collect::<Vec<_>>(), order);
+    }
+
+    #[test]
+    fn into_vec_keeps_insertion_order() {
+        let mut map = StHashMap::new();
+        for i in (0..64).rev() {
+            let _ = map.insert(i, i * 10);
+        }
+        for i in (0..64).step_by(3) {
+            let _ = map.remove(&i);
+        }
+        map.update(10, 1000);
+        let expected = map.iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>();
+
+        let pairs = map.into_vec();
+        assert_eq!(pairs, expected);
+        assert_eq!(pairs.len(), 42);
+        assert_eq!(pairs[0], (62, 620));
+        assert!(pairs.contains(&(10, 1000)));
+    }
+
+    #[test]
+    fn insert_rank_opt_distinguishes_empty_maps() {
+        let mut map = StHashMap::new();
+        assert_eq!(map.min_insert_rank_opt(), None);
+        assert_eq!(map.max_insert_rank_opt(), None);
+
+        let _ = map.insert("a", 1);
+        assert_eq!(map.min_insert_rank_opt(), Some(0));
+        assert_eq!(map.max_insert_rank_opt(), Some(0));
+        assert_eq!(map.min_insert_rank(), 0);
+        assert_eq!(map.max_insert_rank(), 0);
+
+        for key in ["b", "c", "d"] {
+            let _ = map.insert(key, 2);
+        }
+        for key in ["a", "b", "c", "d"] {
+            let _ = map.remove(&key);
+        }
+        assert_eq!(map.ordered.len(), 4);
+        assert_eq!(map.min_insert_rank_opt(), None);
+        assert_eq!(map.max_insert_rank_opt(), None);
+        assert_eq!(map.min_insert_rank(), 0);
+        assert_eq!(map.max_insert_rank(), 0);
+
+        let _ = map.insert("e", 5);
+        let rank = map.get_index_of(&"e");
+        assert!(rank.is_some());
+        assert_eq!(map.min_insert_rank_opt(), rank);
+        assert_eq!(map.max_insert_rank_opt(), rank);
+    }
+
+    #[test]
+    fn estimated_memsize_counts_reserved_capacity() {
+        let mut map = StHashMap::<usize, [u64; 4]>::new();
+        let _ = map.insert(0, [0; 4]);
+        let _ = map.insert(1, [1; 4]);
+        let small = map.estimated_memsize();
+
+        map.reserve(10_000);
+        assert_eq!(map.len(), 2);
+        let reserved = map.estimated_memsize();
+        let entry_size = size_of::<InsertionEntry<usize, [u64; 4]>>();
+        assert!(reserved > small);
+        assert!(reserved >= entry_size * 10_000);
+
+        map.shrink_to_fit();
+        assert!(map.estimated_memsize() < reserved);
+    }
+
+    #[test]
+    fn shrink_to_respects_lower_bound() {
+        let mut map = StHashMap::with_capacity(1024);
+        for i in 0..20 {
+            let _ = map.insert(i, i * 10);
+        }
+        for i in 0..5 {
+            let _ = map.remove(&i);
+        }
+        assert!(map.capacity() >= 1024);
+
+        map.shrink_to(100);
+        assert!(map.capacity() >= 100);
+        assert!(map.capacity() < 1024);
+
+        map.shrink_to(0);
+        assert!(map.capacity() >= 15);
+        assert!(map.ordered.capacity() >= 20);
+        assert!(map.capacity() < 100);
+
+        map.shrink_to(1000);
+        assert!(map.capacity() < 100);
+
+        assert_eq!(map.len(), 15);
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            (5..20).collect::<Vec<_>>()
+        );
+        for i in 5..20 {
+            assert_eq!(map.get(&i), Some(&(i * 10)));
+        }
+    }
+
+    #[test]
+    fn append_moves_entries_in_order() {
+        let mut map = StHashMap::new();
+        for i in 0..8 {
+            let _ = map.insert(i, i);
+        }
+        let _ = map.remove(&3);
+
+        let mut disjoint = StHashMap::new();
+        for i in (100..104).rev() {
+            let _ = disjoint.insert(i, i);
+        }
+        let capacity = disjoint.capacity();
+        map.append(&mut disjoint);
+        assert!(disjoint.is_empty());
+        assert_eq!(disjoint.capacity(), capacity);
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            [0, 1, 2, 4, 5, 6, 7, 103, 102, 101, 100]
+        );
+
+        let mut overlapping = StHashMap::new();
+        for i in [6, 200, 0, 3, 101] {
+            let _ = overlapping.insert(i, i * 10);
+        }
+        map.append(&mut overlapping);
+        assert!(overlapping.is_empty());
+        assert_eq!(
+            map.iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>(),
+            [
+                (0, 0),
+                (1, 1),
+                (2, 2),
+                (4, 4),
+                (5, 5),
+                (6, 60),
+                (7, 7),
+                (103, 103),
+                (102, 102),
+                (101, 1010),
+                (100, 100),
+                (200, 2000),
+                (3, 30),
+            ]
+        );
+
+        // Appending an empty map is a no-op.
+        map.append(&mut overlapping);
+        assert_eq!(map.len(), 13);
+    }
+
+    #[test]
+    fn merge_with_sums_word_counts() {
+        fn word_counts(text: &str) -> StHashMap<&str, usize> {
+            let mut counts = StHashMap::new();
+            for word in text.split_whitespace() {
+                let count = counts.get(&word).copied().unwrap_or_default();
+                let _ = counts.insert(word, count + 1);
+            }
+            counts
+        }
+
+        let mut counts = word_counts("the quick brown fox jumps over the lazy dog");
+        let _ = counts.remove(&"quick");
+        let ranks = counts
+            .keys()
+            .map(|&word| (word, counts.get_index_of(&word)))
+            .collect::<Vec<_>>();
+
+        let other = word_counts("a lazy dog and the other lazy dog sleep");
+        let mut calls = 0;
+        counts.merge_with(other, |_, count, incoming| {
+            calls += 1;
+            *count += incoming;
+        });
+
+        assert_eq!(calls, 3);
+        for (word, rank) in ranks {
+            assert_eq!(counts.get_index_of(&word), rank);
+        }
+        assert_eq!(
+            counts.iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>(),
+            [
+                ("the", 3),
+                ("brown", 1),
+                ("fox", 1),
+                ("jumps", 1),
+                ("over", 1),
+                ("lazy", 3),
+                ("dog", 3),
+                ("a", 1),
+                ("and", 1),
+                ("other", 1),
+                ("sleep", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn map_values_keeps_key_order() {
+        use alloc::string::{String, ToString};
+
+        let mut map = StHashMap::new();
+        for i in (0..32_i32).rev() {
+            let _ = map.insert(i, i - 16);
+        }
+        for i in (0..32).step_by(3) {
+            let _ = map.remove(&i);
+        }
+
+        let strings: StHashMap<i32, String> = map.map_values(ToString::to_string);
+        assert_eq!(strings.len(), map.len());
+        assert!(strings.keys().eq(map.keys()));
+        for (key, value) in &map {
+            assert_eq!(strings.get(key), Some(&value.to_string()));
+        }
+        assert_eq!(strings.ordered.len(), strings.len());
+        for (rank, key) in map.keys().enumerate() {
+            assert_eq!(strings.get_index_of(key), Some(rank));
+            assert_eq!(strings.get_nth(rank).map(|(k, _)| k), Some(key));
+        }
+    }
+
+    #[test]
+    fn retain_mut_expires_ttls() {
+        let mut map = StHashMap::new();
+        for i in 0..16_u32 {
+            let _ = map.insert(i, i % 4 + 1);
+        }
+        let _ = map.remove(&5);
+        let ranks = map
+            .keys()
+            .map(|&key| (key, map.get_index_of(&key)))
+            .collect::<Vec<_>>();
+
+        for tick in 1..=4 {
+            map.retain_mut(|_, ttl| {
+                *ttl -= 1;
+                *ttl > 0
+            });
+            let expected = (0..16)
+                .filter(|&i| i != 5 && i % 4 + 1 > tick)
+                .map(|i| (i, i % 4 + 1 - tick))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                map.iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>(),
+                expected
+            );
+            for (key, rank) in &ranks {
+                if map.contains_key(key) {
+                    assert_eq!(map.get_index_of(key), *rank);
+                }
+            }
+        }
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn try_insert_rejects_occupied_keys() {
+        use alloc::string::ToString;
+
+        let mut map = StHashMap::new();
+        let _ = map.insert("a", 1);
+        let _ = map.insert("b", 2);
+        let _ = map.remove(&"a");
+
+        let value = map.try_insert("c", 3).unwrap();
+        assert_eq!(*value, 3);
+        *value += 10;
+        assert_eq!(map.last(), Some((&"c", &13)));
+
+        let err = map.try_insert("b", 20).unwrap_err();
+        assert_eq!(err.entry.key(), &"b");
+        assert_eq!(err.entry.get(), &2);
+        assert_eq!(err.value, 20);
+        assert_eq!(
+            err.to_string(),
+            r#"failed to insert 20, key "b" already exists with value 2"#
+        );
+        assert_eq!(err.entry.remove(), 2);
+
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), ["c"]);
+        assert_eq!(map.try_insert("a", 100).ok().copied(), Some(100));
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), ["c", "a"]);
+    }
+
+    #[test]
+    fn insert_before_and_after_splice_pairs() {
+        fn keys(map: &StHashMap<&'static str, i32>) -> Vec<&'static str> {
+            map.keys().copied().collect()
+        }
+
+        let mut map = StHashMap::new();
+        for (key, value) in [("a", 0), ("b", 1), ("x", 2), ("c", 3), ("d", 4)] {
+            let _ = map.insert(key, value);
+        }
+        let _ = map.remove(&"x");
+
+        assert_eq!(map.insert_before(0, "front", 10), (0, None));
+        assert_eq!(keys(&map), ["front", "a", "b", "c", "d"]);
+        assert_eq!(map.ordered.len(), 5);
+
+        assert_eq!(map.insert_before(2, "mid", 20), (2, None));
+        assert_eq!(keys(&map), ["front", "a", "mid", "b", "c", "d"]);
+
+        assert_eq!(map.insert_after(5, "end", 30), (6, None));
+        assert_eq!(keys(&map), ["front", "a", "mid", "b", "c", "d", "end"]);
+
+        // Moving an existing key forward and backward does not duplicate it.
+        assert_eq!(map.insert_before(7, "front", 11), (6, Some(10)));
+        assert_eq!(keys(&map), ["a", "mid", "b", "c", "d", "end", "front"]);
+        assert_eq!(map.insert_after(0, "end", 31), (1, Some(30)));
+        assert_eq!(keys(&map), ["a", "end", "mid", "b", "c", "d", "front"]);
+        assert_eq!(map.insert_before(3, "b", 4), (3, Some(1)));
+        assert_eq!(keys(&map), ["a", "end", "mid", "b", "c", "d", "front"]);
+        assert_eq!(map.len(), 7);
+
+        for (rank, key) in keys(&map).into_iter().enumerate() {
+            assert_eq!(map.get_index_of(&key), Some(rank));
+            assert_eq!(map.get_nth(rank).map(|(k, _)| *k), Some(key));
+        }
+        assert_eq!(map[&"front"], 11);
+        assert_eq!(map[&"end"], 31);
+        assert_eq!(map[&"b"], 4);
+    }
+
+    #[test]
+    fn insert_before_and_after_renumber_ranks() {
+        let mut map = StHashMap::new();
+        for i in 0..4 {
+            let _ = map.insert(i, i * 10);
+        }
+
+        // Splicing a new pair in front shifts every later pair.
+        assert!(resume_after(&mut map, |map| {
+            assert_eq!(map.insert_before(1, 10, 100), (1, None));
+        }));
+        // Moving an existing pair shifts the pairs it passes.
+        assert!(resume_after(&mut map, |map| {
+            assert_eq!(map.insert_after(3, 0, 1), (3, Some(0)));
+        }));
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), [10, 1, 2, 0, 3]);
+
+        // Appending or replacing a pair in place leaves other ranks alone.
+        assert!(!resume_after(&mut map, |map| {
+            assert_eq!(map.insert_before(5, 4, 40), (5, None));
+        }));
+        assert!(!resume_after(&mut map, |map| {
+            assert_eq!(map.insert_before(2, 2, 21), (2, Some(20)));
+        }));
+        assert!(!resume_after(&mut map, |map| {
+            assert_eq!(map.insert_after(4, 4, 41), (5, Some(40)));
+        }));
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), [10, 1, 2, 0, 3, 4]);
+        for (rank, key) in [10, 1, 2, 0, 3, 4].iter().enumerate() {
+            assert_eq!(map.get_index_of(key), Some(rank));
+        }
+    }
+
+    #[test]
+    fn swap_indices_and_move_index_reorder_pairs() {
+        let mut map = StHashMap::new();
+        for i in 0..10 {
+            let _ = map.insert(i, i * 10);
+        }
+        let _ = map.remove(&0);
+        let _ = map.remove(&5);
+
+        map.swap_indices(0, 7);
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            [9, 2, 3, 4, 6, 7, 8, 1]
+        );
+        assert_eq!(map.first(), Some((&9, &90)));
+        assert_eq!(map.last(), Some((&1, &10)));
+        assert_eq!(map.ordered.len(), 8);
+
+        map.swap_indices(3, 3);
+        map.move_index(1, 5);
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            [9, 3, 4, 6, 7, 2, 8, 1]
+        );
+        map.move_index(6, 0);
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            [8, 9, 3, 4, 6, 7, 2, 1]
+        );
+        map.move_index(4, 4);
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            [8, 9, 3, 4, 6, 7, 2, 1]
+        );
+
+        for (rank, key) in map.keys().enumerate() {
+            assert_eq!(map.get_index_of(key), Some(rank));
+            assert_eq!(map.get(key), Some(&(key * 10)));
+        }
+    }
+
+    #[test]
+    fn swap_indices_and_move_index_renumber_ranks_of_dense_map() {
+        let mut map = StHashMap::new();
+        for i in 0..4 {
+            let _ = map.insert(i, i * 10);
+        }
+        assert_eq!(map.dead_slots(), 0);
+
+        assert!(resume_after(&mut map, |map| map.swap_indices(0, 3)));
+        assert!(resume_after(&mut map, |map| map.move_index(1, 2)));
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), [3, 2, 1, 0]);
+
+        assert!(!resume_after(&mut map, |map| map.swap_indices(2, 2)));
+        assert!(!resume_after(&mut map, |map| map.move_index(1, 1)));
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), [3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn shrink_to_fit_reclaims_dead_slots() {
+        let mut map = StHashMap::new();
+        for i in 0..1000 {
+            let _ = map.insert(i, i * 10);
+        }
+        for i in 0..900 {
+            let _ = map.remove(&i);
+        }
+        let rebuilds = map.rebuilds();
+        assert_eq!(map.ordered.len(), 1000);
+
+        map.shrink_to_fit();
+        assert_eq!(map.rebuilds(), rebuilds + 1);
+        assert_eq!(map.ordered.len(), 100);
+        assert!(map.ordered.capacity() < 128);
+        assert!(map.map.capacity() < 256);
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            (900..1000).collect::<Vec<_>>()
+        );
+        for (rank, key) in (900..1000).enumerate() {
+            assert_eq!(map.get_index_of(&key), Some(rank));
+            assert_eq!(map.get(&key), Some(&(key * 10)));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "no entry found for key")]
+    fn index_mut_panics_on_missing_key() {
+        let mut map = StHashMap::new();
+        let _ = map.insert("a", 1);
+        let _ = map.remove(&"a");
+        map[&"a"] += 1;
+    }
+
+    #[test]
+    fn clone_from_reuses_allocations() {
+        let mut small = StHashMap::new();
+        for i in 0..10_usize {
+            let _ = small.insert(i, i);
+        }
+        let mut large = StHashMap::new();
+        for i in 0..1000_usize {
+            let _ = large.insert(i, i * 2);
+        }
+        for i in (0..1000_usize).step_by(7) {
+            let _ = large.remove(&i);
+        }
+
+        let mut dest = StHashMap::new();
+        dest.clone_from(&large);
+        let capacity = (dest.map.capacity(), dest.ordered.capacity());
+        for _ in 0..100 {
+            dest.clone_from(&small);
+            assert_eq!(
+                dest.iter().collect::<Vec<_>>(),
+                small.iter().collect::<Vec<_>>()
+            );
+            dest.clone_from(&large);
+            assert!(dest.ordered.capacity() <= capacity.1);
+            assert!(dest.map.capacity() <= capacity.0);
+        }
+
+        // The dead slot layout of the source is preserved.
+        assert_eq!(dest.ordered.len(), large.ordered.len());
+        assert!(dest
+            .ordered
+            .iter()
+            .zip(&large.ordered)
+            .all(|(a, b)| matches!(
+                (a, b),
+                (InsertionEntry::Dead, InsertionEntry::Dead)
+                    | (InsertionEntry::Alive(..), InsertionEntry::Alive(..))
+            )));
+        assert_eq!(dest.min_insert_rank(), large.min_insert_rank());
+        assert_eq!(dest.max_insert_rank(), large.max_insert_rank());
+        assert_eq!(dest.rebuilds(), large.rebuilds());
+        assert_eq!(
+            dest.iter().collect::<Vec<_>>(),
+            large.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn extract_if_removes_even_values() {
+        let mut map = StHashMap::new();
+        for i in 0..20_usize {
+            let _ = map.insert(i, i * 3);
+        }
+        let _ = map.remove(&3);
+        let max_rank = map.max_insert_rank();
+
+        let extracted = map
+            .extract_if(|_, value| *value % 2 == 0)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            extracted,
+            (0..20_usize)
+                .step_by(2)
+                .map(|i| (i, i * 3))
+                .collect::<Vec<_>>()
+        );
+
+        let survivors = [1_usize, 5, 7, 9, 11, 13, 15, 17, 19];
+        assert_eq!(map.len(), survivors.len());
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), survivors);
+        for key in survivors {
+            assert_eq!(map.get(&key), Some(&(key * 3)));
+            assert_eq!(map.get_full(&key).map(|(rank, _, _)| rank), Some(key));
+        }
+        for (key, _) in extracted {
+            assert!(!map.contains_key(&key));
+        }
+        assert_eq!(map.max_insert_rank(), max_rank);
+    }
+
+    #[test]
+    fn extract_if_dropped_early_keeps_yielded_removals() {
+        let mut map = StHashMap::new();
+        for i in 0..10_usize {
+            let _ = map.insert(i, i);
+        }
+
+        let mut iter = map.extract_if(|_, value| *value % 2 == 0);
+        assert_eq!(iter.next(), Some((0, 0)));
+        assert_eq!(iter.next(), Some((2, 2)));
+        drop(iter);
+
+        assert_eq!(map.len(), 8);
+        assert!(!map.contains_key(&0));
+        assert!(!map.contains_key(&2));
+        assert!(map.contains_key(&4));
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            [1, 3, 4, 5, 6, 7, 8, 9]
+        );
+    }
+
+    #[test]
+    fn backing_store_capacities_diverge_after_churn() {
+        let mut map = StHashMap::new();
+        for i in 0..100_usize {
+            let _ = map.insert(i, i);
+        }
+        for i in 100..1100_usize {
+            let _ = map.remove(&(i - 100));
+            let _ = map.insert(i, i);
+        }
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.map_capacity(), map.map.capacity());
+        assert_eq!(map.ordered_capacity(), map.ordered.capacity());
+        assert!(map.ordered_capacity() > map.map_capacity());
+        assert_eq!(map.capacity(), map.map_capacity());
+
+        map.shrink_to_fit();
+        assert!(map.ordered_capacity() >= map.len());
+        assert_eq!(
+            map.capacity(),
+            map.map_capacity().min(map.ordered_capacity())
+        );
+    }
+
+    #[test]
+    fn dead_slots_counts_removed_entries_until_compact() {
+        let mut map = StHashMap::new();
+        for i in 0..100_usize {
+            let _ = map.insert(i, i);
+        }
+        assert_eq!(map.dead_slots(), 0);
+
+        for (removed, i) in (0..100_usize).step_by(4).enumerate() {
+            let _ = map.remove(&i);
+            assert_eq!(map.dead_slots(), removed + 1);
+        }
+        // Removing a missing key does not leave a dead slot.
+        let _ = map.remove(&0);
+        assert_eq!(map.dead_slots(), 25);
+        assert_eq!(
+            map.ordered
+                .iter()
+                .filter(|entry| matches!(entry, InsertionEntry::Dead))
+                .count(),
+            25
+        );
+        assert!((map.load_factor() - 0.75).abs() < f64::EPSILON);
+
+        assert_eq!(map.compact(), 25);
+        assert_eq!(map.dead_slots(), 0);
+        assert!((map.load_factor() - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn compaction_threshold_bounds_dead_slots() {
+        let mut map = StHashMap::with_compaction_threshold(0.25);
+        for i in 0..10_000_usize {
+            let _ = map.insert(i, i);
+        }
+        let peak_capacity = map.ordered.capacity();
+        for i in 0..9_990_usize {
+            assert_eq!(map.remove(&i), Some(i));
+            assert!(map.dead_slots() * 4 <= map.ordered.len());
+        }
+        assert_eq!(map.len(), 10);
+        assert!(map.rebuilds() > 0);
+        assert!(map.ordered.len() < 20);
+        assert!(map.ordered.capacity() < peak_capacity / 100);
+        // Keys were removed oldest first, so the remaining dead slots lead the
+        // insertion list.
+        assert_eq!(map.min_insert_rank(), map.dead_slots());
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            (9_990..10_000).collect::<Vec<_>>()
+        );
+        for (offset, key) in (9_990..10_000_usize).enumerate() {
+            let rank = map.dead_slots() + offset;
+            assert_eq!(map.get_full(&key).map(|(rank, _, _)| rank), Some(rank));
+        }
+    }
+
+    #[test]
+    fn compaction_threshold_is_off_by_default() {
+        let mut map = StHashMap::new();
+        assert_eq!(map.compaction_threshold(), None);
+        for i in 0..10_000_usize {
+            let _ = map.insert(i, i);
+        }
+        for i in 0..9_990_usize {
+            let _ = map.remove_entry(&i);
+        }
+        assert_eq!(map.dead_slots(), 9_990);
+        assert_eq!(map.rebuilds(), 0);
+        assert_eq!(map.get_full(&9_990).map(|(rank, _, _)| rank), Some(9_990));
+    }
+
+    #[test]
+    #[should_panic(expected = "compaction threshold must be between 0.0 and 1.0")]
+    fn compaction_threshold_rejects_nan() {
+        let _ = StHashMap::<usize, usize>::with_compaction_threshold(f64::NAN);
+    }
+
+    #[test]
+    fn get_key_value_mut_returns_stored_key() {
+        #[derive(Debug, Clone)]
+        struct Interned {
+            name: &'static str,
+            id: usize,
+        }
+
+        impl PartialEq for Interned {
+            fn eq(&self, other: &Self) -> bool {
+                self.name == other.name
+            }
+        }
+
+        impl Eq for Interned {}
+
+        impl Hash for Interned {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.name.hash(state);
+            }
+        }
+
+        let mut map = StHashMap::new();
+        for (id, name) in ["a", "b", "c"].iter().enumerate() {
+            let _ = map.insert(Interned { name, id }, id * 10);
+        }
+        let lookup = Interned {
+            name: "b",
+            id: usize::MAX,
+        };
+
+        let (key, value) = map.get_key_value_mut(&lookup).unwrap();
+        assert_eq!(key.id, 1);
+        assert_eq!(*value, 10);
+        *value += key.id;
+        assert_eq!(map.get(&lookup), Some(&11));
+
+        let _ = map.remove(&lookup);
+        assert_eq!(map.get_key_value_mut(&lookup), None);
+    }
+
+    #[test]
+    fn rename_key_keeps_value_and_rank() {
+        let mut map = StHashMap::new();
+        for i in 0..LINEAR_SCAN_THRESHOLD * 2 {
+            let _ = map.insert(i, i * 10);
+        }
+        let _ = map.remove(&0);
+        let rank = map.get_full(&5).map(|(rank, _, _)| rank);
+
+        assert!(map.rename_key(&5, 1_000));
+        assert_eq!(map.get(&5), None);
+        assert_eq!(map.get(&1_000), Some(&50));
+        assert_eq!(map.get_full(&1_000).map(|(rank, _, _)| rank), rank);
+        assert_eq!(map.len(), LINEAR_SCAN_THRESHOLD * 2 - 1);
+        let expected = (1..LINEAR_SCAN_THRESHOLD * 2)
+            .map(|i| if i == 5 { 1_000 } else { i })
+            .collect::<Vec<_>>();
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), expected);
+
+        // Missing old keys and existing new keys leave the map unchanged.
+        assert!(!map.rename_key(&5, 2_000));
+        assert!(!map.rename_key(&1_000, 1));
+        assert!(!map.rename_key(&1_000, 1_000));
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), expected);
+        assert_eq!(map.get(&1), Some(&10));
+        assert_eq!(map.get(&1_000), Some(&50));
+    }
+
+    // Compares the footprint of `InsertionEntry` with an `Option<(K, V)>`
+    // insertion list.
+    #[test]
+    #[ignore = "memory report; run with `cargo test --release -- --ignored --nocapture`"]
+    #[cfg(feature = "std")]
+    fn report_estimated_memsize_for_one_million_u64_entries() {
+        let map = (0..1_000_000_u64)
+            .map(|i| (i, i))
+            .collect::<StHashMap<_, _>>();
+        let enum_size = map.estimated_memsize();
+        let option_size = enum_size
+            - size_of::<InsertionEntry<u64, u64>>() * map.ordered.capacity()
+            + size_of::<Option<(u64, u64)>>() * map.ordered.capacity();
+        std::println!("enum InsertionEntry: {enum_size} bytes");
+        std::println!("Option<(K, V)>:      {option_size} bytes");
+        assert_eq!(enum_size, option_size);
+    }
+
+    #[test]
+    fn rank_conversion_round_trips_at_u32_max() {
+        assert_eq!(to_rank(0), 0);
+        assert_eq!(to_rank(u32::MAX as usize), u32::MAX);
+        assert_eq!(from_rank(u32::MAX), u32::MAX as usize);
+        assert_eq!(
+            from_rank(to_rank(u32::MAX as usize - 1)),
+            u32::MAX as usize - 1
+        );
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    #[should_panic(expected = "StHashMap insertion list exceeds u32::MAX + 1 slots")]
+    fn rank_conversion_panics_past_u32_max() {
+        let _ = to_rank(u32::MAX as usize + 1);
+    }
+
+    #[test]
+    fn rank_buckets_are_compact_for_small_keys() {
+        assert_eq!(size_of::<Rank>(), 4);
+        assert_eq!(size_of::<(u32, Rank)>(), 8);
+        assert_eq!(size_of::<(u64, Rank)>(), 16);
+        #[cfg(target_pointer_width = "64")]
+        {
+            assert!(size_of::<(u32, Rank)>() < size_of::<(u32, usize)>());
+            // Word-aligned keys pad the bucket to the same size either way.
+            assert_eq!(size_of::<(u64, Rank)>(), size_of::<(u64, usize)>());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn full_insertion_list_reclaims_dead_slots_before_overflowing() {
+        MAX_RANK.with(|max_rank| max_rank.set(7));
+        let mut map = (0..8).map(|i| (i, i * 10)).collect::<StHashMap<_, _>>();
+        assert_eq!(map.ordered.len(), 8);
+        assert!(map.try_reserve(1).is_err());
+        assert_eq!(map.insert(1, 11), Some(10));
+
+        // One dead slot out of eight is below the usual reclaim threshold,
+        // but the insertion list has run out of ranks.
+        assert_eq!(map.remove(&3), Some(30));
+        assert!(map.try_reserve(1).is_ok());
+        assert!(map.try_reserve(2).is_err());
+        let rebuilds = map.rebuilds();
+        assert_eq!(map.insert(8, 80), None);
+        assert_eq!(map.rebuilds(), rebuilds + 1);
+        assert_eq!(map.ordered.len(), 8);
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            [0, 1, 2, 4, 5, 6, 7, 8]
+        );
+        assert_eq!(map.get_index_of(&8), Some(7));
+        assert_eq!(map.get_nth(7), Some((&8, &80)));
+        assert_eq!(map.get(&4), Some(&40));
+
+        assert_eq!(map.remove(&0), Some(0));
+        let _ = map.entry(9).or_insert(90);
+        assert_eq!(map.rebuilds(), rebuilds + 2);
+        assert_eq!(map.ordered.len(), 8);
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            [1, 2, 4, 5, 6, 7, 8, 9]
+        );
+        assert_eq!(map.get_nth(7), Some((&9, &90)));
+        assert_eq!(map.len(), map.iter().count());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    #[should_panic(expected = "StHashMap insertion list exceeds u32::MAX + 1 slots")]
+    fn insert_into_full_insertion_list_without_dead_slots_panics() {
+        MAX_RANK.with(|max_rank| max_rank.set(7));
+        let mut map = (0..8).map(|i| (i, i * 10)).collect::<StHashMap<_, _>>();
+        let _ = map.insert(8, 80);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    #[should_panic(expected = "StHashMap insertion list exceeds u32::MAX + 1 slots")]
+    fn vacant_entry_into_full_insertion_list_without_dead_slots_panics() {
+        MAX_RANK.with(|max_rank| max_rank.set(7));
+        let mut map = (0..8).map(|i| (i, i * 10)).collect::<StHashMap<_, _>>();
+        let _ = map.entry(8).or_insert(80);
+    }
+
+    #[test]
+    fn insertion_entry_is_as_small_as_option() {
+        // `Dead` fills a niche in the key or value when either has one, so the
+        // enum never costs more than `Option<(K, V)>`.
+        assert_eq!(
+            size_of::<InsertionEntry<u64, u64>>(),
+            size_of::<Option<(u64, u64)>>()
+        );
+        assert_eq!(
+            size_of::<InsertionEntry<&str, u64>>(),
+            size_of::<(&str, u64)>()
+        );
+        assert_eq!(
+            size_of::<InsertionEntry<u64, Vec<u8>>>(),
+            size_of::<(u64, Vec<u8>)>()
+        );
+    }
+
+    #[test]
+    fn with_pow2_capacity_rounds_up() {
+        for (size, expected) in [(0, 1), (1, 1), (5, 8), (8, 8), (9, 16), (1000, 1024)] {
+            let map = StHashMap::<usize, usize>::with_pow2_capacity(size);
+            assert!(map.capacity() >= expected);
+            assert!(map.map_capacity() >= expected);
+            assert_eq!(map.ordered_capacity(), expected);
+        }
+    }
+
+    #[test]
+    fn keys_and_values_vec_follow_iter_order() {
+        let mut map = StHashMap::new();
+        for i in 0..100_u32 {
+            let _ = map.insert(i * 7 % 100, i);
+        }
+        for i in (0..100_u32).step_by(3) {
+            let _ = map.remove(&i);
+        }
+
+        let keys = map.keys_vec();
+        let values = map.values_vec();
+        assert_eq!(keys.len(), map.len());
+        assert_eq!(values.len(), map.len());
+        assert!(map
+            .iter()
+            .zip(keys.iter().zip(&values))
+            .all(|((key, value), (k, v))| key == k && value == v));
+    }
+
+    #[test]
+    fn try_from_vec_rejects_duplicate_keys() {
+        use core::convert::TryFrom;
+
+        use super::DuplicateKey;
+
+        let pairs = (0..10_u32).rev().map(|i| (i, i * 10)).collect::<Vec<_>>();
+        let map = StHashMap::<_, _>::try_from(pairs.clone()).unwrap();
+        assert_eq!(map.into_vec(), pairs);
+
+        let mut pairs = pairs;
+        pairs.insert(7, (4, 400));
+        pairs.push((4, 401));
+        let err = StHashMap::<_, _>::try_from(pairs).unwrap_err();
+        assert_eq!(
+            err,
+            DuplicateKey {
+                index: 7,
+                key: 4,
+                value: 400
+            }
+        );
+    }
+
+    #[test]
+    fn eq_ordered_requires_same_insertion_order() {
+        let mut forward = StHashMap::new();
+        let mut backward = StHashMap::new();
+        for i in 0..10_u32 {
+            let _ = forward.insert(i, i * 2);
+            let _ = backward.insert(9 - i, (9 - i) * 2);
+        }
+        assert_eq!(forward, backward);
+        assert!(!forward.eq_ordered(&backward));
+
+        // Dead slots do not affect ordered equality.
+        let mut churned = forward.clone();
+        let _ = churned.insert(100, 0);
+        let _ = churned.remove(&100);
+        let _ = churned.remove(&3);
+        let _ = forward.remove(&3);
+        assert!(churned.eq_ordered(&forward));
+        assert!(forward.eq_ordered(&churned));
+
+        let _ = churned.remove(&4);
+        let _ = churned.insert(4, 8);
+        assert_eq!(churned, forward);
+        assert!(!churned.eq_ordered(&forward));
+    }
+
+    #[test]
+    fn rehash_restores_lookups_after_key_mutation() {
+        use alloc::rc::Rc;
+        use core::cell::Cell;
+
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        struct Key(Rc<Cell<u32>>);
+
+        impl Hash for Key {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.get().hash(state);
+            }
+        }
+
+        let key = |n| Key(Rc::new(Cell::new(n)));
+        let keys = (0..100).map(key).collect::<Vec<_>>();
+        let mut map = StHashMap::new();
+        for (i, k) in keys.iter().enumerate() {
+            let _ = map.insert(k.clone(), i);
+        }
+        let _ = map.remove(&key(50));
+
+        for k in &keys {
+            k.0.set(k.0.get() + 1000);
+        }
+        // Collapse keys 10 and 20 into the same key.
+        keys[20].0.set(1010);
+        assert_eq!(map.get(&key(1001)), None);
+
+        map.rehash();
+        assert_eq!(map.len(), 98);
+        assert_eq!(map.get(&key(1001)), Some(&1));
+        assert_eq!(map.get(&key(1050)), None);
+        assert_eq!(map.get(&key(1010)), Some(&20));
+        assert_eq!(map.get_index_of(&key(1010)), Some(10));
+        assert_eq!(map.get_index_of(&key(1099)), Some(99));
+        let expected = keys
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != 20 && i != 50)
+            .map(|(_, k)| k);
+        assert!(map.keys().zip(expected).all(|(k, expected)| k == expected));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn rehash_with_panicking_hash_leaves_map_consistent() {
+        use alloc::rc::Rc;
+        use core::cell::Cell;
+        use std::panic::{self, AssertUnwindSafe};
+
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        struct Key(Rc<Cell<u32>>);
+
+        impl Hash for Key {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                assert!(self.0.get() != u32::MAX, "hash panicked");
+                self.0.get().hash(state);
+            }
+        }
+
+        let keys = (0..20)
+            .map(|n| Key(Rc::new(Cell::new(n))))
+            .collect::<Vec<_>>();
+        let mut map = StHashMap::new();
+        for (i, k) in keys.iter().enumerate() {
+            let _ = map.insert(k.clone(), i);
+        }
+        let _ = map.remove(&keys[3]);
+        keys[10].0.set(u32::MAX);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| map.rehash()));
+        assert!(result.is_err());
+
+        assert_eq!(map.len(), 19);
+        assert_eq!(map.len(), map.iter().count());
+        assert_eq!(map.dead_slots(), 1);
+        assert_eq!(map.get(&keys[5]), Some(&5));
+    }
+
+    #[test]
+    fn default_hasher_constructors_for_custom_build_hasher() {
+        use core::hash::BuildHasherDefault;
+
+        #[derive(Default)]
+        struct Fnv(u64);
+
+        impl Hasher for Fnv {
+            fn finish(&self) -> u64 {
+                self.0
+            }
+
+            fn write(&mut self, bytes: &[u8]) {
+                for &byte in bytes {
+                    self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+                }
+            }
+        }
+
+        fn build<S>(capacity: usize) -> StHashMap<u32, u32, S>
+        where
+            S: core::hash::BuildHasher + Default,
+        {
+            let mut map = StHashMap::with_capacity_and_default_hasher(capacity);
+            for i in 0..10 {
+                let _ = map.insert(i, i * 2);
+            }
+            map
+        }
+
+        let map = build::<BuildHasherDefault<Fnv>>(100);
+        assert!(map.capacity() >= 100);
+        assert!(map.ordered_capacity() >= 100);
+        assert_eq!(map.get(&7), Some(&14));
+
+        let map = StHashMap::<u32, u32, BuildHasherDefault<Fnv>>::with_default_hasher();
+        assert_eq!(map.capacity(), 0);
+        assert_eq!(map.ordered_capacity(), 0);
+    }
+
+    #[test]
+    fn reserve_exact_sizes_insertion_list_exactly() {
+        let mut map = StHashMap::new();
+        for i in 0..5 {
+            let _ = map.insert(i, i);
+        }
+        let _ = map.remove(&2);
+        assert_eq!(map.dead_slots(), 1);
+
+        map.reserve_exact(100);
+        assert_eq!(map.ordered_capacity(), 5 + 100);
+        assert!(map.map_capacity() >= 4 + 100);
+
+        // Reserving less than the spare capacity does not shrink or grow.
+        map.reserve_exact(50);
+        assert_eq!(map.ordered_capacity(), 105);
+    }
+
+    #[cfg(feature = "nightly")]
+    mod bump {
+        use core::alloc::Layout;
+        use core::cell::{Cell, UnsafeCell};
+        use core::hash::{BuildHasher, Hash};
+        use core::mem::size_of;
+        use core::ptr::{self, NonNull};
+
+        use allocator_api2::alloc::{AllocError, Allocator};
+
+        use crate::st::map::{InsertionEntry, StHashMap};
+
+        const ARENA_SIZE: usize = 1 << 14;
+
+        // A bump allocator over a fixed arena. Freed memory is never reused;
+        // `live` tracks the bytes which are currently allocated.
+        #[repr(C, align(64))]
+        struct Bump {
+            arena: UnsafeCell<[u8; ARENA_SIZE]>,
+            used: Cell<usize>,
+            live: Cell<usize>,
+        }
+
+        impl Bump {
+            fn new() -> Self {
+                Self {
+                    arena: UnsafeCell::new([0; ARENA_SIZE]),
+                    used: Cell::new(0),
+                    live: Cell::new(0),
+                }
+            }
+
+            fn owns<T>(&self, ptr: *const T) -> bool {
+                let start = self.arena.get() as usize;
+                (start..start + ARENA_SIZE).contains(&(ptr as usize))
+            }
+        }
+
+        unsafe impl Allocator for &Bump {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                let start = self.arena.get().cast::<u8>();
+                let offset = (start as usize + self.used.get()).next_multiple_of(layout.align())
+                    - start as usize;
+                let end = offset.checked_add(layout.size()).ok_or(AllocError)?;
+                if end > ARENA_SIZE {
+                    return Err(AllocError);
+                }
+                self.used.set(end);
+                self.live.set(self.live.get() + layout.size());
+                // SAFETY: `offset + layout.size()` is within the arena.
+                let ptr = unsafe { start.add(offset) };
+                let slice = ptr::slice_from_raw_parts_mut(ptr, layout.size());
+                NonNull::new(slice).ok_or(AllocError)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                assert!(self.owns(ptr.as_ptr()));
+                self.live.set(self.live.get() - layout.size());
+            }
+        }
+
+        // The bytes of the hash table and the insertion list of `map`.
+        fn heap_size<K: Eq + Hash, V, S: BuildHasher>(map: &StHashMap<K, V, S, &Bump>) -> usize {
+            map.map.allocation_size() + map.ordered.capacity() * size_of::<InsertionEntry<K, V>>()
+        }
+
+        fn assert_allocates_through<K, V, S>(map: &StHashMap<K, V, S, &Bump>, bump: &Bump) {
+            assert!(ptr::eq(*map.allocator(), bump));
+            assert!(bump.owns(map.ordered.as_ptr()));
+        }
+
+        #[test]
+        fn new_in_allocates_through_allocator() {
+            let bump = Bump::new();
+            let mut map = StHashMap::new_in(&bump);
+            assert_eq!(bump.live.get(), 0);
+
+            for i in 0..100_u32 {
+                let _ = map.insert(i, i * 2);
+            }
+            assert_allocates_through(&map, &bump);
+            assert_eq!(bump.live.get(), heap_size(&map));
+
+            for i in 0..50 {
+                let _ = map.remove(&i);
+            }
+            map.shrink_to_fit();
+            assert_allocates_through(&map, &bump);
+            assert_eq!(bump.live.get(), heap_size(&map));
+            assert_eq!(map.get(&70), Some(&140));
+
+            drop(map);
+            assert_eq!(bump.live.get(), 0);
+        }
+
+        #[test]
+        fn with_capacity_in_allocates_through_allocator() {
+            let bump = Bump::new();
+            let mut map = StHashMap::with_capacity_in(64, &bump);
+            assert!(map.capacity() >= 64);
+            assert_allocates_through(&map, &bump);
+            assert_eq!(bump.live.get(), heap_size(&map));
+
+            for i in 0..64_u32 {
+                let _ = map.insert(i, i);
+            }
+            assert_eq!(bump.live.get(), heap_size(&map));
+
+            drop(map);
+            assert_eq!(bump.live.get(), 0);
+        }
+
+        #[test]
+        fn rebuilt_stores_allocate_through_allocator() {
+            let bump = Bump::new();
+            let mut map = StHashMap::new_in(&bump);
+            for i in (0..40_u32).rev() {
+                let _ = map.insert(i, i);
+            }
+
+            map.sort_keys();
+            assert_allocates_through(&map, &bump);
+            assert_eq!(bump.live.get(), heap_size(&map));
+            assert_eq!(map.first(), Some((&0, &0)));
+
+            map.rehash();
+            assert_allocates_through(&map, &bump);
+            assert_eq!(bump.live.get(), heap_size(&map));
+
+            let copy = map.clone();
+            assert_allocates_through(&copy, &bump);
+            assert_eq!(bump.live.get(), heap_size(&map) + heap_size(&copy));
+            drop(copy);
+
+            let doubled = map.map_values(|v| u64::from(*v) * 2);
+            assert_allocates_through(&doubled, &bump);
+            assert_eq!(bump.live.get(), heap_size(&map) + heap_size(&doubled));
+            assert_eq!(doubled.get(&39), Some(&78));
+            drop(doubled);
+
+            let odd = map.filter_map(|k, v| (k % 2 == 1).then_some(*v));
+            assert_allocates_through(&odd, &bump);
+            assert_eq!(bump.live.get(), heap_size(&map) + heap_size(&odd));
+            assert_eq!(odd.len(), 20);
+            drop(odd);
+
+            let shifted = map.map_keys(|k| k + 100);
+            assert_allocates_through(&shifted, &bump);
+            assert_eq!(bump.live.get(), heap_size(&shifted));
+            assert_eq!(shifted.first(), Some((&100, &0)));
+
+            drop(shifted);
+            assert_eq!(bump.live.get(), 0);
+        }
+    }
+
+    #[test]
+    fn windows_and_chunks_skip_dead_slots() {
+        let mut map = StHashMap::new();
+        for i in 0..12 {
+            let _ = map.insert(i, i * 10);
+        }
+        // Interleave dead slots at the front, middle, and back of the
+        // insertion list.
+        for i in &[0, 1, 4, 5, 6, 9, 11] {
+            let _ = map.remove(i);
+        }
+        let live = map.keys().copied().collect::<Vec<_>>();
+        assert_eq!(live, [2, 3, 7, 8, 10]);
+
+        for size in 1..=6 {
+            let windows = map
+                .windows(size)
+                .map(|window| {
+                    assert_eq!(window.len(), size);
+                    window.iter().map(|(&key, _)| key).collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            let expected = live.windows(size).map(<[_]>::to_vec).collect::<Vec<_>>();
+            assert_eq!(windows, expected, "windows of {size}");
+
+            let chunks = map
+                .chunks(size)
+                .map(|chunk| {
+                    let keys = chunk.iter().map(|(&key, _)| key).collect::<Vec<_>>();
+                    assert_eq!(chunk.len(), keys.len());
+                    keys
+                })
+                .collect::<Vec<_>>();
+            let expected = live.chunks(size).map(<[_]>::to_vec).collect::<Vec<_>>();
+            assert_eq!(chunks, expected, "chunks of {size}");
+        }
+
+        let window = map.windows(3).nth(1).unwrap();
+        assert_eq!(window.first(), Some((&3, &30)));
+        assert_eq!(window.last(), Some((&8, &80)));
+
+        map.clear();
+        assert_eq!(map.windows(1).next().map(|window| window.len()), None);
+        assert_eq!(map.chunks(1).next().map(|chunk| chunk.len()), None);
+    }
+
+    #[test]
+    fn clear_and_shrink_releases_capacity() {
+        let mut map = StHashMap::new();
+        for i in 0..1000 {
+            let _ = map.insert(i, i);
+        }
+        for i in (0..1000).step_by(2) {
+            let _ = map.remove(&i);
+        }
+        let memsize = map.estimated_memsize();
+
+        // Clearing drops tombstones left by removals in the hash table, which
+        // may raise its reported capacity.
+        map.clear();
+        assert!(map.is_empty());
+        assert!(map.capacity() >= 1000);
+        assert!(map.estimated_memsize() >= memsize);
+
+        for i in 0..1000 {
+            let _ = map.insert(i, i);
+        }
+        map.clear_and_shrink();
+        assert!(map.is_empty());
+        assert_eq!(map.capacity(), 0);
+        assert_eq!(map.estimated_memsize(), size_of::<StHashMap<i32, i32>>());
+
+        // The map is usable after releasing its memory.
+        let _ = map.insert(7, 70);
+        assert_eq!(map.get(&7), Some(&70));
+        assert_eq!(map.first(), Some((&7, &70)));
+    }
+
+    /// A type whose `Clone` implementation counts how many times it is called.
+    #[derive(Debug)]
+    struct CountClones<'a> {
+        id: i32,
+        clones: &'a Cell<usize>,
+    }
+
+    impl<'a> CountClones<'a> {
+        fn new(id: i32, clones: &'a Cell<usize>) -> Self {
+            Self { id, clones }
+        }
+    }
+
+    impl Clone for CountClones<'_> {
+        fn clone(&self) -> Self {
+            self.clones.set(self.clones.get() + 1);
+            Self {
+                id: self.id,
+                clones: self.clones,
+            }
+        }
+    }
+
+    impl PartialEq for CountClones<'_> {
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    impl Eq for CountClones<'_> {}
+
+    impl Hash for CountClones<'_> {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.id.hash(state);
+        }
+    }
+
+    #[test]
+    fn insert_never_clones_values() {
+        let key_clones = Cell::new(0);
+        let value_clones = Cell::new(0);
+        let mut map = StHashMap::new();
+
+        for i in 0..8 {
+            let old = map.insert(
+                CountClones::new(i, &key_clones),
+                CountClones::new(i, &value_clones),
+            );
+            assert!(old.is_none());
+        }
+        // Keys are stored in both the hash table and insertion order.
+        assert_eq!(key_clones.get(), 8);
+        assert_eq!(value_clones.get(), 0);
+
+        // Updating an existing key moves the new value into place.
+        let old = map.insert(
+            CountClones::new(3, &key_clones),
+            CountClones::new(30, &value_clones),
+        );
+        assert_eq!(old.map(|value| value.id), Some(3));
+        let (rank, old) = map.insert_full(
+            CountClones::new(4, &key_clones),
+            CountClones::new(40, &value_clones),
+        );
+        assert_eq!(rank, 4);
+        assert_eq!(old.map(|value| value.id), Some(4));
+        assert_eq!(key_clones.get(), 8);
+        assert_eq!(value_clones.get(), 0);
+
+        // Reclaiming dead slots moves values rather than cloning them.
+        for i in 0..7 {
+            let _ = map.remove(&CountClones::new(i, &key_clones));
+        }
+        for i in 8..16 {
+            let _ = map.insert(
+                CountClones::new(i, &key_clones),
+                CountClones::new(i, &value_clones),
+            );
+        }
+        assert!(map.rebuilds() > 0);
+        assert_eq!(key_clones.get(), 16);
+        assert_eq!(value_clones.get(), 0);
+        assert_eq!(
+            map.get(&CountClones::new(7, &key_clones)).map(|v| v.id),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn get_disjoint_indices_mut_returns_values_in_requested_order() {
+        let mut map = StHashMap::new();
+        for i in 0..5 {
+            let _ = map.insert(i, i * 10);
+        }
+        let [last, first, middle] = map.get_disjoint_indices_mut([4, 0, 2]).unwrap();
+        assert_eq!((*last, *first, *middle), (40, 0, 20));
+        *last += 1;
+        *first += 1;
+        *middle += 1;
+        assert_eq!(
+            map.values().copied().collect::<Vec<_>>(),
+            [1, 10, 21, 30, 41]
+        );
+
+        assert_eq!(map.get_disjoint_indices_mut([]), Some([]));
+    }
+
+    #[test]
+    fn get_disjoint_indices_mut_rejects_duplicate_indices() {
+        let mut map = StHashMap::new();
+        for i in 0..5 {
+            let _ = map.insert(i, i * 10);
+        }
+        assert_eq!(map.get_disjoint_indices_mut([1, 3, 1]), None);
+        assert_eq!(map.get_disjoint_indices_mut([2, 2]), None);
+        assert_eq!(map.get_disjoint_indices_mut([0, 5]), None);
+        assert_eq!(map.get_disjoint_indices_mut([usize::MAX]), None);
+    }
+
+    #[test]
+    fn get_disjoint_indices_mut_rejects_dead_slots() {
+        let mut map = StHashMap::new();
+        for i in 0..5 {
+            let _ = map.insert(i, i * 10);
+        }
+        let _ = map.remove(&1);
+        let _ = map.remove(&4);
+        assert_eq!(map.get_disjoint_indices_mut([0, 1]), None);
+        assert_eq!(map.get_disjoint_indices_mut([4]), None);
+        assert_eq!(
+            map.get_disjoint_indices_mut([3, 0, 2]),
+            Some([&mut 30, &mut 0, &mut 20])
+        );
+    }
+
+    #[test]
+    fn map_keys_collision_keeps_earlier_rank_and_later_value() {
+        let mut map = StHashMap::new();
+        let _ = map.insert(1, "one");
+        let _ = map.insert(20, "twenty");
+        let _ = map.insert(11, "eleven");
+        let _ = map.insert(3, "three");
+        let _ = map.remove(&20);
+
+        // 1 and 11 collide on their last digit.
+        let map = map.map_keys(|key| key % 10);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_nth(0), Some((&1, &"eleven")));
+        assert_eq!(map.get_nth(1), Some((&3, &"three")));
+        assert_eq!(
+            map.into_iter().collect::<Vec<_>>(),
+            [(1, "eleven"), (3, "three")]
+        );
+    }
+
+    #[test]
+    fn filter_map_keeps_order_of_survivors() {
+        let mut map = StHashMap::new();
+        for i in 0..8 {
+            let _ = map.insert(i, i * 10);
+        }
+        let _ = map.remove(&2);
+
+        let odd_halves = map.filter_map(
+            |&key, &value| {
+                if key % 2 == 1 {
+                    Some(value / 2)
+                } else {
+                    None
+                }
+            },
+        );
+        assert_eq!(odd_halves.len(), 4);
+        assert_eq!(
+            odd_halves.iter().collect::<Vec<_>>(),
+            [(&1, &5), (&3, &15), (&5, &25), (&7, &35)]
+        );
+        assert_eq!(odd_halves.get_nth(0), Some((&1, &5)));
+        assert_eq!(odd_halves.get_nth(3), Some((&7, &35)));
+        assert_eq!(map.len(), 7);
+
+        let none: StHashMap<i32, ()> = map.filter_map(|_, _| None);
+        assert!(none.is_empty());
     }
 }
@@ -0,0 +1,232 @@
+//! Rayon parallel iterators for [`StHashMap`].
+
+use core::mem;
+
+use allocator_api2::alloc::{Allocator, Global};
+use allocator_api2::vec::Vec;
+use rayon::iter::plumbing::UnindexedConsumer;
+use rayon::iter::{
+    IntoParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
+};
+
+use crate::st::map::{InsertionEntry, StHashMap};
+
+/// Parallel iterator over shared references to the entries of an
+/// [`StHashMap`].
+///
+/// This struct is created by the [`par_iter`] method on [`StHashMap`] from the
+/// [`IntoParallelRefIterator`] trait.
+///
+/// The parallel iterators of `StHashMap` split its insertion list and skip
+/// dead slots. Adaptors like [`ParallelIterator::for_each`] observe entries in
+/// no particular order, but order-preserving consumers like
+/// [`ParallelIterator::collect`] into a [`Vec`] yield entries in insertion
+/// order.
+///
+/// The number of live entries in each split is not known without scanning it,
+/// so these iterators do not implement [`IndexedParallelIterator`].
+///
+/// [`par_iter`]: IntoParallelRefIterator::par_iter
+/// [`Vec`]: alloc::vec::Vec
+/// [`IndexedParallelIterator`]: rayon::iter::IndexedParallelIterator
+#[derive(Debug, Clone)]
+pub struct ParIter<'a, K, V> {
+    entries: &'a [InsertionEntry<K, V>],
+}
+
+impl<'a, K, V> ParallelIterator for ParIter<'a, K, V>
+where
+    K: Sync,
+    V: Sync,
+{
+    type Item = (&'a K, &'a V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.entries
+            .par_iter()
+            .filter_map(|entry| match entry {
+                InsertionEntry::Alive(key, value) => Some((key, value)),
+                InsertionEntry::Dead => None,
+            })
+            .drive_unindexed(consumer)
+    }
+}
+
+/// Parallel iterator over shared references to the values of an
+/// [`StHashMap`].
+///
+/// This struct is created by the [`par_values`](StHashMap::par_values) method
+/// on [`StHashMap`]. See [`ParIter`] for ordering guarantees.
+#[derive(Debug, Clone)]
+pub struct ParValues<'a, K, V> {
+    entries: &'a [InsertionEntry<K, V>],
+}
+
+impl<'a, K, V> ParallelIterator for ParValues<'a, K, V>
+where
+    K: Sync,
+    V: Sync,
+{
+    type Item = &'a V;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.entries
+            .par_iter()
+            .filter_map(|entry| match entry {
+                InsertionEntry::Alive(_, value) => Some(value),
+                InsertionEntry::Dead => None,
+            })
+            .drive_unindexed(consumer)
+    }
+}
+
+/// Parallel iterator over the owned entries of an [`StHashMap`].
+///
+/// This struct is created by the [`into_par_iter`] method on [`StHashMap`]
+/// from the [`IntoParallelIterator`] trait. See [`ParIter`] for ordering
+/// guarantees.
+///
+/// [`into_par_iter`]: IntoParallelIterator::into_par_iter
+#[derive(Debug)]
+pub struct IntoParIter<K, V, A: Allocator = Global> {
+    entries: Vec<InsertionEntry<K, V>, A>,
+}
+
+impl<K, V, A> ParallelIterator for IntoParIter<K, V, A>
+where
+    K: Send,
+    V: Send,
+    A: Allocator + Send,
+{
+    type Item = (K, V);
+
+    fn drive_unindexed<C>(mut self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        // Move each pair out of its slot rather than consuming the insertion
+        // list, so the list is freed through its own allocator.
+        self.entries
+            .par_iter_mut()
+            .filter_map(|entry| match mem::replace(entry, InsertionEntry::Dead) {
+                InsertionEntry::Alive(key, value) => Some((key, value)),
+                InsertionEntry::Dead => None,
+            })
+            .drive_unindexed(consumer)
+    }
+}
+
+impl<K, V, S, A> IntoParallelIterator for StHashMap<K, V, S, A>
+where
+    K: Send,
+    V: Send,
+    A: Allocator + Send,
+{
+    type Item = (K, V);
+    type Iter = IntoParIter<K, V, A>;
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        IntoParIter {
+            entries: self.ordered,
+        }
+    }
+}
+
+impl<'a, K, V, S, A: Allocator> IntoParallelIterator for &'a StHashMap<K, V, S, A>
+where
+    K: Sync,
+    V: Sync,
+{
+    type Item = (&'a K, &'a V);
+    type Iter = ParIter<'a, K, V>;
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        ParIter {
+            entries: &self.ordered,
+        }
+    }
+}
+
+impl<K, V, S, A: Allocator> StHashMap<K, V, S, A> {
+    /// A parallel iterator visiting all values. The iterator element type is
+    /// `&'a V`.
+    ///
+    /// See [`ParIter`] for ordering guarantees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon::prelude::*;
+    /// use strudel::StHashMap;
+    ///
+    /// let map = (0..1000).map(|i| (i, i * 2)).collect::<StHashMap<_, _>>();
+    /// let sum = map.par_values().sum::<i32>();
+    /// assert_eq!(sum, map.values().sum());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn par_values(&self) -> ParValues<'_, K, V>
+    where
+        K: Sync,
+        V: Sync,
+    {
+        ParValues {
+            entries: &self.ordered,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+
+    use crate::st::map::StHashMap;
+
+    fn churned_map() -> StHashMap<u64, u64> {
+        let mut map = StHashMap::new();
+        for i in 0..100_000 {
+            let _ = map.insert(i, i * 3);
+        }
+        for i in (0..100_000).step_by(5) {
+            let _ = map.remove(&i);
+        }
+        map
+    }
+
+    #[test]
+    fn par_iter_matches_sequential() {
+        let map = churned_map();
+        let sum = map.values().sum::<u64>();
+        assert_eq!(map.par_values().sum::<u64>(), sum);
+        assert_eq!(map.par_iter().map(|(_, value)| value).sum::<u64>(), sum);
+        assert_eq!(map.par_iter().count(), map.len());
+
+        let collected = map.par_iter().collect::<Vec<_>>();
+        assert_eq!(collected, map.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn into_par_iter_matches_sequential() {
+        let map = churned_map();
+        let expected = map.clone().into_iter().collect::<Vec<_>>();
+        let sum = map.values().sum::<u64>();
+        assert_eq!(
+            map.clone()
+                .into_par_iter()
+                .map(|(_, value)| value)
+                .sum::<u64>(),
+            sum
+        );
+        assert_eq!(map.into_par_iter().collect::<Vec<_>>(), expected);
+    }
+}
@@ -0,0 +1,116 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+
+use allocator_api2::alloc::Allocator;
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::st::map::StHashMap;
+
+// Cap the up-front allocation made from an untrusted size hint.
+pub(crate) const MAX_PREALLOCATED_CAPACITY: usize = 4096;
+
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<K, V, H, A: Allocator> Serialize for StHashMap<K, V, H, A>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    /// Serialize this map as a map of its key-value pairs in insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let mut map = StHashMap::new();
+    /// map.insert("c", 1);
+    /// map.insert("a", 2);
+    /// map.insert("b", 3);
+    /// map.remove(&"a");
+    ///
+    /// let json = serde_json::to_string(&map).unwrap();
+    /// assert_eq!(json, r#"{"c":1,"b":3}"#);
+    /// ```
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in self {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'de, K, V, H> Deserialize<'de> for StHashMap<K, V, H>
+where
+    K: Deserialize<'de> + Eq + Hash + Clone,
+    V: Deserialize<'de>,
+    H: BuildHasher + Default,
+{
+    /// Deserialize a map by inserting its key-value pairs in document order.
+    ///
+    /// Insertion ranks match the order of the serialized entries. Duplicate
+    /// keys follow the semantics of [`StHashMap::insert`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashMap;
+    ///
+    /// let map: StHashMap<String, i32> = serde_json::from_str(r#"{"z":1,"a":2,"m":3}"#).unwrap();
+    /// assert_eq!(map.keys().collect::<Vec<_>>(), vec!["z", "a", "m"]);
+    ///
+    /// let mut map = StHashMap::new();
+    /// for (idx, key) in ["q", "w", "e", "r", "t", "y", "u", "i", "o", "p"].iter().enumerate() {
+    ///     map.insert(key.to_string(), idx);
+    /// }
+    /// map.remove(&String::from("e"));
+    /// let json = serde_json::to_string(&map).unwrap();
+    /// let roundtrip: StHashMap<String, usize> = serde_json::from_str(&json).unwrap();
+    /// assert_eq!(roundtrip, map);
+    /// assert!(roundtrip.keys().eq(map.keys()));
+    /// ```
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(StHashMapVisitor(PhantomData))
+    }
+}
+
+type MapMarker<K, V, H> = PhantomData<fn() -> StHashMap<K, V, H>>;
+
+struct StHashMapVisitor<K, V, H>(MapMarker<K, V, H>);
+
+impl<'de, K, V, H> Visitor<'de> for StHashMapVisitor<K, V, H>
+where
+    K: Deserialize<'de> + Eq + Hash + Clone,
+    V: Deserialize<'de>,
+    H: BuildHasher + Default,
+{
+    type Value = StHashMap<K, V, H>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let capacity = access
+            .size_hint()
+            .unwrap_or_default()
+            .min(MAX_PREALLOCATED_CAPACITY);
+        let mut map = StHashMap::with_capacity_and_hasher(capacity, H::default());
+        while let Some((key, value)) = access.next_entry()? {
+            let _ = map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
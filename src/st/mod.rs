@@ -1,2 +1,4 @@
 pub mod map;
 pub mod set;
+#[cfg(feature = "sync")]
+pub mod sync;
@@ -0,0 +1,230 @@
+use core::hash::{BuildHasher, Hash};
+
+use crate::st::map::{
+    DefaultHashBuilder, OccupiedEntry as MapOccupiedEntry, VacantEntry as MapVacantEntry,
+};
+
+/// A view into a single entry in a set, which may either be vacant or
+/// occupied.
+///
+/// This `enum` is constructed from the [`entry`] method on [`StHashSet`].
+///
+/// [`entry`]: crate::StHashSet::entry
+/// [`StHashSet`]: crate::StHashSet
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub enum SetEntry<'a, T, S = DefaultHashBuilder> {
+    /// An occupied entry.
+    Occupied(OccupiedEntry<'a, T, S>),
+    /// A vacant entry.
+    Vacant(VacantEntry<'a, T, S>),
+}
+
+/// A view into an occupied entry in a [`StHashSet`]. It is part of the
+/// [`SetEntry`] enum.
+///
+/// [`StHashSet`]: crate::StHashSet
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct OccupiedEntry<'a, T, S = DefaultHashBuilder> {
+    pub(crate) inner: MapOccupiedEntry<'a, T, (), S>,
+}
+
+/// A view into a vacant entry in a [`StHashSet`]. It is part of the
+/// [`SetEntry`] enum.
+///
+/// [`StHashSet`]: crate::StHashSet
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct VacantEntry<'a, T, S = DefaultHashBuilder> {
+    pub(crate) inner: MapVacantEntry<'a, T, (), S>,
+}
+
+impl<T, S> SetEntry<'_, T, S> {
+    /// Returns a reference to this entry's element.
+    ///
+    /// For an occupied entry this is the element stored in the set. For a
+    /// vacant entry this is the element that would be inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut set: StHashSet<&str> = StHashSet::new();
+    /// assert_eq!(set.entry("poneyland").get(), &"poneyland");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn get(&self) -> &T {
+        match self {
+            Self::Occupied(entry) => entry.get(),
+            Self::Vacant(entry) => entry.get(),
+        }
+    }
+}
+
+impl<T, S> SetEntry<'_, T, S>
+where
+    T: Hash + Clone,
+    S: BuildHasher,
+{
+    /// Ensures the element is in the set by inserting it if the entry is
+    /// vacant.
+    ///
+    /// A newly inserted element is ordered after all existing elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the entry is vacant and the set already holds `u32::MAX + 1`
+    /// elements. See [`StHashMap`](crate::StHashMap) for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut set = StHashSet::new();
+    /// set.insert("a");
+    ///
+    /// set.entry("poneyland").or_insert();
+    /// set.entry("a").or_insert();
+    /// assert_eq!(set.iter().collect::<Vec<_>>(), vec![&"a", &"poneyland"]);
+    /// ```
+    #[inline]
+    pub fn or_insert(self) {
+        if let Self::Vacant(entry) = self {
+            entry.insert();
+        }
+    }
+}
+
+impl<T, S> OccupiedEntry<'_, T, S> {
+    /// Gets a reference to the element stored in the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    /// use strudel::st_hash_set::SetEntry;
+    ///
+    /// let mut set: StHashSet<&str> = StHashSet::new();
+    /// set.insert("poneyland");
+    ///
+    /// if let SetEntry::Occupied(o) = set.entry("poneyland") {
+    ///     assert_eq!(o.get(), &"poneyland");
+    /// }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn get(&self) -> &T {
+        self.inner.key()
+    }
+
+    /// Takes the stored element out of the set, and returns it.
+    ///
+    /// The insertion ranks of the remaining elements are not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    /// use strudel::st_hash_set::SetEntry;
+    ///
+    /// let mut set: StHashSet<&str> = StHashSet::new();
+    /// set.insert("a");
+    /// set.insert("poneyland");
+    /// set.insert("b");
+    ///
+    /// if let SetEntry::Occupied(o) = set.entry("poneyland") {
+    ///     assert_eq!(o.remove(), "poneyland");
+    /// }
+    ///
+    /// assert!(!set.contains(&"poneyland"));
+    /// assert_eq!(set.iter().collect::<Vec<_>>(), vec![&"a", &"b"]);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn remove(self) -> T {
+        let (element, ()) = self.inner.remove_entry();
+        element
+    }
+}
+
+impl<T, S> VacantEntry<'_, T, S> {
+    /// Gets a reference to the element that would be inserted through the
+    /// `VacantEntry`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    /// use strudel::st_hash_set::SetEntry;
+    ///
+    /// let mut set: StHashSet<&str> = StHashSet::new();
+    ///
+    /// if let SetEntry::Vacant(v) = set.entry("poneyland") {
+    ///     assert_eq!(v.get(), &"poneyland");
+    /// }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn get(&self) -> &T {
+        self.inner.key()
+    }
+
+    /// Take ownership of the element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    /// use strudel::st_hash_set::SetEntry;
+    ///
+    /// let mut set: StHashSet<&str> = StHashSet::new();
+    ///
+    /// if let SetEntry::Vacant(v) = set.entry("poneyland") {
+    ///     assert_eq!(v.into_value(), "poneyland");
+    /// }
+    /// assert!(set.is_empty());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn into_value(self) -> T {
+        self.inner.into_key()
+    }
+}
+
+impl<T, S> VacantEntry<'_, T, S>
+where
+    T: Hash + Clone,
+    S: BuildHasher,
+{
+    /// Inserts the element into the set.
+    ///
+    /// The new element is ordered after all existing elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the set already holds `u32::MAX + 1` elements. See
+    /// [`StHashMap`](crate::StHashMap) for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    /// use strudel::st_hash_set::SetEntry;
+    ///
+    /// let mut set: StHashSet<&str> = StHashSet::new();
+    /// set.insert("a");
+    ///
+    /// if let SetEntry::Vacant(v) = set.entry("poneyland") {
+    ///     v.insert();
+    /// }
+    /// assert_eq!(set.last(), Some(&"poneyland"));
+    /// ```
+    #[inline]
+    pub fn insert(self) {
+        self.inner.insert(());
+    }
+}
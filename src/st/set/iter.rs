@@ -1,4 +1,5 @@
-use core::iter::{FromIterator, FusedIterator, IntoIterator};
+use core::hash::{BuildHasher, Hash};
+use core::iter::{Chain, FromIterator, FusedIterator, IntoIterator};
 
 use crate::st::map;
 use crate::st::set::StHashSet;
@@ -42,11 +43,11 @@ impl<'a, T> Iterator for Iter<'a, T> {
     }
 }
 
-impl<'a, T> FusedIterator for Iter<'a, T> {}
+impl<T> FusedIterator for Iter<'_, T> {}
 
-impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+impl<T> ExactSizeIterator for Iter<'_, T> {}
 
-impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+impl<T> DoubleEndedIterator for Iter<'_, T> {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.0.next_back()
     }
@@ -110,6 +111,168 @@ impl<T> DoubleEndedIterator for IntoIter<T> {
     }
 }
 
+/// This struct is created by the [`union`](StHashSet::union) method on
+/// [`StHashSet`]. See its documentation for more.
+#[derive(Debug, Clone)]
+pub struct Union<'a, T, S> {
+    pub(crate) iter: Chain<Iter<'a, T>, Difference<'a, T, S>>,
+}
+
+impl<'a, T, S> Iterator for Union<'a, T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T, S> FusedIterator for Union<'_, T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+}
+
+/// This struct is created by the [`intersection`](StHashSet::intersection)
+/// method on [`StHashSet`]. See its documentation for more.
+#[derive(Debug, Clone)]
+pub struct Intersection<'a, T, S> {
+    pub(crate) iter: Iter<'a, T>,
+    pub(crate) other: &'a StHashSet<T, S>,
+}
+
+impl<'a, T, S> Iterator for Intersection<'a, T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let other = self.other;
+        self.iter.find(|elem| other.contains(elem))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_, upper) = self.iter.size_hint();
+        (0, upper)
+    }
+}
+
+impl<T, S> FusedIterator for Intersection<'_, T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+}
+
+/// This struct is created by the [`difference`](StHashSet::difference) method
+/// on [`StHashSet`]. See its documentation for more.
+#[derive(Debug, Clone)]
+pub struct Difference<'a, T, S> {
+    pub(crate) iter: Iter<'a, T>,
+    pub(crate) other: &'a StHashSet<T, S>,
+}
+
+impl<'a, T, S> Iterator for Difference<'a, T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let other = self.other;
+        self.iter.find(|elem| !other.contains(elem))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_, upper) = self.iter.size_hint();
+        (0, upper)
+    }
+}
+
+impl<T, S> FusedIterator for Difference<'_, T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+}
+
+/// This struct is created by the
+/// [`symmetric_difference`](StHashSet::symmetric_difference) method on
+/// [`StHashSet`]. See its documentation for more.
+#[derive(Debug, Clone)]
+pub struct SymmetricDifference<'a, T, S> {
+    pub(crate) iter: Chain<Difference<'a, T, S>, Difference<'a, T, S>>,
+}
+
+impl<'a, T, S> Iterator for SymmetricDifference<'a, T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T, S> FusedIterator for SymmetricDifference<'_, T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+}
+
+/// This struct is created by the [`drain`](StHashSet::drain) method on
+/// [`StHashSet`]. See its documentation for more.
+#[derive(Debug)]
+pub struct Drain<'a, T>(pub(crate) map::Drain<'a, T, ()>);
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(elem, ())| elem)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T> FusedIterator for Drain<'_, T> {}
+
+impl<T> DoubleEndedIterator for Drain<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(elem, ())| elem)
+    }
+}
+
 /// This struct is created by the [`insert_ranks_from`] method on [`StHashSet`].
 /// See its documentation for more.
 ///
@@ -169,6 +332,31 @@ impl<T, S> IntoIterator for StHashSet<T, S> {
     type Item = T;
     type IntoIter = IntoIter<T>;
 
+    /// Creates a consuming iterator, that is, one that moves each element out
+    /// of the set in insertion order. The set cannot be used after calling
+    /// this.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut set = StHashSet::new();
+    /// set.insert(String::from("c"));
+    /// set.insert(String::from("a"));
+    /// set.insert(String::from("d"));
+    /// set.insert(String::from("b"));
+    /// set.remove(&String::from("d"));
+    ///
+    /// let iter = set.into_iter();
+    /// assert_eq!(iter.len(), 3);
+    ///
+    /// let mut elements = vec![];
+    /// for elem in iter {
+    ///     elements.push(elem);
+    /// }
+    /// assert_eq!(elements, vec!["c", "a", "b"]);
+    /// ```
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
         IntoIter(self.map.into_iter())
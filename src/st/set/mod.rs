@@ -1,12 +1,28 @@
+use core::borrow::Borrow;
+use core::fmt;
+#[cfg(feature = "std")]
+use core::hash::Hasher;
 use core::hash::{BuildHasher, Hash};
+use core::iter::FromIterator;
 use core::mem::size_of;
-use std::collections::hash_map::RandomState;
 
-use crate::st::map::StHashMap;
+#[cfg(feature = "std")]
+use crate::st::map::Entry as MapEntry;
+#[cfg(feature = "std")]
+pub use crate::st::map::TryReserveError;
+use crate::st::map::{DefaultHashBuilder, StHashMap};
 
+#[cfg(feature = "std")]
+mod entry;
 mod iter;
+#[cfg(feature = "serde")]
+mod serde;
 
-pub use iter::{InsertRanks, IntoIter, Iter};
+#[cfg(feature = "std")]
+pub use entry::{OccupiedEntry, SetEntry, VacantEntry};
+pub use iter::{
+    Difference, Drain, InsertRanks, Intersection, IntoIter, Iter, SymmetricDifference, Union,
+};
 
 /// An insertion-ordered hash set implemented as an `StHashMap` where the value
 /// is `()`.
@@ -15,7 +31,7 @@ pub use iter::{InsertRanks, IntoIter, Iter};
 /// implement the [`Eq`] and [`Hash`] traits.
 #[derive(Default, Debug, Clone)]
 #[allow(clippy::module_name_repetitions)]
-pub struct StHashSet<T, S = RandomState> {
+pub struct StHashSet<T, S = DefaultHashBuilder> {
     map: StHashMap<T, (), S>,
 }
 
@@ -37,7 +53,164 @@ where
 {
 }
 
-impl<T> StHashSet<T, RandomState> {
+impl<T, S> fmt::Display for StHashSet<T, S>
+where
+    T: fmt::Display,
+{
+    /// Formats the elements of this set as `{e1, e2}` in insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut set = StHashSet::new();
+    /// set.insert("a");
+    /// set.insert("b");
+    /// set.insert("c");
+    /// set.remove(&"b");
+    ///
+    /// assert_eq!(set.to_string(), "{a, c}");
+    /// assert_eq!(StHashSet::<i32>::new().to_string(), "{}");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("{")?;
+        for (i, element) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{element}")?;
+        }
+        f.write_str("}")
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<T, S> Hash for StHashSet<T, S>
+where
+    T: Hash,
+{
+    /// Feeds the contents of this set into the given [`Hasher`].
+    ///
+    /// Like [`PartialEq`], hashing ignores insertion order: sets with the same
+    /// elements hash equally regardless of the order the elements were
+    /// inserted in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::{Hash, Hasher};
+    /// use strudel::StHashSet;
+    ///
+    /// fn hash_of<T: Hash>(value: &T) -> u64 {
+    ///     let mut hasher = DefaultHasher::new();
+    ///     value.hash(&mut hasher);
+    ///     hasher.finish()
+    /// }
+    ///
+    /// let forward = [1, 2, 3].iter().copied().collect::<StHashSet<_>>();
+    /// let backward = [3, 2, 1].iter().copied().collect::<StHashSet<_>>();
+    ///
+    /// assert_eq!(forward, backward);
+    /// assert_eq!(hash_of(&forward), hash_of(&backward));
+    /// ```
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.map.hash(state);
+    }
+}
+
+impl<T, S> Extend<T> for StHashSet<T, S>
+where
+    T: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    /// Inserts all elements from the iterator in iteration order.
+    ///
+    /// Elements already present in the set keep their insertion rank.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the set would hold more than `u32::MAX + 1` elements. See
+    /// [`StHashMap`](crate::StHashMap) for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut set = StHashSet::new();
+    /// set.insert(2);
+    /// set.extend(vec![1, 2, 3]);
+    /// assert_eq!(set.iter().copied().collect::<Vec<_>>(), vec![2, 1, 3]);
+    /// ```
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.map.extend(iter.into_iter().map(|elem| (elem, ())));
+    }
+}
+
+impl<T, S> FromIterator<T> for StHashSet<T, S>
+where
+    T: Eq + Hash + Clone,
+    S: BuildHasher + Default,
+{
+    /// Creates an `StHashSet` from an iterator of elements.
+    ///
+    /// Elements are inserted in iteration order. Duplicate elements keep the
+    /// insertion rank of their first occurrence.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the set would hold more than `u32::MAX + 1` elements. See
+    /// [`StHashMap`](crate::StHashMap) for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let set = vec!["b", "a", "b", "c"].into_iter().collect::<StHashSet<_>>();
+    /// assert_eq!(set.iter().copied().collect::<Vec<_>>(), vec!["b", "a", "c"]);
+    /// ```
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::with_hasher(S::default());
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for StHashSet<T, DefaultHashBuilder>
+where
+    T: Eq + Hash + Clone,
+{
+    /// Creates an `StHashSet` from an array of elements.
+    ///
+    /// Elements are inserted in array order. Duplicate elements keep the
+    /// insertion rank of their first occurrence.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the set would hold more than `u32::MAX + 1` elements. See
+    /// [`StHashMap`](crate::StHashMap) for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let set: StHashSet<_> = [3, 1, 2, 1].into();
+    /// assert_eq!(set.iter().copied().collect::<Vec<_>>(), vec![3, 1, 2]);
+    /// ```
+    #[inline]
+    fn from(arr: [T; N]) -> Self {
+        IntoIterator::into_iter(arr).collect()
+    }
+}
+
+impl<T> StHashSet<T, DefaultHashBuilder> {
     /// Creates an empty `StHashSet`.
     ///
     /// The hash set is initially created with a capacity of 0, so it will not
@@ -190,9 +363,14 @@ impl<T, S> StHashSet<T, S> {
     /// The yielded elements may be passed to [`get_nth`] to retrieve the
     /// `element` in the nth insertion slot.
     ///
-    /// This API can be used to build a mutable iterator over the set that can
-    /// safely be invalidated. This is safe because new inserts always have
-    /// higher insert rank. See `api::st_foreach`.
+    /// This API can be used to build an iterator over the set that tolerates
+    /// mutation, like `st_foreach` in `strudel-ffi`. Removing an element leaves
+    /// a dead slot behind and a new element is ranked after all existing
+    /// elements, but an insert may also reclaim dead slots, which renumbers
+    /// every insertion rank. Compare [`rebuilds`] before and after each
+    /// mutation. If it changed, find the new rank of the last visited element
+    /// and resume from the rank after it. Otherwise elements may be skipped or
+    /// visited twice.
     ///
     /// # Examples
     ///
@@ -219,6 +397,7 @@ impl<T, S> StHashSet<T, S> {
     /// ```
     ///
     /// [`get_nth`]: StHashSet::get_nth
+    /// [`rebuilds`]: crate::StHashMap::rebuilds
     #[inline]
     #[must_use]
     pub fn insert_ranks_from(&self, rank: usize) -> InsertRanks {
@@ -427,6 +606,86 @@ impl<T, S> StHashSet<T, S> {
         self.map.clear();
     }
 
+    /// Clears the set, returning all elements as an iterator in insertion
+    /// order. Keeps the allocated memory for reuse.
+    ///
+    /// If the returned iterator is dropped before being fully consumed, it
+    /// drops the remaining elements. The set is empty either way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut set = StHashSet::with_capacity(10);
+    /// set.insert(3);
+    /// set.insert(1);
+    /// set.insert(2);
+    ///
+    /// assert_eq!(set.drain().collect::<Vec<_>>(), vec![3, 1, 2]);
+    /// assert!(set.is_empty());
+    /// assert!(set.capacity() >= 10);
+    /// ```
+    #[inline]
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        Drain(self.map.drain())
+    }
+
+    /// Rebuilds the set's insertion list without the slots left behind by
+    /// removed elements and returns the number of slots reclaimed.
+    ///
+    /// Iteration order is unchanged. See [`StHashMap::compact`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut set = StHashSet::new();
+    /// for i in 0..100 {
+    ///     set.insert(i);
+    /// }
+    /// for i in 0..90 {
+    ///     set.remove(&i);
+    /// }
+    /// let churned = set.estimated_memsize();
+    ///
+    /// assert_eq!(set.compact(), 90);
+    /// assert!(set.estimated_memsize() < churned);
+    /// assert_eq!(set.get_nth(0), Some(&90));
+    /// assert_eq!(set.iter().copied().collect::<Vec<_>>(), (90..100).collect::<Vec<_>>());
+    /// ```
+    #[inline]
+    pub fn compact(&mut self) -> usize {
+        self.map.compact()
+    }
+
+    /// Returns `true` if both sets contain the same elements in the same
+    /// insertion order.
+    ///
+    /// `==` ignores insertion order. See [`StHashMap::eq_ordered`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let forward = [1, 2, 3].iter().copied().collect::<StHashSet<_>>();
+    /// let backward = [3, 2, 1].iter().copied().collect::<StHashSet<_>>();
+    ///
+    /// assert_eq!(forward, backward);
+    /// assert!(!forward.eq_ordered(&backward));
+    /// assert!(forward.eq_ordered(&forward.clone()));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn eq_ordered(&self, other: &Self) -> bool
+    where
+        T: PartialEq,
+    {
+        self.map.eq_ordered(&other.map)
+    }
+
     /// Returns a reference to the set's [`BuildHasher`].
     ///
     /// # Examples
@@ -436,7 +695,7 @@ impl<T, S> StHashSet<T, S> {
     /// use std::collections::hash_map::RandomState;
     ///
     /// let hasher = RandomState::new();
-    /// let set: StHashSet<i32> = StHashSet::with_hasher(hasher);
+    /// let set: StHashSet<i32, RandomState> = StHashSet::with_hasher(hasher);
     /// let hasher: &RandomState = set.hasher();
     /// ```
     #[inline]
@@ -490,10 +749,65 @@ where
         self.map.reserve(additional);
     }
 
+    /// Reserves the minimum capacity for at least `additional` more elements to
+    /// be inserted in the `StHashSet`.
+    ///
+    /// See [`StHashMap::reserve_exact`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new allocation size overflows `usize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    /// let mut set: StHashSet<&str> = StHashSet::new();
+    /// set.insert("a");
+    /// set.reserve_exact(10);
+    /// assert!(set.capacity() >= 11);
+    /// ```
+    #[inline]
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.map.reserve_exact(additional);
+    }
+
+    /// Tries to reserve capacity for at least `additional` more elements to be
+    /// inserted in the `StHashSet`. The collection may reserve more space to
+    /// avoid frequent reallocations.
+    ///
+    /// # Errors
+    ///
+    /// If the capacity overflows, or the allocator reports a failure, then an
+    /// error is returned. The contents of the set are unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut set: StHashSet<i32> = StHashSet::new();
+    /// set.try_reserve(10).expect("why is the test harness OOMing on 10 elements?");
+    /// assert!(set.capacity() >= 10);
+    ///
+    /// set.insert(1);
+    /// assert!(set.try_reserve(usize::MAX).is_err());
+    /// assert!(set.contains(&1));
+    /// ```
+    #[inline]
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.map.try_reserve(additional)
+    }
+
     /// Shrinks the capacity of the set as much as possible. It will drop down
     /// as much as possible while maintaining the internal rules and possibly
     /// leaving some space in accordance with the resize policy.
     ///
+    /// Slots left behind by removed elements are reclaimed first, which
+    /// renumbers insertion ranks. See [`StHashMap::shrink_to_fit`].
+    ///
     /// # Examples
     ///
     /// ```
@@ -510,8 +824,87 @@ where
         self.map.shrink_to_fit();
     }
 
+    /// Shrinks the capacity of the set with a lower limit. It will drop down no
+    /// lower than the supplied limit while maintaining the internal rules and
+    /// possibly leaving some space in accordance with the resize policy.
+    ///
+    /// If the current capacity is less than the lower limit, this is a no-op.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    /// let mut set: StHashSet<i32> = StHashSet::with_capacity(100);
+    /// set.insert(1);
+    /// set.insert(3);
+    /// assert!(set.capacity() >= 100);
+    /// set.shrink_to(10);
+    /// assert!(set.capacity() >= 10);
+    /// set.shrink_to(0);
+    /// assert!(set.capacity() >= 2);
+    /// assert!(set.contains(&1));
+    /// assert!(set.contains(&3));
+    /// ```
+    #[inline]
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.map.shrink_to(min_capacity);
+    }
+
+    /// Clears the set, removing all elements, and releases the allocated
+    /// memory.
+    ///
+    /// See [`StHashMap::clear_and_shrink`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut set = (0..100).collect::<StHashSet<_>>();
+    /// set.clear();
+    /// assert!(set.capacity() >= 100);
+    ///
+    /// set.insert(1);
+    /// set.clear_and_shrink();
+    /// assert!(set.is_empty());
+    /// assert_eq!(set.capacity(), 0);
+    /// ```
+    #[inline]
+    pub fn clear_and_shrink(&mut self) {
+        self.map.clear_and_shrink();
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all elements `e` such that `f(&e)` returns
+    /// `false`. The elements are visited in insertion order.
+    ///
+    /// Insertion ranks of retained elements are not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut set: StHashSet<_> = [5, 2, 8, 1, 4, 7].into();
+    /// set.retain(|&elem| elem % 2 == 0);
+    /// assert_eq!(set.iter().copied().collect::<Vec<_>>(), vec![2, 8, 4]);
+    /// assert_eq!(set.get_nth(1), Some(&2));
+    /// ```
+    #[inline]
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.map.retain(|elem, ()| f(elem));
+    }
+
     /// Returns `true` if the set contains the specified element.
     ///
+    /// The value may be any borrowed form of the set's element type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for the
+    /// element type.
+    ///
     /// # Examples
     ///
     /// ```
@@ -521,16 +914,29 @@ where
     /// set.insert(1);
     /// assert_eq!(set.contains(&1), true);
     /// assert_eq!(set.contains(&2), false);
+    ///
+    /// let mut names = StHashSet::new();
+    /// names.insert(String::from("value"));
+    /// assert!(names.contains("value"));
+    /// assert!(!names.contains("other"));
     /// ```
     #[inline]
     #[must_use]
-    pub fn contains(&self, element: &T) -> bool {
+    pub fn contains<Q>(&self, element: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
         self.map.contains_key(element)
     }
 
     /// Returns a reference to the element in the set corresponding to the given
     /// value.
     ///
+    /// The value may be any borrowed form of the set's element type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for the
+    /// element type.
+    ///
     /// # Examples
     ///
     /// ```
@@ -543,10 +949,209 @@ where
     /// ```
     #[inline]
     #[must_use]
-    pub fn get(&self, element: &T) -> Option<&T> {
+    pub fn get<Q>(&self, element: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
         let (element, ()) = self.map.get_key_value(element)?;
         Some(element)
     }
+
+    /// Gets the given value's corresponding entry in the set for in-place
+    /// manipulation.
+    ///
+    /// Elements inserted through a vacant entry are ordered after all existing
+    /// elements, the same as [`StHashSet::insert`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    /// use strudel::st_hash_set::SetEntry;
+    ///
+    /// let mut seen = StHashSet::new();
+    /// let mut duplicates = Vec::new();
+    /// for word in "b a b c a d".split_whitespace() {
+    ///     match seen.entry(word) {
+    ///         SetEntry::Occupied(o) => duplicates.push(*o.get()),
+    ///         SetEntry::Vacant(v) => v.insert(),
+    ///     }
+    /// }
+    /// assert_eq!(seen.iter().copied().collect::<Vec<_>>(), vec!["b", "a", "c", "d"]);
+    /// assert_eq!(duplicates, vec!["b", "a"]);
+    ///
+    /// if let SetEntry::Occupied(o) = seen.entry("a") {
+    ///     assert_eq!(o.remove(), "a");
+    /// }
+    /// seen.entry("a").or_insert();
+    /// assert_eq!(seen.iter().copied().collect::<Vec<_>>(), vec!["b", "c", "d", "a"]);
+    /// ```
+    #[inline]
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn entry(&mut self, value: T) -> SetEntry<'_, T, S> {
+        match self.map.entry(value) {
+            MapEntry::Occupied(inner) => SetEntry::Occupied(OccupiedEntry { inner }),
+            MapEntry::Vacant(inner) => SetEntry::Vacant(VacantEntry { inner }),
+        }
+    }
+
+    /// Visits the values representing the union, i.e., all the values in
+    /// `self` or `other`, without duplicates.
+    ///
+    /// Elements of `self` are yielded first in insertion order, followed by
+    /// the elements of `other` that are not in `self` in `other`'s insertion
+    /// order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut a = StHashSet::new();
+    /// a.insert(3);
+    /// a.insert(1);
+    /// a.insert(2);
+    /// let mut b = StHashSet::new();
+    /// b.insert(4);
+    /// b.insert(2);
+    /// b.insert(5);
+    ///
+    /// let union = a.union(&b).copied().collect::<Vec<_>>();
+    /// assert_eq!(union, vec![3, 1, 2, 4, 5]);
+    ///
+    /// let union = b.union(&a).copied().collect::<Vec<_>>();
+    /// assert_eq!(union, vec![4, 2, 5, 3, 1]);
+    ///
+    /// let mut c = StHashSet::new();
+    /// c.insert(7);
+    /// c.insert(6);
+    /// let union = a.union(&c).copied().collect::<Vec<_>>();
+    /// assert_eq!(union, vec![3, 1, 2, 7, 6]);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn union<'a>(&'a self, other: &'a StHashSet<T, S>) -> Union<'a, T, S> {
+        Union {
+            iter: self.iter().chain(other.difference(self)),
+        }
+    }
+
+    /// Visits the values representing the difference, i.e., the values that are
+    /// in `self` but not in `other`.
+    ///
+    /// Elements are yielded in `self`'s insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut a = StHashSet::new();
+    /// a.insert(3);
+    /// a.insert(1);
+    /// a.insert(2);
+    /// a.insert(5);
+    /// let mut b = StHashSet::new();
+    /// b.insert(4);
+    /// b.insert(2);
+    /// b.insert(3);
+    ///
+    /// let difference = a.difference(&b).copied().collect::<Vec<_>>();
+    /// assert_eq!(difference, vec![1, 5]);
+    /// let difference = b.difference(&a).copied().collect::<Vec<_>>();
+    /// assert_eq!(difference, vec![4]);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn difference<'a>(&'a self, other: &'a StHashSet<T, S>) -> Difference<'a, T, S> {
+        Difference {
+            iter: self.iter(),
+            other,
+        }
+    }
+
+    /// Visits the values representing the symmetric difference, i.e., the
+    /// values that are in `self` or in `other` but not in both.
+    ///
+    /// Elements of `self` not in `other` are yielded first in `self`'s
+    /// insertion order, followed by the elements of `other` not in `self` in
+    /// `other`'s insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut a = StHashSet::new();
+    /// a.insert(3);
+    /// a.insert(1);
+    /// a.insert(2);
+    /// a.insert(5);
+    /// let mut b = StHashSet::new();
+    /// b.insert(6);
+    /// b.insert(2);
+    /// b.insert(4);
+    /// b.insert(3);
+    ///
+    /// let symmetric_difference = a.symmetric_difference(&b).copied().collect::<Vec<_>>();
+    /// assert_eq!(symmetric_difference, vec![1, 5, 6, 4]);
+    /// let symmetric_difference = b.symmetric_difference(&a).copied().collect::<Vec<_>>();
+    /// assert_eq!(symmetric_difference, vec![6, 4, 1, 5]);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn symmetric_difference<'a>(
+        &'a self,
+        other: &'a StHashSet<T, S>,
+    ) -> SymmetricDifference<'a, T, S> {
+        SymmetricDifference {
+            iter: self.difference(other).chain(other.difference(self)),
+        }
+    }
+
+    /// Visits the values representing the intersection, i.e., the values that
+    /// are both in `self` and `other`.
+    ///
+    /// Elements are yielded in `self`'s insertion order. This iterator always
+    /// walks `self` and probes `other`, even when `other` is the smaller set,
+    /// so that the output order is stable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut a = StHashSet::new();
+    /// a.insert(3);
+    /// a.insert(1);
+    /// a.insert(2);
+    /// let mut b = StHashSet::new();
+    /// b.insert(2);
+    /// b.insert(4);
+    /// b.insert(3);
+    ///
+    /// let intersection = a.intersection(&b).copied().collect::<Vec<_>>();
+    /// assert_eq!(intersection, vec![3, 2]);
+    /// let intersection = b.intersection(&a).copied().collect::<Vec<_>>();
+    /// assert_eq!(intersection, vec![2, 3]);
+    ///
+    /// let intersection = a.intersection(&a).copied().collect::<Vec<_>>();
+    /// assert_eq!(intersection, vec![3, 1, 2]);
+    ///
+    /// let mut c = StHashSet::new();
+    /// c.insert(7);
+    /// assert_eq!(a.intersection(&c).next(), None);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn intersection<'a>(&'a self, other: &'a StHashSet<T, S>) -> Intersection<'a, T, S> {
+        Intersection {
+            iter: self.iter(),
+            other,
+        }
+    }
 }
 
 impl<T, S> StHashSet<T, S>
@@ -561,6 +1166,11 @@ where
     /// If the set did have this element present, `true` is returned. The
     /// element is not updated, though. To update the element in-place, use
     /// [`StHashSet::update`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `element` is not in the set and the set already holds
+    /// `u32::MAX + 1` elements. See [`StHashMap`](crate::StHashMap) for this limit.
     #[inline]
     #[must_use]
     pub fn insert(&mut self, element: T) -> bool {
@@ -573,14 +1183,57 @@ where
     /// This function maintains the insertion rank of the element.
     ///
     /// If you do not wish to update the element in-place, use [`StHashSet::insert`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `element` is not in the set and the set already holds
+    /// `u32::MAX + 1` elements. See [`StHashMap`](crate::StHashMap) for this limit.
     #[inline]
     pub fn update(&mut self, element: T) {
         self.map.update(element, ());
     }
 
+    /// Removes an element from the set. Returns whether the element was present
+    /// in the set.
+    ///
+    /// To take ownership of the stored element, use [`StHashSet::remove_entry`].
+    ///
+    /// The value may be any borrowed form of the set's element type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for the
+    /// element type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut set = StHashSet::new();
+    /// set.insert(1);
+    /// assert_eq!(set.remove(&1), true);
+    /// assert_eq!(set.remove(&1), false);
+    /// assert!(set.is_empty());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn remove<Q>(&mut self, element: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.map.remove(element).is_some()
+    }
+
     /// Removes an element from the set, returning the stored element if the
     /// element was previously in the set.
     ///
+    /// The value may be any borrowed form of the set's element type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for the
+    /// element type.
+    ///
+    /// The stored element is the one that was first inserted, or the most
+    /// recent argument to [`StHashSet::update`], which may differ from the
+    /// given `element` in ways not observed by [`Eq`].
+    ///
     /// # Examples
     ///
     /// ```
@@ -588,12 +1241,46 @@ where
     ///
     /// let mut set = StHashSet::new();
     /// set.insert(1);
-    /// assert!(set.remove(&1));
-    /// assert!(!set.remove(&1));
+    /// set.insert(2);
+    /// assert_eq!(set.remove_entry(&1), Some(1));
+    /// assert_eq!(set.remove_entry(&1), None);
+    /// assert_eq!(set.iter().collect::<Vec<_>>(), vec![&2]);
     /// ```
     #[inline]
     #[must_use]
-    pub fn remove(&mut self, element: &T) -> bool {
-        self.map.remove(element).is_some()
+    pub fn remove_entry<Q>(&mut self, element: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let (element, ()) = self.map.remove_entry(element)?;
+        Some(element)
+    }
+
+    /// Removes the last element in insertion order and returns it, or
+    /// [`None`] if the set is empty.
+    ///
+    /// See [`StHashMap::pop`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut set = StHashSet::new();
+    /// set.insert(1);
+    /// set.insert(2);
+    /// set.insert(3);
+    ///
+    /// assert_eq!(set.pop(), Some(3));
+    /// assert_eq!(set.pop(), Some(2));
+    /// assert_eq!(set.pop(), Some(1));
+    /// assert_eq!(set.pop(), None);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn pop(&mut self) -> Option<T> {
+        let (element, ()) = self.map.pop()?;
+        Some(element)
     }
 }
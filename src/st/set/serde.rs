@@ -0,0 +1,114 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+use crate::st::map::serde::MAX_PREALLOCATED_CAPACITY;
+use crate::st::set::StHashSet;
+
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<T, H> Serialize for StHashSet<T, H>
+where
+    T: Serialize,
+{
+    /// Serialize this set as a sequence of its elements in insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let mut set = StHashSet::new();
+    /// set.insert("c");
+    /// set.insert("a");
+    /// set.insert("b");
+    /// set.remove(&"a");
+    ///
+    /// let json = serde_json::to_string(&set).unwrap();
+    /// assert_eq!(json, r#"["c","b"]"#);
+    /// ```
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for element in self {
+            seq.serialize_element(element)?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'de, T, H> Deserialize<'de> for StHashSet<T, H>
+where
+    T: Deserialize<'de> + Eq + Hash + Clone,
+    H: BuildHasher + Default,
+{
+    /// Deserialize a set by inserting its elements in document order.
+    ///
+    /// Insertion ranks match the order of the serialized elements. Duplicate
+    /// elements follow the semantics of [`StHashSet::insert`], so the first
+    /// occurrence determines the element's position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::StHashSet;
+    ///
+    /// let set: StHashSet<String> = serde_json::from_str(r#"["z","a","m"]"#).unwrap();
+    /// assert_eq!(set.iter().collect::<Vec<_>>(), vec!["z", "a", "m"]);
+    ///
+    /// let set: StHashSet<i32> = serde_json::from_str("[3, 1, 2, 1, 3, 4]").unwrap();
+    /// assert_eq!(set.iter().copied().collect::<Vec<_>>(), vec![3, 1, 2, 4]);
+    ///
+    /// let mut set = StHashSet::new();
+    /// for element in ["q", "w", "e", "r", "t", "y", "u", "i", "o", "p"] {
+    ///     set.insert(element.to_string());
+    /// }
+    /// set.remove(&String::from("e"));
+    /// let json = serde_json::to_string(&set).unwrap();
+    /// let roundtrip: StHashSet<String> = serde_json::from_str(&json).unwrap();
+    /// assert_eq!(roundtrip, set);
+    /// assert!(roundtrip.iter().eq(set.iter()));
+    /// ```
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(StHashSetVisitor(PhantomData))
+    }
+}
+
+type SetMarker<T, H> = PhantomData<fn() -> StHashSet<T, H>>;
+
+struct StHashSetVisitor<T, H>(SetMarker<T, H>);
+
+impl<'de, T, H> Visitor<'de> for StHashSetVisitor<T, H>
+where
+    T: Deserialize<'de> + Eq + Hash + Clone,
+    H: BuildHasher + Default,
+{
+    type Value = StHashSet<T, H>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a sequence")
+    }
+
+    fn visit_seq<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let capacity = access
+            .size_hint()
+            .unwrap_or_default()
+            .min(MAX_PREALLOCATED_CAPACITY);
+        let mut set = StHashSet::with_capacity_and_hasher(capacity, H::default());
+        while let Some(element) = access.next_element()? {
+            let _ = set.insert(element);
+        }
+        Ok(set)
+    }
+}
@@ -0,0 +1,401 @@
+use core::hash::{BuildHasher, Hash};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::st::map::{DefaultHashBuilder, StHashMap};
+
+/// A thread-safe handle to an [`StHashMap`] which guards the map with a
+/// [`Mutex`].
+///
+/// `SyncStHashMap` is [`Send`] and [`Sync`] when the keys, values and hasher
+/// of the map are [`Send`]. Share it between threads with an [`Arc`].
+///
+/// Each method locks the map for the duration of the call. Use
+/// [`SyncStHashMap::lock`] to run several operations, like iterating in
+/// insertion order, under a single lock.
+///
+/// If a thread panics while holding the lock, other threads can still lock
+/// the map. The map is not guaranteed to be consistent if the panic came from
+/// user code, like a `Hash` or `Eq` implementation, in the middle of an
+/// operation: entries may be lost or fail to be found. The map remains memory
+/// safe to use, but it is marked as poisoned. Callers that recover from panics
+/// should check [`SyncStHashMap::is_poisoned`] and discard a poisoned map, or
+/// call [`SyncStHashMap::clear_poison`] once they have repaired it.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+/// use strudel::SyncStHashMap;
+///
+/// let map = Arc::new(SyncStHashMap::new());
+/// let handles = (0..4)
+///     .map(|thread| {
+///         let map = Arc::clone(&map);
+///         thread::spawn(move || {
+///             for i in 0..100 {
+///                 map.insert(thread * 100 + i, thread);
+///             }
+///         })
+///     })
+///     .collect::<Vec<_>>();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// assert_eq!(map.len(), 400);
+/// assert_eq!(map.get(&250), Some(2));
+/// ```
+///
+/// [`Arc`]: std::sync::Arc
+#[derive(Default, Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct SyncStHashMap<K, V, S = DefaultHashBuilder> {
+    map: Mutex<StHashMap<K, V, S>>,
+}
+
+impl<K, V, S> From<StHashMap<K, V, S>> for SyncStHashMap<K, V, S> {
+    #[inline]
+    fn from(map: StHashMap<K, V, S>) -> Self {
+        let map = Mutex::new(map);
+        Self { map }
+    }
+}
+
+impl<K, V> SyncStHashMap<K, V, DefaultHashBuilder> {
+    /// Creates an empty `SyncStHashMap`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::SyncStHashMap;
+    /// let map: SyncStHashMap<&str, i32> = SyncStHashMap::new();
+    /// assert!(map.is_empty());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::from(StHashMap::new())
+    }
+}
+
+impl<K, V, S> SyncStHashMap<K, V, S> {
+    /// Creates an empty `SyncStHashMap` which will use the given hash builder
+    /// to hash keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::hash_map::RandomState;
+    /// use strudel::SyncStHashMap;
+    ///
+    /// let s = RandomState::new();
+    /// let map = SyncStHashMap::with_hasher(s);
+    /// map.insert(1, 2);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::from(StHashMap::with_hasher(hash_builder))
+    }
+
+    /// Locks the map, blocking the current thread until the lock is acquired,
+    /// and returns a guard which dereferences to the underlying
+    /// [`StHashMap`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::SyncStHashMap;
+    ///
+    /// let map = SyncStHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    ///
+    /// let guard = map.lock();
+    /// assert_eq!(guard.keys().copied().collect::<Vec<_>>(), vec!["a", "b"]);
+    /// ```
+    #[inline]
+    pub fn lock(&self) -> MutexGuard<'_, StHashMap<K, V, S>> {
+        self.map.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Consumes this handle, returning the underlying [`StHashMap`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::SyncStHashMap;
+    ///
+    /// let map = SyncStHashMap::new();
+    /// map.insert("a", 1);
+    /// let map = map.into_inner();
+    /// assert_eq!(map.get(&"a"), Some(&1));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> StHashMap<K, V, S> {
+        self.map
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns `true` if a thread panicked while holding the lock.
+    ///
+    /// The map may be inconsistent after such a panic. Check this before
+    /// trusting the contents of a map shared with threads that may have
+    /// panicked, including before [`SyncStHashMap::into_inner`], which does
+    /// not report poisoning.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use strudel::SyncStHashMap;
+    ///
+    /// let map = Arc::new(SyncStHashMap::new());
+    /// map.insert("a", 1);
+    /// assert!(!map.is_poisoned());
+    ///
+    /// let panicking = Arc::clone(&map);
+    /// let _ = thread::spawn(move || {
+    ///     let _guard = panicking.lock();
+    ///     panic!("poison the map");
+    /// })
+    /// .join();
+    /// assert!(map.is_poisoned());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn is_poisoned(&self) -> bool {
+        self.map.is_poisoned()
+    }
+
+    /// Clears the poisoned state of the map.
+    ///
+    /// Call this once the map has been checked or repaired after a thread
+    /// panicked while holding the lock. See [`SyncStHashMap::is_poisoned`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use strudel::SyncStHashMap;
+    ///
+    /// let map = Arc::new(SyncStHashMap::new());
+    /// let panicking = Arc::clone(&map);
+    /// let _ = thread::spawn(move || {
+    ///     let mut guard = panicking.lock();
+    ///     guard.insert("a", 1);
+    ///     panic!("poison the map");
+    /// })
+    /// .join();
+    ///
+    /// assert!(map.is_poisoned());
+    /// map.lock().clear();
+    /// map.clear_poison();
+    /// assert!(!map.is_poisoned());
+    /// ```
+    #[inline]
+    pub fn clear_poison(&self) {
+        self.map.clear_poison();
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::SyncStHashMap;
+    ///
+    /// let map = SyncStHashMap::new();
+    /// assert_eq!(map.len(), 0);
+    /// map.insert(1, "a");
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::SyncStHashMap;
+    ///
+    /// let map = SyncStHashMap::new();
+    /// assert!(map.is_empty());
+    /// map.insert(1, "a");
+    /// assert!(!map.is_empty());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+}
+
+impl<K, V, S> SyncStHashMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns a copy of the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::SyncStHashMap;
+    ///
+    /// let map = SyncStHashMap::new();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.get(&1), Some("a"));
+    /// assert_eq!(map.get(&2), None);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.lock().get(key).cloned()
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::SyncStHashMap;
+    ///
+    /// let map = SyncStHashMap::new();
+    /// map.insert(1, "a");
+    /// assert!(map.contains_key(&1));
+    /// assert!(!map.contains_key(&2));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.lock().contains_key(key)
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key
+    /// was previously in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::SyncStHashMap;
+    ///
+    /// let map = SyncStHashMap::new();
+    /// map.insert(1, "a");
+    /// assert_eq!(map.remove(&1), Some("a"));
+    /// assert_eq!(map.remove(&1), None);
+    /// ```
+    #[inline]
+    #[allow(clippy::must_use_candidate)]
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.lock().remove(key)
+    }
+}
+
+impl<K, V, S> SyncStHashMap<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    /// Inserts a key-value pair into the map, returning the old value if the
+    /// key was already present. See [`StHashMap::insert`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the map and the map already holds
+    /// `u32::MAX + 1` entries. See [`StHashMap`] for this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use strudel::SyncStHashMap;
+    ///
+    /// let map = SyncStHashMap::new();
+    /// assert_eq!(map.insert(37, "a"), None);
+    /// assert_eq!(map.insert(37, "b"), Some("a"));
+    /// assert_eq!(map.get(&37), Some("b"));
+    /// ```
+    #[inline]
+    #[allow(clippy::must_use_candidate)]
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.lock().insert(key, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::SyncStHashMap;
+
+    #[test]
+    fn concurrent_inserts_and_removes() {
+        let map = Arc::new(SyncStHashMap::new());
+        let handles = (0..8_usize)
+            .map(|thread| {
+                let map = Arc::clone(&map);
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        let key = thread * 1000 + i;
+                        assert_eq!(map.insert(key, thread), None);
+                        if i % 2 == 1 {
+                            assert_eq!(map.remove(&key), Some(thread));
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let map = Arc::try_unwrap(map).unwrap().into_inner();
+        assert_eq!(map.len(), 4000);
+        for thread in 0..8 {
+            // Each thread's inserts are ordered relative to each other.
+            let keys = map
+                .keys()
+                .copied()
+                .filter(|key| key / 1000 == thread)
+                .collect::<Vec<_>>();
+            let expected = (0..1000)
+                .step_by(2)
+                .map(|i| thread * 1000 + i)
+                .collect::<Vec<_>>();
+            assert_eq!(keys, expected);
+        }
+    }
+
+    #[test]
+    fn panic_while_locked_marks_map_poisoned() {
+        let map = Arc::new(SyncStHashMap::new());
+        let _ = map.insert(1, 10);
+        let panicking = Arc::clone(&map);
+        let result = thread::spawn(move || {
+            let mut guard = panicking.lock();
+            let _ = guard.insert(2, 20);
+            panic!("poison the lock");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(map.is_poisoned());
+        assert_eq!(map.get(&1), Some(10));
+        assert_eq!(map.get(&2), Some(20));
+
+        map.clear_poison();
+        assert!(!map.is_poisoned());
+    }
+}
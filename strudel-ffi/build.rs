@@ -0,0 +1,48 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "capi")]
+    capi::generate_header();
+}
+
+#[cfg(feature = "capi")]
+mod capi {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Generate `$OUT_DIR/st.h` from the `#[no_mangle]` functions and
+    /// `#[repr(C)]` types exported by this crate.
+    ///
+    /// If `STRUDEL_CAPI_INCLUDE_DIR` is set, the header is also copied into
+    /// that directory.
+    pub fn generate_header() {
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        println!("cargo:rerun-if-changed=src");
+        println!("cargo:rerun-if-env-changed=STRUDEL_CAPI_INCLUDE_DIR");
+
+        let crate_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+        let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+        let pointer_width = env::var("CARGO_CFG_TARGET_POINTER_WIDTH").unwrap();
+
+        let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml"))
+            .expect("cbindgen.toml is a valid cbindgen config");
+        // `st_table` padding depends on the target pointer width. The header
+        // is generated for a single target, so select its layout up front.
+        let target_define = format!("#define STRUDEL_POINTER_WIDTH_{pointer_width}");
+
+        let header = out_dir.join("st.h");
+        cbindgen::Builder::new()
+            .with_crate(&crate_dir)
+            .with_config(config)
+            .with_after_include(target_define)
+            .generate()
+            .expect("strudel-ffi exports a C API cbindgen can describe")
+            .write_to_file(&header);
+
+        if let Some(include_dir) = env::var_os("STRUDEL_CAPI_INCLUDE_DIR") {
+            let include_dir = PathBuf::from(include_dir);
+            fs::create_dir_all(&include_dir).expect("create STRUDEL_CAPI_INCLUDE_DIR");
+            fs::copy(&header, include_dir.join("st.h")).expect("install st.h");
+        }
+    }
+}
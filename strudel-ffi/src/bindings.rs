@@ -1,8 +1,8 @@
 use std::os::raw::c_int;
 
-use crate::primitives::{st_data_t, st_index_t};
+use crate::primitives::{st_data_t, st_hash_t, st_index_t};
 
-/// Equality comparator function for `StHash` keys.
+/// Equality comparator function for `StTable` keys.
 ///
 /// # Header declaration
 ///
@@ -11,7 +11,7 @@ use crate::primitives::{st_data_t, st_index_t};
 /// ```
 pub type st_compare_func = unsafe extern "C" fn(st_data_t, st_data_t) -> c_int;
 
-/// Hash function for `StHash` keys.
+/// Hash function for `StTable` keys.
 ///
 /// # Header declaration
 ///
@@ -28,9 +28,11 @@ pub type st_hash_func = unsafe extern "C" fn(st_data_t) -> st_index_t;
 /// # Safety
 ///
 /// `st_hash_type` are expected to have `'static` lifetime. This assumption is
-/// exploited by [`StHashMap`] and [`StBuildHasher`].
+/// exploited by [`StHashMap`], `StBuildHasher` and `KeyBuildHasher`.
+///
+/// [`StHashMap`]: strudel::StHashMap
 #[repr(C)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy)]
 pub struct st_hash_type {
     /// `st_compare_func`
     ///
@@ -74,6 +76,12 @@ pub enum st_retval {
     /// [`st_foreach`]: crate::ffi::st_foreach
     /// [`st_foreach_check`]: crate::ffi::st_foreach_check
     ST_CHECK,
+
+    /// Replace the current iteration `(key, value)` pair by calling the replace
+    /// callback passed to [`st_foreach_with_replace`].
+    ///
+    /// [`st_foreach_with_replace`]: crate::ffi::st_foreach_with_replace
+    ST_REPLACE,
 }
 
 impl PartialEq<i32> for st_retval {
@@ -112,3 +120,30 @@ pub type st_update_callback_func =
 /// [`st_foreach_check`]: crate::ffi::st_foreach_check
 pub type st_foreach_callback_func =
     unsafe extern "C" fn(st_data_t, st_data_t, st_data_t, c_int) -> c_int;
+
+/// [`st_foreach_check`] and [`st_foreach_with_replace`] callback function.
+///
+/// # Header declaration
+///
+/// ```c
+/// typedef int st_foreach_check_callback_func(st_data_t, st_data_t, st_data_t, int);
+/// ```
+///
+/// [`st_foreach_check`]: crate::ffi::st_foreach_check
+/// [`st_foreach_with_replace`]: crate::ffi::st_foreach_with_replace
+pub type st_foreach_check_callback_func =
+    unsafe extern "C" fn(st_data_t, st_data_t, st_data_t, c_int) -> c_int;
+
+/// [`st_foreach_with_hash`] callback function.
+///
+/// The third argument is the hash stored with the entry.
+///
+/// # Header declaration
+///
+/// ```c
+/// typedef int st_foreach_hash_callback_func(st_data_t, st_data_t, st_hash_t, st_data_t, int);
+/// ```
+///
+/// [`st_foreach_with_hash`]: crate::ffi::st_foreach_with_hash
+pub type st_foreach_hash_callback_func =
+    unsafe extern "C" fn(st_data_t, st_data_t, st_hash_t, st_data_t, c_int) -> c_int;
@@ -8,15 +8,52 @@
 //! These functions are callable from C by including `st.h` and linking in
 //! `libstrudel`.
 //!
+//! `st_hash`, `st_hash_uint32`, `st_hash_uint`, `st_hash_start` and
+//! `st_hash_end` use FNV by default. Enabling the **hash-siphash** Cargo
+//! feature switches them to SipHash-2-4.
+//!
+//! Enabling the **rb-symbols** Cargo feature additionally exports every `st_*`
+//! function under the `rb_st_*` name used by the `st.h` of Ruby 2.7 and later.
+//!
+//! # Panics in callbacks
+//!
+//! Callbacks passed to functions like `st_foreach`, `st_update` and
+//! `st_insert2` use the `"C"` ABI, which cannot unwind. A callback implemented
+//! in Rust that panics aborts the process at its own boundary instead of
+//! unwinding into strudel, and a panic inside one of these exported functions
+//! aborts the same way. A panic never leaves an `st_table` half-updated and
+//! observable by the caller.
+//!
 //! [`StHashMap`]: strudel::StHashMap
 
 use core::ffi::c_void;
-use std::os::raw::c_int;
+use std::os::raw::{c_int, c_long};
 
-use crate::bindings::{st_foreach_callback_func, st_hash_type, st_update_callback_func};
+use crate::bindings::{
+    st_foreach_callback_func, st_foreach_check_callback_func, st_foreach_hash_callback_func,
+    st_hash_type, st_update_callback_func,
+};
 use crate::primitives::{st_data_t, st_hash_t, st_index_t};
 use crate::st_table::ffi::st_table;
 
+/// Export `rb_st_*` aliases of `st_*` functions.
+///
+/// Ruby 2.7 and later rename the public `st_*` functions to `rb_st_*` with
+/// `#define`s in `st.h` to avoid symbol clashes, so extensions compiled against
+/// a newer Ruby link against the `rb_st_*` names. Each alias forwards to the
+/// `st_*` function of the same signature in scope.
+#[cfg(feature = "rb-symbols")]
+macro_rules! rb_st_aliases {
+    ($(fn $alias:ident => $target:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)?;)*) => {
+        $(
+            #[no_mangle]
+            unsafe extern "C" fn $alias($($arg: $ty),*) $(-> $ret)? {
+                $target($($arg),*)
+            }
+        )*
+    };
+}
+
 mod imp;
 mod init;
 
@@ -30,6 +67,19 @@ unsafe extern "C" fn st_init_table(hash_type: *const st_hash_type) -> *mut st_ta
     imp::st_init_table(hash_type)
 }
 
+/// # Header declaration
+///
+/// ```c
+/// st_table *st_init_existing_table(st_table *tab, const struct st_hash_type *type);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_init_existing_table(
+    table: *mut st_table,
+    hash_type: *const st_hash_type,
+) -> *mut st_table {
+    imp::st_init_existing_table(table, hash_type)
+}
+
 /// # Header declaration
 ///
 /// ```c
@@ -43,6 +93,19 @@ unsafe extern "C" fn st_init_table_with_size(
     imp::st_init_table_with_size(hash_type, size)
 }
 
+/// # Header declaration
+///
+/// ```c
+/// st_table *st_init_table_with_pow2_size(const struct st_hash_type *, st_index_t);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_init_table_with_pow2_size(
+    hash_type: *const st_hash_type,
+    size: st_index_t,
+) -> *mut st_table {
+    imp::st_init_table_with_pow2_size(hash_type, size)
+}
+
 /// # Header declaration
 ///
 /// ```c
@@ -96,6 +159,51 @@ unsafe extern "C" fn st_insert(table: *mut st_table, key: st_data_t, value: st_d
     imp::st_insert(table, key, value)
 }
 
+/// # Header declaration
+///
+/// ```c
+/// int st_insert_with_hash(st_table *tab, st_data_t key, st_data_t value, st_hash_t hash);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_insert_with_hash(
+    table: *mut st_table,
+    key: st_data_t,
+    value: st_data_t,
+    hash: st_hash_t,
+) -> c_int {
+    imp::st_insert_with_hash(table, key, value, hash)
+}
+
+/// # Header declaration
+///
+/// ```c
+/// void st_insert_many(st_table *tab, const st_data_t *keys, const st_data_t *values, st_index_t n);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_insert_many(
+    table: *mut st_table,
+    keys: *const st_data_t,
+    values: *const st_data_t,
+    n: st_index_t,
+) {
+    imp::st_insert_many(table, keys, values, n);
+}
+
+/// # Header declaration
+///
+/// ```c
+/// int st_replace(st_table *tab, st_data_t key, st_data_t value, st_data_t *old_value);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_replace(
+    table: *mut st_table,
+    key: st_data_t,
+    value: st_data_t,
+    old_value: *mut st_data_t,
+) -> c_int {
+    imp::st_replace(table, key, value, old_value)
+}
+
 /// # Header declaration
 ///
 /// ```c
@@ -125,6 +233,21 @@ unsafe extern "C" fn st_lookup(
     imp::st_lookup(table, key, value)
 }
 
+/// # Header declaration
+///
+/// ```c
+/// int st_lookup_with_hash(st_table *tab, st_data_t key, st_hash_t hash, st_data_t *value);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_lookup_with_hash(
+    table: *mut st_table,
+    key: st_data_t,
+    hash: st_hash_t,
+    value: *mut st_data_t,
+) -> c_int {
+    imp::st_lookup_with_hash(table, key, hash, value)
+}
+
 /// # Header declaration
 ///
 /// ```c
@@ -183,6 +306,35 @@ unsafe extern "C" fn st_foreach_check(
     imp::st_foreach_check(table, func, arg, never)
 }
 
+/// # Header declaration
+///
+/// ```c
+/// int st_foreach_with_replace(st_table *tab, st_foreach_check_callback_func *func, st_update_callback_func *replace, st_data_t arg);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_foreach_with_replace(
+    table: *mut st_table,
+    func: st_foreach_check_callback_func,
+    replace: st_update_callback_func,
+    arg: st_data_t,
+) -> c_int {
+    imp::st_foreach_with_replace(table, func, replace, arg)
+}
+
+/// # Header declaration
+///
+/// ```c
+/// int st_foreach_with_hash(st_table *tab, st_foreach_hash_callback_func *func, st_data_t arg);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_foreach_with_hash(
+    table: *mut st_table,
+    func: st_foreach_hash_callback_func,
+    arg: st_data_t,
+) -> c_int {
+    imp::st_foreach_with_hash(table, func, arg)
+}
+
 /// # Header declaration
 ///
 /// ```c
@@ -241,6 +393,36 @@ unsafe extern "C" fn st_values_check(
     imp::st_values_check(table, values, size, never)
 }
 
+/// # Header declaration
+///
+/// ```c
+/// st_data_t *st_bulk_keys(st_table *table, st_index_t *len);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_bulk_keys(table: *mut st_table, len: *mut st_index_t) -> *mut st_data_t {
+    imp::st_bulk_keys(table, len)
+}
+
+/// # Header declaration
+///
+/// ```c
+/// st_data_t *st_bulk_values(st_table *table, st_index_t *len);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_bulk_values(table: *mut st_table, len: *mut st_index_t) -> *mut st_data_t {
+    imp::st_bulk_values(table, len)
+}
+
+/// # Header declaration
+///
+/// ```c
+/// void st_free_array(st_data_t *array);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_free_array(array: *mut st_data_t) {
+    imp::st_free_array(array);
+}
+
 /// # Header declaration
 ///
 /// ```c
@@ -263,8 +445,7 @@ unsafe extern "C" fn st_add_direct_with_hash(
     value: st_data_t,
     hash: st_hash_t,
 ) {
-    let _ = hash;
-    imp::st_add_direct(table, key, value);
+    imp::st_add_direct_with_hash(table, key, value, hash);
 }
 
 /// # Header declaration
@@ -287,6 +468,16 @@ unsafe extern "C" fn st_cleanup_safe(table: *mut st_table, never: st_data_t) {
     imp::st_cleanup_safe(table, never);
 }
 
+/// # Header declaration
+///
+/// ```c
+/// void st_rehash(st_table *);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_rehash(table: *mut st_table) {
+    imp::st_rehash(table);
+}
+
 /// # Header declaration
 ///
 /// ```c
@@ -297,6 +488,16 @@ unsafe extern "C" fn st_clear(table: *mut st_table) {
     imp::st_clear(table);
 }
 
+/// # Header declaration
+///
+/// ```c
+/// void st_clear_and_shrink(st_table *);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_clear_and_shrink(table: *mut st_table) {
+    imp::st_clear_and_shrink(table);
+}
+
 /// # Header declaration
 ///
 /// ```c
@@ -317,6 +518,16 @@ unsafe extern "C" fn st_memsize(table: *const st_table) -> libc::size_t {
     imp::st_memsize(table.cast_mut())
 }
 
+/// # Header declaration
+///
+/// ```c
+/// st_index_t st_compact(st_table *);
+/// ```
+#[no_mangle]
+unsafe extern "C" fn st_compact(table: *mut st_table) -> st_index_t {
+    imp::st_compact(table)
+}
+
 /// # Header declaration
 ///
 /// ```c
@@ -367,4 +578,63 @@ unsafe extern "C" fn st_hash_start(h: st_index_t) -> st_index_t {
     imp::st_hash_start(h)
 }
 
-// void rb_hash_bulk_insert_into_st_table(long, const VALUE *, VALUE);
+/// # Header declaration
+///
+/// ```c
+/// void rb_hash_bulk_insert_into_st_table(long, const VALUE *, VALUE);
+/// ```
+#[no_mangle]
+#[allow(clippy::similar_names)]
+unsafe extern "C" fn rb_hash_bulk_insert_into_st_table(
+    argc: c_long,
+    argv: *const st_data_t,
+    hash: st_data_t,
+) {
+    imp::rb_hash_bulk_insert_into_st_table(argc, argv, hash);
+}
+
+#[cfg(feature = "rb-symbols")]
+rb_st_aliases! {
+    fn rb_st_init_table => st_init_table(hash_type: *const st_hash_type) -> *mut st_table;
+    fn rb_st_init_existing_table => st_init_existing_table(table: *mut st_table, hash_type: *const st_hash_type) -> *mut st_table;
+    fn rb_st_init_table_with_size => st_init_table_with_size(hash_type: *const st_hash_type, size: st_index_t) -> *mut st_table;
+    fn rb_st_init_table_with_pow2_size => st_init_table_with_pow2_size(hash_type: *const st_hash_type, size: st_index_t) -> *mut st_table;
+    fn rb_st_delete => st_delete(table: *mut st_table, key: *mut st_data_t, value: *mut st_data_t) -> c_int;
+    fn rb_st_delete_safe => st_delete_safe(table: *mut st_table, key: *mut st_data_t, value: *mut st_data_t, never: *const st_data_t) -> c_int;
+    fn rb_st_shift => st_shift(table: *mut st_table, key: *mut st_data_t, value: *mut st_data_t) -> c_int;
+    fn rb_st_insert => st_insert(table: *mut st_table, key: st_data_t, value: st_data_t) -> c_int;
+    fn rb_st_insert_with_hash => st_insert_with_hash(table: *mut st_table, key: st_data_t, value: st_data_t, hash: st_hash_t) -> c_int;
+    fn rb_st_insert_many => st_insert_many(table: *mut st_table, keys: *const st_data_t, values: *const st_data_t, n: st_index_t);
+    fn rb_st_replace => st_replace(table: *mut st_table, key: st_data_t, value: st_data_t, old_value: *mut st_data_t) -> c_int;
+    fn rb_st_insert2 => st_insert2(table: *mut st_table, key: st_data_t, value: st_data_t, func: unsafe extern "C" fn(st_data_t) -> st_data_t) -> c_int;
+    fn rb_st_lookup => st_lookup(table: *mut st_table, key: st_data_t, value: *mut st_data_t) -> c_int;
+    fn rb_st_lookup_with_hash => st_lookup_with_hash(table: *mut st_table, key: st_data_t, hash: st_hash_t, value: *mut st_data_t) -> c_int;
+    fn rb_st_get_key => st_get_key(table: *mut st_table, key: st_data_t, result: *mut st_data_t) -> c_int;
+    fn rb_st_update => st_update(table: *mut st_table, key: st_data_t, func: st_update_callback_func, arg: st_data_t) -> c_int;
+    fn rb_st_foreach => st_foreach(table: *mut st_table, func: st_foreach_callback_func, arg: st_data_t) -> c_int;
+    fn rb_st_foreach_check => st_foreach_check(table: *mut st_table, func: st_foreach_callback_func, arg: st_data_t, never: st_data_t) -> c_int;
+    fn rb_st_foreach_with_replace => st_foreach_with_replace(table: *mut st_table, func: st_foreach_check_callback_func, replace: st_update_callback_func, arg: st_data_t) -> c_int;
+    fn rb_st_foreach_with_hash => st_foreach_with_hash(table: *mut st_table, func: st_foreach_hash_callback_func, arg: st_data_t) -> c_int;
+    fn rb_st_keys => st_keys(table: *mut st_table, keys: *mut st_data_t, size: st_index_t) -> st_index_t;
+    fn rb_st_keys_check => st_keys_check(table: *mut st_table, keys: *mut st_data_t, size: st_index_t, never: st_data_t) -> st_index_t;
+    fn rb_st_values => st_values(table: *mut st_table, values: *mut st_data_t, size: st_index_t) -> st_index_t;
+    fn rb_st_values_check => st_values_check(table: *mut st_table, values: *mut st_data_t, size: st_index_t, never: st_data_t) -> st_index_t;
+    fn rb_st_bulk_keys => st_bulk_keys(table: *mut st_table, len: *mut st_index_t) -> *mut st_data_t;
+    fn rb_st_bulk_values => st_bulk_values(table: *mut st_table, len: *mut st_index_t) -> *mut st_data_t;
+    fn rb_st_free_array => st_free_array(array: *mut st_data_t);
+    fn rb_st_add_direct => st_add_direct(table: *mut st_table, key: st_data_t, value: st_data_t);
+    fn rb_st_add_direct_with_hash => st_add_direct_with_hash(table: *mut st_table, key: st_data_t, value: st_data_t, hash: st_hash_t);
+    fn rb_st_free_table => st_free_table(table: *mut st_table);
+    fn rb_st_cleanup_safe => st_cleanup_safe(table: *mut st_table, never: st_data_t);
+    fn rb_st_rehash => st_rehash(table: *mut st_table);
+    fn rb_st_clear => st_clear(table: *mut st_table);
+    fn rb_st_clear_and_shrink => st_clear_and_shrink(table: *mut st_table);
+    fn rb_st_copy => st_copy(table: *mut st_table) -> *mut st_table;
+    fn rb_st_memsize => st_memsize(table: *const st_table) -> libc::size_t;
+    fn rb_st_compact => st_compact(table: *mut st_table) -> st_index_t;
+    fn rb_st_hash => st_hash(ptr: *const c_void, len: libc::size_t, h: st_index_t) -> st_index_t;
+    fn rb_st_hash_uint32 => st_hash_uint32(h: st_index_t, i: u32) -> st_index_t;
+    fn rb_st_hash_uint => st_hash_uint(h: st_index_t, i: st_index_t) -> st_index_t;
+    fn rb_st_hash_end => st_hash_end(h: st_index_t) -> st_index_t;
+    fn rb_st_hash_start => st_hash_start(h: st_index_t) -> st_index_t;
+}
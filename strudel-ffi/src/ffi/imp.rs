@@ -2,22 +2,28 @@
 //!
 //! [`StHashMap`]: strudel::StHashMap
 
+use core::convert::TryFrom;
 use core::ffi::c_void;
 use core::hash::Hasher;
-use core::mem;
 use core::ptr;
 use core::slice;
-use std::os::raw::c_int;
+use std::alloc::{handle_alloc_error, Layout};
+use std::os::raw::{c_int, c_long};
 
-use fnv::FnvHasher;
-
-use crate::bindings::{st_foreach_callback_func, st_hash_type, st_retval, st_update_callback_func};
-use crate::primitives::{st_data_t, st_index_t};
+use crate::bindings::{
+    st_foreach_callback_func, st_foreach_check_callback_func, st_foreach_hash_callback_func,
+    st_hash_type, st_retval, st_update_callback_func,
+};
+use crate::hasher::{default_hasher, keyed_hasher};
+use crate::primitives::{st_data_t, st_hash_t, st_index_t};
 use crate::st_table::ffi::st_table;
 use crate::st_table::StTable;
 
 const DEFAULT_CAPACITY: usize = 8;
 
+#[cfg(not(any(feature = "hash-fnv", feature = "hash-siphash")))]
+compile_error!("strudel-ffi requires one of the `hash-fnv` or `hash-siphash` features");
+
 /// Create and return table with `type` which can hold a minimal number of
 /// entries.
 ///
@@ -49,6 +55,58 @@ pub fn st_init_table_with_size(hash_type: *const st_hash_type, size: st_index_t)
     st_table::into_raw(table.into())
 }
 
+/// Create and return table with `type` which can hold at least `size` entries
+/// rounded up to the next power of two.
+///
+/// Unlike [`st_init_table_with_size`], the insertion list is allocated with
+/// exactly the rounded capacity, so the `entry_power` and `bin_power` fields
+/// of the returned table match the sizing of MRI's `st.c`.
+///
+/// # Header declaration
+///
+/// ```c
+/// st_table *st_init_table_with_pow2_size(const struct st_hash_type *, st_index_t);
+/// ```
+#[inline]
+#[must_use]
+pub fn st_init_table_with_pow2_size(
+    hash_type: *const st_hash_type,
+    size: st_index_t,
+) -> *mut st_table {
+    let table = StTable::with_pow2_capacity_and_hash_type(size.into(), hash_type);
+    st_table::into_raw(table.into())
+}
+
+/// Reinitialize existing table `table` as an empty table with `type` and
+/// return it.
+///
+/// # Notes
+///
+/// This function reuses the `st_table` allocation, so the returned pointer is
+/// always `table`. Existing entries are dropped without calling back into C.
+///
+/// # Header declaration
+///
+/// ```c
+/// st_table *st_init_existing_table(st_table *tab, const struct st_hash_type *type);
+/// ```
+///
+/// # Safety
+///
+/// `table` must be non-null and point to a valid `st_table` created by the
+/// `st_init_table` family of functions. The caller retains ownership of
+/// `table` and must eventually free it with `st_free_table`. `hash_type` must
+/// be non-null and have `'static` lifetime.
+#[inline]
+pub unsafe fn st_init_existing_table(
+    table: *mut st_table,
+    hash_type: *const st_hash_type,
+) -> *mut st_table {
+    let mut existing = st_table::from_raw(table);
+    existing.reinit(hash_type);
+    table
+}
+
 /// Delete entry with `key` from table `table`.
 ///
 /// Set up `*VALUE` (unless `VALUE` is zero) from deleted table entry, and
@@ -170,6 +228,128 @@ pub unsafe fn st_insert(table: *mut st_table, key: st_data_t, value: st_data_t)
     (*inner).insert_raw(key, value).is_some().into()
 }
 
+/// Insert (KEY, VALUE, HASH) into table TAB and return zero. If there is
+/// already entry with KEY in the table, return nonzero and and update the value
+/// of the found entry.
+///
+/// # Notes
+///
+/// `hash` is used in place of the table's hash function. A newly inserted entry
+/// stores `hash` with its key.
+///
+/// # Header declaration
+///
+/// ```c
+/// int st_insert_with_hash(st_table *tab, st_data_t key, st_data_t value, st_hash_t hash);
+/// ```
+///
+/// # Safety
+///
+/// `table` must be non-null and point to a valid `st_table` created by the
+/// `st_init_table` family of functions.
+#[inline]
+pub unsafe fn st_insert_with_hash(
+    table: *mut st_table,
+    key: st_data_t,
+    value: st_data_t,
+    hash: st_hash_t,
+) -> c_int {
+    let mut table = st_table::from_raw(table);
+    let inner = table.as_inner_mut();
+
+    (*inner)
+        .insert_with_hash_raw(key, value, hash)
+        .is_some()
+        .into()
+}
+
+/// Insert the `n` pairs (`keys[i]`, `values[i]`) into table `table` in array
+/// order.
+///
+/// # Notes
+///
+/// This function reserves space for `n` new entries before inserting and syncs
+/// `num_entries` once after the last pair is inserted.
+///
+/// Like [`st_insert`], if a key is already in the table, or appears more than
+/// once in `keys`, the last value wins and the entry keeps the insertion rank
+/// of its first occurrence.
+///
+/// # Header declaration
+///
+/// ```c
+/// void st_insert_many(st_table *tab, const st_data_t *keys, const st_data_t *values, st_index_t n);
+/// ```
+///
+/// # Safety
+///
+/// `table` must be non-null and point to a valid `st_table` created by the
+/// `st_init_table` family of functions.
+///
+/// `keys` and `values` must each point to an array of at least `n` elements.
+/// `keys` and `values` may be null if `n` is zero.
+#[inline]
+pub unsafe fn st_insert_many(
+    table: *mut st_table,
+    keys: *const st_data_t,
+    values: *const st_data_t,
+    n: st_index_t,
+) {
+    let n = usize::from(n);
+    if n == 0 {
+        return;
+    }
+    let mut table = st_table::from_raw(table);
+    let inner = table.as_inner_mut();
+
+    let keys = slice::from_raw_parts(keys, n);
+    let values = slice::from_raw_parts(values, n);
+    (*inner).inner.reserve(n);
+    for (&key, &value) in keys.iter().zip(values) {
+        let _ = (*inner).insert_raw(key, value);
+    }
+}
+
+/// Insert (`key`, `value`) into table `table`. If there is already entry with
+/// `key` in the table, set up `*OLD_VALUE` (unless `OLD_VALUE` is zero) to the
+/// previous value of the found entry and return nonzero. Otherwise, return
+/// zero.
+///
+/// # Notes
+///
+/// This function has no equivalent in MRI. A replaced entry keeps its
+/// insertion rank.
+///
+/// # Header declaration
+///
+/// ```c
+/// int st_replace(st_table *tab, st_data_t key, st_data_t value, st_data_t *old_value);
+/// ```
+///
+/// # Safety
+///
+/// `table` must be non-null and point to a valid `st_table` created by the
+/// `st_init_table` family of functions.
+#[inline]
+pub unsafe fn st_replace(
+    table: *mut st_table,
+    key: st_data_t,
+    value: st_data_t,
+    old_value: *mut st_data_t,
+) -> c_int {
+    let mut table = st_table::from_raw(table);
+    let inner = table.as_inner_mut();
+
+    if let Some(previous) = (*inner).insert_raw(key, value) {
+        if !old_value.is_null() {
+            ptr::write(old_value, previous);
+        }
+        1
+    } else {
+        0
+    }
+}
+
 /// Insert (FUNC(KEY), VALUE) into table TAB and return zero. If there is
 /// already entry with KEY in the table, return nonzero and and update the value
 /// of the found entry.
@@ -244,6 +424,43 @@ pub unsafe fn st_lookup(table: *mut st_table, key: st_data_t, value: *mut st_dat
     }
 }
 
+/// Find an entry with `key` and precomputed `hash` in table `table`. Return
+/// non-zero if we found it. Set up `*VALUE` to the found entry record.
+///
+/// # Notes
+///
+/// `hash` is used in place of the table's hash function.
+///
+/// # Header declaration
+///
+/// ```c
+/// int st_lookup_with_hash(st_table *tab, st_data_t key, st_hash_t hash, st_data_t *value);
+/// ```
+///
+/// # Safety
+///
+/// `table` must be non-null and point to a valid `st_table` created by the
+/// `st_init_table` family of functions.
+#[inline]
+pub unsafe fn st_lookup_with_hash(
+    table: *mut st_table,
+    key: st_data_t,
+    hash: st_hash_t,
+    value: *mut st_data_t,
+) -> c_int {
+    let mut table = st_table::from_raw(table);
+    let inner = table.as_inner_mut();
+
+    if let Some(&entry_value) = (*inner).get_with_hash_raw(key, hash) {
+        if !value.is_null() {
+            ptr::write(value, entry_value);
+        }
+        1
+    } else {
+        0
+    }
+}
+
 /// Find an entry with `key` in table `table`. Return non-zero if we found it.
 /// Set up `*RESULT` to the found table entry key.
 ///
@@ -314,8 +531,11 @@ pub unsafe fn st_update(
     let mut table = st_table::from_raw(table);
     let inner = table.as_inner_mut();
 
+    // Hash `key` once and reuse the hash for the lookup and for writing back or
+    // deleting the entry if `func` does not change the key.
+    let hash = (*inner).hash_raw(key);
     let (existing, mut key, mut value) =
-        if let Some((&entry_key, &entry_value)) = (*inner).get_key_value_raw(key) {
+        if let Some((&entry_key, &entry_value)) = (*inner).get_key_value_with_hash_raw(key, hash) {
             (true, entry_key, entry_value)
         } else {
             (false, key, 0_usize.into())
@@ -326,13 +546,18 @@ pub unsafe fn st_update(
 
     // `func` might mutate this table, so make sure we don't alias the `Box`.
     drop(table);
-    let update = func(&mut key, &mut value, arg, existing as c_int);
+    let update = func(
+        ptr::addr_of_mut!(key),
+        ptr::addr_of_mut!(value),
+        arg,
+        existing as c_int,
+    );
 
     if update == ST_CONTINUE {
         match (key, value) {
             (key, value) if existing && key == old_key && value == old_value => {}
             (key, value) if key == old_key => {
-                let _ = (*inner).insert_raw(key, value);
+                let _ = (*inner).insert_with_hash_raw(key, value, hash);
             }
             (key, value) => {
                 // In the MRI implementation, `st_add_direct_with_hash` is called in
@@ -341,10 +566,9 @@ pub unsafe fn st_update(
                 // > Insert (KEY, VALUE, HASH) into table TAB. The table should not
                 // > have entry with KEY before the insertion.
                 //
-                // Rust maps do not expose direct insert with hash APIs, so we go
-                // through the normal insert route. This is semantically different
-                // behavior because `hash` of `key` might have changed when calling
-                // `func`.
+                // We go through the normal insert route and rehash `key`. This is
+                // semantically different behavior because `hash` of `key` might
+                // have changed when calling `func`.
                 //
                 // # Header declaration
                 //
@@ -355,7 +579,7 @@ pub unsafe fn st_update(
             }
         }
     } else if update == ST_DELETE && existing {
-        let _ = (*inner).remove_raw(old_key);
+        let _ = (*inner).remove_with_hash_raw(old_key, hash);
     }
 
     // We can reuse `inner` above because it is guaranteed to not change for the
@@ -395,62 +619,7 @@ pub unsafe fn st_foreach(
     func: st_foreach_callback_func,
     arg: st_data_t,
 ) -> c_int {
-    use st_retval::{ST_CHECK, ST_CONTINUE, ST_DELETE, ST_STOP};
-
-    let table_raw = table;
-    let mut table = st_table::from_raw(table);
-    let inner = table.as_inner_mut();
-
-    let mut insertion_ranks = (*inner).inner.insert_ranks_from(0);
-    let mut last_seen_rank = 0;
-
-    // `func` might mutate this table, so make sure we don't alias the `Box`.
-    drop(table);
-
-    loop {
-        if let Some(rank) = insertion_ranks.next() {
-            last_seen_rank = rank;
-            let nth = (*inner)
-                .inner
-                .get_nth(rank)
-                .map(|(key, &value)| (*key.inner(), value));
-
-            if let Some((key, value)) = nth {
-                let retval = func(key, value, arg, 0);
-
-                match retval {
-                    retval if ST_CONTINUE == retval => {}
-                    retval if ST_CHECK == retval || ST_STOP == retval => return 0,
-                    retval if ST_DELETE == retval => {
-                        let _ = (*inner).remove_raw(key);
-                        // We can reuse `inner` above because it is guaranteed
-                        // to not change for the life of the table, but we reify
-                        // the table so we can repack its size and other metadata
-                        // into the FFI struct.
-                        //
-                        // We must do this since we are looping and `func` may be
-                        // called multiple times.
-                        drop(st_table::from_raw(table_raw));
-                    }
-                    _ => {}
-                }
-            }
-        } else {
-            let current_max = (*inner).inner.max_insert_rank();
-            if current_max <= last_seen_rank {
-                break;
-            }
-            insertion_ranks = (*inner).inner.insert_ranks_from(last_seen_rank);
-            // We can reuse `inner` above because it is guaranteed to not change
-            // for the life of the table, but we reify the table so we can repack
-            // its size and other metadata into the FFI struct.
-            //
-            // We must do this since we are looping and `func` may be called
-            // multiple times.
-            drop(st_table::from_raw(table_raw));
-        }
-    }
-    0
+    st_general_foreach(table, Callback::Plain(func), None, arg, false, None)
 }
 
 /// Traverse all entries in table `table` calling `func` with current entry key
@@ -461,7 +630,13 @@ pub unsafe fn st_foreach(
 ///
 /// The behavior is a bit different from [`st_foreach`] when `ST_CHECK` is
 /// returned from `func` and when the current element is removed during
-/// traversing.
+/// traversing:
+///
+/// - Entries whose key is `never` are skipped. `never` is the sentinel that
+///   previous implementations of `st_delete_safe` stored in deleted entries.
+/// - If `func` returns `ST_CHECK` after the current entry was removed from the
+///   table, `func` is called with zero key and value and a nonzero error
+///   argument and traversal stops with a return value of 1.
 ///
 /// # Header declaration
 ///
@@ -481,9 +656,118 @@ pub unsafe fn st_foreach_check(
     table: *mut st_table,
     func: st_foreach_callback_func,
     arg: st_data_t,
-    _never: st_data_t,
+    never: st_data_t,
+) -> c_int {
+    st_general_foreach(table, Callback::Plain(func), None, arg, true, Some(never))
+}
+
+/// Traverse all entries in table `table` like [`st_foreach_check`]. If `func`
+/// returns `ST_REPLACE`, call `replace` with pointers to the current entry key
+/// and value, store the possibly modified key and value back into the table in
+/// place, and then act on the return value of `replace`.
+///
+/// The key written by `replace` must be equal to the old key, i.e. the results
+/// of `hash()` are same and `compare()` returns 0. Replacing an entry
+/// maintains its insertion rank.
+///
+/// # Header declaration
+///
+/// ```c
+/// int st_foreach_with_replace(st_table *tab, st_foreach_check_callback_func *func, st_update_callback_func *replace, st_data_t arg);
+/// ```
+///
+/// # Safety
+///
+/// `table` must be non-null and point to a valid `st_table` created by the
+/// `st_init_table` family of functions.
+///
+/// `func` must be a callback function with the
+/// `st_foreach_check_callback_func` signature.
+///
+/// `replace` must be a callback function with the `st_update_callback_func`
+/// signature.
+#[inline]
+pub unsafe fn st_foreach_with_replace(
+    table: *mut st_table,
+    func: st_foreach_check_callback_func,
+    replace: st_update_callback_func,
+    arg: st_data_t,
+) -> c_int {
+    st_general_foreach(table, Callback::Plain(func), Some(replace), arg, true, None)
+}
+
+/// Traverse all entries in table `table` like [`st_foreach`], additionally
+/// passing the hash stored with the current entry to `func`.
+///
+/// The hash is the result of the table's `st_hash_type` hash function for the
+/// key, or the hash supplied when the entry was added with a `*_with_hash`
+/// function. Callers can use it to skip comparisons against keys with a
+/// different hash.
+///
+/// # Header declaration
+///
+/// ```c
+/// typedef int st_foreach_hash_callback_func(st_data_t, st_data_t, st_hash_t, st_data_t, int);
+/// int st_foreach_with_hash(st_table *tab, st_foreach_hash_callback_func *func, st_data_t arg);
+/// ```
+///
+/// # Safety
+///
+/// `table` must be non-null and point to a valid `st_table` created by the
+/// `st_init_table` family of functions.
+///
+/// `func` must be a callback function with the
+/// `st_foreach_hash_callback_func` signature.
+#[inline]
+pub unsafe fn st_foreach_with_hash(
+    table: *mut st_table,
+    func: st_foreach_hash_callback_func,
+    arg: st_data_t,
+) -> c_int {
+    st_general_foreach(table, Callback::WithHash(func), None, arg, false, None)
+}
+
+/// Callback invoked by [`st_general_foreach`] for each entry.
+#[derive(Clone, Copy)]
+enum Callback {
+    Plain(st_foreach_callback_func),
+    WithHash(st_foreach_hash_callback_func),
+}
+
+impl Callback {
+    #[inline]
+    unsafe fn call(
+        self,
+        key: st_data_t,
+        value: st_data_t,
+        hash: st_hash_t,
+        arg: st_data_t,
+        error: c_int,
+    ) -> c_int {
+        match self {
+            Self::Plain(func) => func(key, value, arg, error),
+            Self::WithHash(func) => func(key, value, hash, arg, error),
+        }
+    }
+}
+
+/// Shared traversal loop for the `st_foreach` family of functions.
+///
+/// Traversal is by insertion rank so `func` may safely mutate the table. If
+/// `check` is true, `ST_CHECK` continues traversal and a traversal that cannot
+/// be resumed reports an error by calling `func` with a nonzero error argument
+/// and returning 1. Otherwise, `ST_CHECK` stops traversal.
+///
+/// Entries whose key is `never` are skipped without calling `func`.
+unsafe fn st_general_foreach(
+    table: *mut st_table,
+    func: Callback,
+    replace: Option<st_update_callback_func>,
+    arg: st_data_t,
+    check: bool,
+    never: Option<st_data_t>,
 ) -> c_int {
-    use st_retval::{ST_CHECK, ST_CONTINUE, ST_DELETE, ST_STOP};
+    use st_retval::{ST_CHECK, ST_CONTINUE, ST_DELETE, ST_REPLACE, ST_STOP};
 
     let table_raw = table;
     let mut table = st_table::from_raw(table);
@@ -501,13 +785,68 @@ pub unsafe fn st_foreach_check(
             let nth = (*inner)
                 .inner
                 .get_nth(rank)
-                .map(|(key, &value)| (*key.inner(), value));
+                .map(|(key, &value)| (*key.inner(), value, key.hash()));
+
+            if let Some((mut key, value, hash)) = nth {
+                if never == Some(key) {
+                    continue;
+                }
+                let rebuilds = (*inner).inner.rebuilds();
+                let mut retval = func.call(key, value, hash, arg, 0);
+
+                // `func` may have inserted into the table and caused it to
+                // reclaim dead slots, which renumbers insertion ranks. Resume
+                // traversal from the new rank of the current entry.
+                if rebuilds != (*inner).inner.rebuilds() {
+                    if let Some(rank) = (*inner).insert_rank_raw(key) {
+                        last_seen_rank = rank;
+                        insertion_ranks = (*inner).inner.insert_ranks_from(rank + 1);
+                    } else {
+                        // The current entry was deleted so there is no
+                        // position to resume traversal from.
+                        drop(st_table::from_raw(table_raw));
+                        if check {
+                            let _ = func.call(
+                                st_data_t::default(),
+                                st_data_t::default(),
+                                st_hash_t::default(),
+                                arg,
+                                1,
+                            );
+                            return 1;
+                        }
+                        return 0;
+                    }
+                }
+
+                if let (true, Some(replace)) = (ST_REPLACE == retval, replace) {
+                    let mut value = value;
+                    retval = replace(ptr::addr_of_mut!(key), ptr::addr_of_mut!(value), arg, 1);
+                    // Write the replacement back in place to maintain the
+                    // insertion rank of the current entry.
+                    (*inner).update_raw(key, value);
+                    drop(st_table::from_raw(table_raw));
+                }
 
-            if let Some((key, value)) = nth {
-                let retval = func(key, value, arg, 0);
                 match retval {
-                    retval if ST_CONTINUE == retval || ST_CHECK == retval => {}
-                    retval if ST_STOP == retval => return 0,
+                    // Check whether `func` deleted the current entry.
+                    retval
+                        if ST_CHECK == retval
+                            && check
+                            && (*inner).insert_rank_raw(key) != Some(last_seen_rank) =>
+                    {
+                        drop(st_table::from_raw(table_raw));
+                        let _ = func.call(
+                            st_data_t::default(),
+                            st_data_t::default(),
+                            st_hash_t::default(),
+                            arg,
+                            1,
+                        );
+                        return 1;
+                    }
+                    retval if ST_CONTINUE == retval || (ST_CHECK == retval && check) => {}
+                    retval if ST_CHECK == retval || ST_STOP == retval => return 0,
                     retval if ST_DELETE == retval => {
                         let _ = (*inner).remove_raw(key);
                         // We can reuse `inner` above because it is guaranteed
@@ -523,9 +862,9 @@ pub unsafe fn st_foreach_check(
                 }
             }
         } else {
-            let current_max = (*inner).inner.max_insert_rank();
-            if current_max <= last_seen_rank {
-                break;
+            match (*inner).inner.max_insert_rank_opt() {
+                Some(current_max) if current_max > last_seen_rank => {}
+                _ => break,
             }
             insertion_ranks = (*inner).inner.insert_ranks_from(last_seen_rank);
             // We can reuse `inner` above because it is guaranteed to not change
@@ -561,12 +900,11 @@ pub unsafe fn st_keys(table: *mut st_table, keys: *mut st_data_t, size: st_index
     let inner = table.as_inner_mut();
 
     let keys = slice::from_raw_parts_mut(keys, size.into());
-    let mut count = 0;
-    for (counter, (slot, key)) in keys.iter_mut().zip((*inner).inner.keys()).enumerate() {
+    let mut count = 0_usize;
+    for (slot, key) in keys.iter_mut().zip((*inner).inner.keys()) {
         ptr::write(slot, *key.inner());
-        count = counter;
+        count += 1;
     }
-    mem::forget(table);
     count.into()
 }
 
@@ -618,11 +956,11 @@ pub unsafe fn st_values(
     let mut table = st_table::from_raw(table);
     let inner = table.as_inner_mut();
 
-    let keys = slice::from_raw_parts_mut(values, size.into());
-    let mut count = 0;
-    for (counter, (slot, &value)) in keys.iter_mut().zip((*inner).inner.values()).enumerate() {
+    let values = slice::from_raw_parts_mut(values, size.into());
+    let mut count = 0_usize;
+    for (slot, &value) in values.iter_mut().zip((*inner).inner.values()) {
         ptr::write(slot, value);
-        count = counter;
+        count += 1;
     }
     count.into()
 }
@@ -651,12 +989,108 @@ pub unsafe fn st_values_check(
     st_values(table, values, size)
 }
 
-/// Insert (`key`, `value`) into table `table`. The table should not have entry
-/// with `key` before the insertion.
+/// Return a newly allocated array holding all keys of table `table` in
+/// insertion order and set `*len` to the number of keys in the array.
 ///
 /// # Notes
 ///
-/// This implementation delegates to
+/// The returned array must be freed with [`st_free_array`]. If `table` is
+/// empty, this function returns null.
+///
+/// # Header declaration
+///
+/// ```c
+/// st_data_t *st_bulk_keys(st_table *table, st_index_t *len);
+/// ```
+///
+/// # Safety
+///
+/// `table` must be non-null and point to a valid `st_table` created by the
+/// `st_init_table` family of functions.
+///
+/// `len` must be non-null and point to an `st_index_t`.
+#[inline]
+pub unsafe fn st_bulk_keys(table: *mut st_table, len: *mut st_index_t) -> *mut st_data_t {
+    let mut table = st_table::from_raw(table);
+    let inner = table.as_inner_mut();
+
+    let keys = (*inner).inner.keys().map(|key| *key.inner());
+    alloc_array(keys, (*inner).len(), len)
+}
+
+/// Return a newly allocated array holding all values of table `table` in
+/// insertion order and set `*len` to the number of values in the array.
+///
+/// # Notes
+///
+/// The returned array must be freed with [`st_free_array`]. If `table` is
+/// empty, this function returns null.
+///
+/// # Header declaration
+///
+/// ```c
+/// st_data_t *st_bulk_values(st_table *table, st_index_t *len);
+/// ```
+///
+/// # Safety
+///
+/// `table` must be non-null and point to a valid `st_table` created by the
+/// `st_init_table` family of functions.
+///
+/// `len` must be non-null and point to an `st_index_t`.
+#[inline]
+pub unsafe fn st_bulk_values(table: *mut st_table, len: *mut st_index_t) -> *mut st_data_t {
+    let mut table = st_table::from_raw(table);
+    let inner = table.as_inner_mut();
+
+    let values = (*inner).inner.values().copied();
+    alloc_array(values, (*inner).len(), len)
+}
+
+/// Free an array returned by [`st_bulk_keys`] or [`st_bulk_values`].
+///
+/// # Header declaration
+///
+/// ```c
+/// void st_free_array(st_data_t *array);
+/// ```
+///
+/// # Safety
+///
+/// `array` must be null or a pointer returned by [`st_bulk_keys`] or
+/// [`st_bulk_values`] which has not already been freed.
+#[inline]
+pub unsafe fn st_free_array(array: *mut st_data_t) {
+    libc::free(array.cast::<c_void>());
+}
+
+/// Copy the `count` items of `items` into an array allocated with `malloc`
+/// and store `count` in `*len`.
+unsafe fn alloc_array<I>(items: I, count: usize, len: *mut st_index_t) -> *mut st_data_t
+where
+    I: Iterator<Item = st_data_t>,
+{
+    ptr::write(len, count.into());
+    if count == 0 {
+        return ptr::null_mut();
+    }
+    let layout = Layout::array::<st_data_t>(count).expect("capacity overflow");
+    let array = libc::malloc(layout.size()).cast::<st_data_t>();
+    if array.is_null() {
+        handle_alloc_error(layout);
+    }
+    for (offset, item) in items.take(count).enumerate() {
+        ptr::write(array.add(offset), item);
+    }
+    array
+}
+
+/// Insert (`key`, `value`) into table `table`. The table should not have entry
+/// with `key` before the insertion.
+///
+/// # Notes
+///
+/// This implementation delegates to
 /// [`StHashMap::insert`](crate::StHashMap::insert) directly.
 ///
 /// # Header declaration
@@ -688,8 +1122,44 @@ pub unsafe fn st_add_direct(table: *mut st_table, key: st_data_t, value: st_data
     let _ = (*inner).insert_raw(key, value);
 }
 
+/// Insert (`key`, `value`, `hash`) into table `table`. The table should not
+/// have entry with `key` before the insertion.
+///
+/// # Notes
+///
+/// `hash` is stored with `key` and is used instead of the table's hash
+/// function whenever the entry is hashed. Callers must ensure `hash` is the
+/// hash of `key` for lookups through the other `st_*` functions to find it.
+///
+/// # Header declaration
+///
+/// ```c
+/// void st_add_direct_with_hash(st_table *tab, st_data_t key, st_data_t value, st_hash_t hash)
+/// ```
+///
+/// # Safety
+///
+/// `table` must be non-null and point to a valid `st_table` created by the
+/// `st_init_table` family of functions.
+#[inline]
+pub unsafe fn st_add_direct_with_hash(
+    table: *mut st_table,
+    key: st_data_t,
+    value: st_data_t,
+    hash: st_hash_t,
+) {
+    let mut table = st_table::from_raw(table);
+    let inner = table.as_inner_mut();
+
+    let _ = (*inner).insert_with_hash_raw(key, value, hash);
+}
+
 /// Free table `table` space.
 ///
+/// # Notes
+///
+/// Unlike MRI, this function does nothing if `table` is null.
+///
 /// # Header declaration
 ///
 /// ```c
@@ -698,10 +1168,13 @@ pub unsafe fn st_add_direct(table: *mut st_table, key: st_data_t, value: st_data
 ///
 /// # Safety
 ///
-/// `table` must be non-null and point to a valid `st_table` created by the
+/// `table` must be null or point to a valid `st_table` created by the
 /// `st_init_table` family of functions.
 #[inline]
 pub unsafe fn st_free_table(table: *mut st_table) {
+    if table.is_null() {
+        return;
+    }
     let table = st_table::from_raw(table);
     let table = table.take();
     drop(table);
@@ -719,8 +1192,39 @@ pub fn st_cleanup_safe(table: *mut st_table, _never: st_data_t) {
     let _ = table;
 }
 
+/// Recompute the hashes of all keys in table `table`.
+///
+/// # Notes
+///
+/// Call this after mutating keys in a way that changes the result of the
+/// table's `st_hash_type` hash function. Entries keep their iteration order.
+/// Hashes supplied to [`st_insert_with_hash`] are replaced by the result of the
+/// hash function.
+///
+/// # Header declaration
+///
+/// ```c
+/// void st_rehash(st_table *);
+/// ```
+///
+/// # Safety
+///
+/// `table` must be non-null and point to a valid `st_table` created by the
+/// `st_init_table` family of functions.
+#[inline]
+pub unsafe fn st_rehash(table: *mut st_table) {
+    let mut table = st_table::from_raw(table);
+    let inner = table.as_inner_mut();
+
+    (*inner).rehash();
+}
+
 /// Make table `table` empty.
 ///
+/// # Notes
+///
+/// Unlike MRI, this function does nothing if `table` is null.
+///
 /// # Header declaration
 ///
 /// ```c
@@ -729,16 +1233,50 @@ pub fn st_cleanup_safe(table: *mut st_table, _never: st_data_t) {
 ///
 /// # Safety
 ///
-/// `table` must be non-null and point to a valid `st_table` created by the
+/// `table` must be null or point to a valid `st_table` created by the
 /// `st_init_table` family of functions.
 #[inline]
 pub unsafe fn st_clear(table: *mut st_table) {
+    if table.is_null() {
+        return;
+    }
     let mut table = st_table::from_raw(table);
     let inner = table.as_inner_mut();
 
     (*inner).inner.clear();
 }
 
+/// Make table `table` empty and release the memory of its entries.
+///
+/// Unlike [`st_clear`], which keeps the allocated memory for reuse, the
+/// capacity of the table drops to 0. This function is intended to be called
+/// when a table is reset by the GC.
+///
+/// # Notes
+///
+/// This function is not present in MRI. It does nothing if `table` is null.
+///
+/// # Header declaration
+///
+/// ```c
+/// void st_clear_and_shrink(st_table *);
+/// ```
+///
+/// # Safety
+///
+/// `table` must be null or point to a valid `st_table` created by the
+/// `st_init_table` family of functions.
+#[inline]
+pub unsafe fn st_clear_and_shrink(table: *mut st_table) {
+    if table.is_null() {
+        return;
+    }
+    let mut table = st_table::from_raw(table);
+    let inner = table.as_inner_mut();
+
+    (*inner).inner.clear_and_shrink();
+}
+
 /// Create and return a copy of table `old_table`.
 ///
 /// # Header declaration
@@ -787,7 +1325,37 @@ pub unsafe fn st_memsize(table: *mut st_table) -> libc::size_t {
     (*inner).inner.estimated_memsize()
 }
 
-/// Hash a byte array with FNV.
+/// Rebuild the entries of table `table` without the slots left behind by
+/// deleted entries. Return the number of reclaimed slots.
+///
+/// This function is intended to be called from GC compaction hooks.
+///
+/// # Notes
+///
+/// This function has no equivalent in MRI. It delegates to
+/// [`StHashMap::compact`](crate::StHashMap::compact). Traversal order is
+/// unchanged.
+///
+/// # Header declaration
+///
+/// ```c
+/// st_index_t st_compact(st_table *);
+/// ```
+///
+/// # Safety
+///
+/// `table` must be non-null and point to a valid `st_table` created by the
+/// `st_init_table` family of functions.
+#[inline]
+pub unsafe fn st_compact(table: *mut st_table) -> st_index_t {
+    let mut table = st_table::from_raw(table);
+    let inner = table.as_inner_mut();
+
+    (*inner).inner.compact().into()
+}
+
+/// Hash a byte array with FNV or `SipHash`, depending on the enabled hash
+/// feature.
 ///
 /// # Header declaration
 ///
@@ -801,13 +1369,11 @@ pub unsafe fn st_memsize(table: *mut st_table) -> libc::size_t {
 #[inline]
 #[must_use]
 pub unsafe fn st_hash(ptr: *const c_void, len: libc::size_t, h: st_index_t) -> st_index_t {
-    let mut hasher = FnvHasher::with_key(h.into());
     let data = slice::from_raw_parts(ptr.cast::<u8>(), len);
-    hasher.write(data);
-    hasher.finish().into()
+    StTable::hash_bytes(data, h)
 }
 
-/// Hash one round of FNV with `h` as the initial state.
+/// Hash one round of FNV or `SipHash` with `h` as the initial state.
 ///
 /// # Header declaration
 ///
@@ -817,12 +1383,12 @@ pub unsafe fn st_hash(ptr: *const c_void, len: libc::size_t, h: st_index_t) -> s
 #[inline]
 #[must_use]
 pub fn st_hash_uint32(h: st_index_t, i: u32) -> st_index_t {
-    let mut hasher = FnvHasher::with_key(h.into());
+    let mut hasher = keyed_hasher(h.into());
     hasher.write_u32(i);
     hasher.finish().into()
 }
 
-/// Hash one round of FNV with `h` as the initial state.
+/// Hash one round of FNV or `SipHash` with `h` as the initial state.
 ///
 /// # Header declaration
 ///
@@ -832,12 +1398,12 @@ pub fn st_hash_uint32(h: st_index_t, i: u32) -> st_index_t {
 #[inline]
 #[must_use]
 pub fn st_hash_uint(h: st_index_t, i: st_index_t) -> st_index_t {
-    let mut hasher = FnvHasher::with_key(h.into());
+    let mut hasher = keyed_hasher(h.into());
     hasher.write_usize(i.into());
     hasher.finish().into()
 }
 
-/// Finalize FNV hash.
+/// Finalize an FNV or `SipHash` hash.
 ///
 /// # Header declaration
 ///
@@ -850,7 +1416,7 @@ pub const fn st_hash_end(h: st_index_t) -> st_index_t {
     h
 }
 
-/// Initialize an FNV hash with `h` data.
+/// Initialize an FNV or `SipHash` hash with `h` data.
 ///
 /// # Header declaration
 ///
@@ -860,7 +1426,1208 @@ pub const fn st_hash_end(h: st_index_t) -> st_index_t {
 #[inline]
 #[must_use]
 pub fn st_hash_start(h: st_index_t) -> st_index_t {
-    let mut hasher = FnvHasher::default();
+    let mut hasher = default_hasher();
     hasher.write_usize(h.into());
     hasher.finish().into()
 }
+
+/// Insert the key-value pairs in the interleaved array `argv` of length `argc`
+/// into the table `hash`.
+///
+/// Pairs are inserted in array order. If a key appears more than once, the
+/// last value wins and the key keeps the insertion rank of its first
+/// occurrence.
+///
+/// # Notes
+///
+/// In MRI, `hash` is a Ruby `Hash` object whose backing `st_table` is the
+/// insertion target. `strudel` has no Ruby object model, so `hash` must be an
+/// `st_table *` cast to `VALUE`.
+///
+/// # Header declaration
+///
+/// ```c
+/// void rb_hash_bulk_insert_into_st_table(long, const VALUE *, VALUE);
+/// ```
+///
+/// # Safety
+///
+/// `hash` must be a non-null pointer to a valid `st_table` created by the
+/// `st_init_table` family of functions.
+///
+/// `argv` must point to an array of at least `argc` elements. `argv` may be
+/// null if `argc` is zero.
+#[inline]
+#[allow(clippy::similar_names)]
+pub unsafe fn rb_hash_bulk_insert_into_st_table(
+    argc: c_long,
+    argv: *const st_data_t,
+    hash: st_data_t,
+) {
+    let argc = usize::try_from(argc).unwrap_or_default();
+    if argc == 0 {
+        return;
+    }
+    let mut table = st_table::from_raw(usize::from(hash) as *mut st_table);
+    let inner = table.as_inner_mut();
+
+    let pairs = slice::from_raw_parts(argv, argc);
+    (*inner).inner.reserve(argc / 2);
+    for pair in pairs.chunks_exact(2) {
+        let _ = (*inner).insert_raw(pair[0], pair[1]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::ptr;
+    use core::slice;
+    use std::os::raw::c_int;
+
+    use crate::bindings::{st_hash_type, st_retval};
+    use crate::primitives::{st_data_t, st_hash_t, st_index_t};
+    use crate::st_table::ffi::st_table;
+    use crate::st_table::StTable;
+
+    unsafe extern "C" fn numcmp(x: st_data_t, y: st_data_t) -> c_int {
+        c_int::from(x != y)
+    }
+
+    unsafe extern "C" fn numhash(n: st_data_t) -> st_index_t {
+        n.into()
+    }
+
+    static NUM_HASH_TYPE: st_hash_type = st_hash_type {
+        compare: numcmp,
+        hash: numhash,
+    };
+
+    std::thread_local! {
+        static HASH_CALLS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe extern "C" fn counting_numhash(n: st_data_t) -> st_index_t {
+        HASH_CALLS.with(|calls| calls.set(calls.get() + 1));
+        (usize::from(n) ^ 0x5555).into()
+    }
+
+    static COUNTING_HASH_TYPE: st_hash_type = st_hash_type {
+        compare: numcmp,
+        hash: counting_numhash,
+    };
+
+    fn numtable(pairs: &[(usize, usize)]) -> *mut st_table {
+        let table = super::st_init_table(ptr::addr_of!(NUM_HASH_TYPE));
+        for &(key, value) in pairs {
+            unsafe {
+                super::st_insert(table, key.into(), value.into());
+            }
+        }
+        table
+    }
+
+    #[test]
+    fn st_keys_returns_number_written() {
+        let table = numtable(&[(3, 30), (1, 10), (2, 20)]);
+        let mut keys = [st_data_t::default(); 5];
+        unsafe {
+            let count = super::st_keys(table, keys.as_mut_ptr(), 5_usize.into());
+            assert_eq!(usize::from(count), 3);
+            assert_eq!(keys[..3], [3_usize, 1, 2]);
+
+            let count = super::st_keys(table, keys.as_mut_ptr(), 2_usize.into());
+            assert_eq!(usize::from(count), 2);
+
+            super::st_free_table(table);
+        }
+    }
+
+    #[test]
+    fn st_values_returns_number_written() {
+        let table = numtable(&[(3, 30), (1, 10), (2, 20)]);
+        let mut values = [st_data_t::default(); 5];
+        unsafe {
+            let count = super::st_values(table, values.as_mut_ptr(), 5_usize.into());
+            assert_eq!(usize::from(count), 3);
+            assert_eq!(values[..3], [30_usize, 10, 20]);
+
+            let count = super::st_values(table, values.as_mut_ptr(), 2_usize.into());
+            assert_eq!(usize::from(count), 2);
+
+            super::st_free_table(table);
+        }
+    }
+
+    #[test]
+    fn rb_hash_bulk_insert_into_st_table_preserves_array_order() {
+        let table = numtable(&[(9, 90)]);
+        let argv = [5_usize, 50, 2, 20, 9, 91, 7, 70, 2, 21].map(st_data_t::from);
+        let mut keys = [st_data_t::default(); 8];
+        let mut values = [st_data_t::default(); 8];
+        unsafe {
+            super::rb_hash_bulk_insert_into_st_table(10, argv.as_ptr(), (table as usize).into());
+            let count = super::st_keys(table, keys.as_mut_ptr(), 8_usize.into());
+            assert_eq!(usize::from(count), 4);
+            assert_eq!(keys[..4], [9_usize, 5, 2, 7]);
+
+            let count = super::st_values(table, values.as_mut_ptr(), 8_usize.into());
+            assert_eq!(usize::from(count), 4);
+            assert_eq!(values[..4], [91_usize, 50, 21, 70]);
+
+            super::rb_hash_bulk_insert_into_st_table(0, ptr::null(), (table as usize).into());
+            let count = super::st_keys(table, keys.as_mut_ptr(), 8_usize.into());
+            assert_eq!(usize::from(count), 4);
+
+            super::st_free_table(table);
+        }
+    }
+
+    #[test]
+    fn st_insert_many_matches_st_insert() {
+        let keys = (0..1000_usize)
+            .map(|i| st_data_t::from(i % 700))
+            .collect::<Vec<_>>();
+        let values = (0..1000_usize).map(st_data_t::from).collect::<Vec<_>>();
+
+        let bulk = numtable(&[]);
+        let single = numtable(&[]);
+        unsafe {
+            super::st_insert_many(bulk, keys.as_ptr(), values.as_ptr(), 1000_usize.into());
+            super::st_insert_many(bulk, ptr::null(), ptr::null(), 0_usize.into());
+            for (&key, &value) in keys.iter().zip(&values) {
+                super::st_insert(single, key, value);
+            }
+
+            let mut bulk_keys = [st_data_t::default(); 1000];
+            let mut single_keys = [st_data_t::default(); 1000];
+            let count = super::st_keys(bulk, bulk_keys.as_mut_ptr(), 1000_usize.into());
+            assert_eq!(usize::from(count), 700);
+            let count = super::st_keys(single, single_keys.as_mut_ptr(), 1000_usize.into());
+            assert_eq!(usize::from(count), 700);
+            assert_eq!(bulk_keys, single_keys);
+
+            let mut bulk_values = [st_data_t::default(); 1000];
+            let mut single_values = [st_data_t::default(); 1000];
+            let _ = super::st_values(bulk, bulk_values.as_mut_ptr(), 1000_usize.into());
+            let _ = super::st_values(single, single_values.as_mut_ptr(), 1000_usize.into());
+            assert_eq!(bulk_values, single_values);
+            // Duplicate keys keep their first rank and take the last value.
+            assert_eq!(bulk_keys[..2], [0_usize, 1]);
+            assert_eq!(bulk_values[..2], [700_usize, 701]);
+            assert_eq!(bulk_values[300..302], [300_usize, 301]);
+
+            // Each growth of the backing store bumps the rebuild counter, so
+            // the counter tracks how often the table reallocated.
+            let bulk_rebuilds = (*st_table::from_raw(bulk).as_inner_mut()).sync_rebuilds();
+            let single_rebuilds = (*st_table::from_raw(single).as_inner_mut()).sync_rebuilds();
+            assert!(bulk_rebuilds <= 1);
+            assert!(single_rebuilds > bulk_rebuilds);
+
+            super::st_free_table(bulk);
+            super::st_free_table(single);
+        }
+    }
+
+    #[test]
+    fn st_bulk_keys_and_values_round_trip() {
+        let table = numtable(&[(3, 30), (1, 10), (4, 40), (5, 50)]);
+        let mut len = st_index_t::default();
+        unsafe {
+            super::st_delete(table, &mut st_data_t::from(4_usize), ptr::null_mut());
+
+            let keys = super::st_bulk_keys(table, ptr::addr_of_mut!(len));
+            assert_eq!(usize::from(len), 3);
+            assert_eq!(slice::from_raw_parts(keys, 3), [3_usize, 1, 5]);
+            super::st_free_array(keys);
+
+            let values = super::st_bulk_values(table, ptr::addr_of_mut!(len));
+            assert_eq!(usize::from(len), 3);
+            assert_eq!(slice::from_raw_parts(values, 3), [30_usize, 10, 50]);
+            super::st_free_array(values);
+
+            super::st_clear(table);
+
+            assert!(super::st_bulk_keys(table, ptr::addr_of_mut!(len)).is_null());
+            assert_eq!(usize::from(len), 0);
+            super::st_free_array(ptr::null_mut());
+            super::st_free_table(table);
+        }
+    }
+
+    #[test]
+    fn free_and_clear_ignore_null_tables() {
+        unsafe {
+            super::st_clear(ptr::null_mut());
+            super::st_clear_and_shrink(ptr::null_mut());
+            super::st_free_table(ptr::null_mut());
+            super::st_free_array(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn st_clear_and_shrink_releases_memory_but_st_clear_does_not() {
+        let pairs = (0..1000).map(|i| (i, i * 10)).collect::<Vec<_>>();
+        let table = numtable(&pairs);
+        let mut value = st_data_t::default();
+        unsafe {
+            let memsize = super::st_memsize(table);
+
+            super::st_clear(table);
+            assert_eq!(super::st_memsize(table), memsize);
+            assert_eq!(
+                super::st_lookup(table, 5_usize.into(), ptr::addr_of_mut!(value)),
+                0
+            );
+
+            for &(key, value) in &pairs {
+                super::st_insert(table, key.into(), value.into());
+            }
+            super::st_clear_and_shrink(table);
+            assert!(super::st_memsize(table) < memsize);
+            assert_eq!(
+                super::st_lookup(table, 5_usize.into(), ptr::addr_of_mut!(value)),
+                0
+            );
+
+            super::st_insert(table, 7_usize.into(), 70_usize.into());
+            assert_eq!(
+                super::st_lookup(table, 7_usize.into(), ptr::addr_of_mut!(value)),
+                1
+            );
+            assert_eq!(value, 70_usize);
+            super::st_free_table(table);
+        }
+    }
+
+    unsafe extern "C" fn derefcmp(x: st_data_t, y: st_data_t) -> c_int {
+        let x = usize::from(x) as *const Cell<usize>;
+        let y = usize::from(y) as *const Cell<usize>;
+        c_int::from((*x).get() != (*y).get())
+    }
+
+    unsafe extern "C" fn derefhash(n: st_data_t) -> st_index_t {
+        let n = usize::from(n) as *const Cell<usize>;
+        (*n).get().into()
+    }
+
+    static DEREF_HASH_TYPE: st_hash_type = st_hash_type {
+        compare: derefcmp,
+        hash: derefhash,
+    };
+
+    #[test]
+    fn st_rehash_finds_mutated_keys() {
+        let cells = (0..32).map(Cell::new).collect::<Vec<_>>();
+        let record = |cell: &Cell<usize>| st_data_t::from(ptr::from_ref(cell) as usize);
+        let probe = Cell::new(0);
+        let table = super::st_init_table(ptr::addr_of!(DEREF_HASH_TYPE));
+        let mut value = st_data_t::default();
+        unsafe {
+            for (i, cell) in cells.iter().enumerate() {
+                super::st_insert(table, record(cell), i.into());
+            }
+            for cell in &cells {
+                cell.set(cell.get() + 100);
+            }
+            probe.set(107);
+            assert_eq!(
+                super::st_lookup(table, record(&probe), ptr::addr_of_mut!(value)),
+                0
+            );
+
+            super::st_rehash(table);
+            assert_eq!(
+                super::st_lookup(table, record(&probe), ptr::addr_of_mut!(value)),
+                1
+            );
+            assert_eq!(usize::from(value), 7);
+
+            let mut keys = [st_data_t::default(); 32];
+            let count = super::st_keys(table, keys.as_mut_ptr(), 32_usize.into());
+            assert_eq!(usize::from(count), 32);
+            assert!(keys
+                .iter()
+                .zip(&cells)
+                .all(|(&key, cell)| key == record(cell)));
+
+            super::st_free_table(table);
+        }
+    }
+
+    struct RehashState {
+        table: *mut st_table,
+        rehash_at: usize,
+        seen: Vec<usize>,
+    }
+
+    unsafe extern "C" fn record_and_rehash(
+        key: st_data_t,
+        _value: st_data_t,
+        arg: st_data_t,
+        _error: c_int,
+    ) -> c_int {
+        let state = &mut *(usize::from(arg) as *mut RehashState);
+        state.seen.push(key.into());
+        if usize::from(key) == state.rehash_at {
+            super::st_rehash(state.table);
+        }
+        st_retval::ST_CONTINUE as c_int
+    }
+
+    #[test]
+    fn st_rehash_in_st_foreach_keeps_traversal_position() {
+        let pairs = (0..8).map(|key| (key, key * 10)).collect::<Vec<_>>();
+        let table = numtable(&pairs);
+        let mut state = RehashState {
+            table,
+            rehash_at: 4,
+            seen: Vec::new(),
+        };
+        unsafe {
+            for key in 0..4_usize {
+                let mut key = st_data_t::from(key);
+                super::st_delete(table, ptr::addr_of_mut!(key), ptr::null_mut());
+            }
+            let arg = st_data_t::from(ptr::addr_of_mut!(state) as usize);
+            assert_eq!(super::st_foreach(table, record_and_rehash, arg), 0);
+            assert_eq!(state.seen, [4, 5, 6, 7]);
+
+            super::st_free_table(table);
+        }
+    }
+
+    unsafe extern "C" fn replace_all(
+        _key: st_data_t,
+        _value: st_data_t,
+        _arg: st_data_t,
+        _error: c_int,
+    ) -> c_int {
+        st_retval::ST_REPLACE as c_int
+    }
+
+    unsafe extern "C" fn double_value(
+        _key: *mut st_data_t,
+        value: *mut st_data_t,
+        _arg: st_data_t,
+        _existing: c_int,
+    ) -> c_int {
+        *value = (usize::from(*value) * 2).into();
+        st_retval::ST_CONTINUE as c_int
+    }
+
+    unsafe extern "C" fn delete_entry(
+        _key: *mut st_data_t,
+        _value: *mut st_data_t,
+        _arg: st_data_t,
+        _existing: c_int,
+    ) -> c_int {
+        st_retval::ST_DELETE as c_int
+    }
+
+    #[test]
+    fn st_update_hashes_key_once() {
+        HASH_CALLS.with(|calls| calls.set(0));
+        let table = super::st_init_table(ptr::addr_of!(COUNTING_HASH_TYPE));
+        let mut value = st_data_t::default();
+        unsafe {
+            // Enough entries to outgrow the initial capacity. Rehashing stored
+            // keys reuses their cached hashes.
+            for key in 0..256_usize {
+                super::st_insert(table, key.into(), key.into());
+            }
+            assert_eq!(HASH_CALLS.with(Cell::get), 256);
+
+            for key in 0..256_usize {
+                assert_eq!(
+                    super::st_update(table, key.into(), double_value, 0_usize.into()),
+                    1
+                );
+            }
+            assert_eq!(HASH_CALLS.with(Cell::get), 512);
+
+            for key in 0..200_usize {
+                assert_eq!(
+                    super::st_update(table, key.into(), delete_entry, 0_usize.into()),
+                    1
+                );
+            }
+            assert_eq!(HASH_CALLS.with(Cell::get), 712);
+
+            assert_eq!(usize::from(super::st_compact(table)), 200);
+            assert_eq!(HASH_CALLS.with(Cell::get), 712);
+
+            for key in 200..256_usize {
+                assert_eq!(
+                    super::st_lookup(table, key.into(), ptr::addr_of_mut!(value)),
+                    1
+                );
+                assert_eq!(value, key * 2);
+            }
+            assert_eq!(
+                super::st_lookup(table, 0_usize.into(), ptr::addr_of_mut!(value)),
+                0
+            );
+
+            super::st_free_table(table);
+        }
+    }
+
+    #[test]
+    fn st_foreach_with_replace_updates_values_in_place() {
+        let table = numtable(&[(3, 30), (1, 10), (2, 20)]);
+        let mut keys = [st_data_t::default(); 3];
+        let mut values = [st_data_t::default(); 3];
+        unsafe {
+            let retval = super::st_foreach_with_replace(
+                table,
+                replace_all,
+                double_value,
+                st_data_t::default(),
+            );
+            assert_eq!(retval, 0);
+
+            let count = super::st_keys(table, keys.as_mut_ptr(), 3_usize.into());
+            assert_eq!(usize::from(count), 3);
+            assert_eq!(keys, [3_usize, 1, 2]);
+
+            let count = super::st_values(table, values.as_mut_ptr(), 3_usize.into());
+            assert_eq!(usize::from(count), 3);
+            assert_eq!(values, [60_usize, 20, 40]);
+
+            super::st_free_table(table);
+        }
+    }
+
+    #[test]
+    fn st_keys_and_st_values_on_empty_table() {
+        let table = numtable(&[]);
+        let mut buf = [st_data_t::default(); 2];
+        unsafe {
+            let count = super::st_keys(table, buf.as_mut_ptr(), 2_usize.into());
+            assert_eq!(usize::from(count), 0);
+            let count = super::st_values(table, buf.as_mut_ptr(), 2_usize.into());
+            assert_eq!(usize::from(count), 0);
+
+            super::st_free_table(table);
+        }
+    }
+
+    #[test]
+    fn st_add_direct_with_hash_uses_supplied_hash() {
+        HASH_CALLS.with(|calls| calls.set(0));
+        let table = super::st_init_table(ptr::addr_of!(COUNTING_HASH_TYPE));
+        let mut value = st_data_t::default();
+        unsafe {
+            // Enough entries to outgrow the initial capacity and force the
+            // table to rehash every stored key.
+            for key in 0..64_usize {
+                let hash = st_hash_t::from(key ^ 0x5555);
+                super::st_add_direct_with_hash(table, key.into(), (key * 10).into(), hash);
+            }
+            assert_eq!(HASH_CALLS.with(Cell::get), 0);
+
+            for key in 0..64_usize {
+                assert_eq!(
+                    super::st_lookup(table, key.into(), ptr::addr_of_mut!(value)),
+                    1
+                );
+                assert_eq!(value, key * 10);
+            }
+            assert_eq!(HASH_CALLS.with(Cell::get), 64);
+            assert_eq!(
+                super::st_lookup(table, 64_usize.into(), ptr::addr_of_mut!(value)),
+                0
+            );
+
+            super::st_free_table(table);
+        }
+    }
+
+    #[test]
+    fn st_insert_with_hash_and_st_lookup_with_hash_round_trip() {
+        HASH_CALLS.with(|calls| calls.set(0));
+        let table = super::st_init_table(ptr::addr_of!(COUNTING_HASH_TYPE));
+        let mut value = st_data_t::default();
+        unsafe {
+            for key in 0..32_usize {
+                let hash = st_hash_t::from(key ^ 0x5555);
+                assert_eq!(
+                    super::st_insert_with_hash(table, key.into(), key.into(), hash),
+                    0
+                );
+            }
+            let hash = st_hash_t::from(7_usize ^ 0x5555);
+            assert_eq!(
+                super::st_insert_with_hash(table, 7_usize.into(), 70_usize.into(), hash),
+                1
+            );
+
+            for key in 0..32_usize {
+                let hash = st_hash_t::from(key ^ 0x5555);
+                let found =
+                    super::st_lookup_with_hash(table, key.into(), hash, ptr::addr_of_mut!(value));
+                assert_eq!(found, 1);
+                assert_eq!(value, if key == 7 { 70 } else { key });
+            }
+            assert_eq!(HASH_CALLS.with(Cell::get), 0);
+
+            assert_eq!(
+                super::st_lookup(table, 7_usize.into(), ptr::addr_of_mut!(value)),
+                1
+            );
+            assert_eq!(value, 70_usize);
+            assert_eq!(HASH_CALLS.with(Cell::get), 1);
+
+            let hash = st_hash_t::from(32_usize ^ 0x5555);
+            let found = super::st_lookup_with_hash(table, 32_usize.into(), hash, ptr::null_mut());
+            assert_eq!(found, 0);
+
+            super::st_free_table(table);
+        }
+    }
+
+    #[test]
+    fn st_compact_shrinks_churned_table() {
+        let table = numtable(&[]);
+        let mut keys = [st_data_t::default(); 4];
+        unsafe {
+            for key in 0..100_usize {
+                super::st_insert(table, key.into(), key.into());
+            }
+            for key in 4..100_usize {
+                let mut key = st_data_t::from(key);
+                super::st_delete(table, ptr::addr_of_mut!(key), ptr::null_mut());
+            }
+            let churned = super::st_memsize(table);
+
+            assert_eq!(usize::from(super::st_compact(table)), 96);
+            assert!(super::st_memsize(table) < churned);
+            assert_eq!(usize::from(super::st_compact(table)), 0);
+
+            let count = super::st_keys(table, keys.as_mut_ptr(), 4_usize.into());
+            assert_eq!(usize::from(count), 4);
+            assert_eq!(keys, [0_usize, 1, 2, 3]);
+
+            super::st_free_table(table);
+        }
+    }
+
+    #[test]
+    fn st_init_existing_table_reuses_table() {
+        HASH_CALLS.with(|calls| calls.set(0));
+        let table = numtable(&[(3, 30), (1, 10), (2, 20)]);
+        let mut value = st_data_t::default();
+        let mut keys = [st_data_t::default(); 2];
+        unsafe {
+            let reinit = super::st_init_existing_table(table, ptr::addr_of!(COUNTING_HASH_TYPE));
+            assert_eq!(reinit, table);
+
+            let count = super::st_keys(table, keys.as_mut_ptr(), 2_usize.into());
+            assert_eq!(usize::from(count), 0);
+            assert_eq!(
+                super::st_lookup(table, 3_usize.into(), ptr::addr_of_mut!(value)),
+                0
+            );
+
+            assert_eq!(super::st_insert(table, 5_usize.into(), 50_usize.into()), 0);
+            assert_eq!(
+                super::st_lookup(table, 5_usize.into(), ptr::addr_of_mut!(value)),
+                1
+            );
+            assert_eq!(value, 50_usize);
+            assert_eq!(HASH_CALLS.with(Cell::get), 3);
+
+            super::st_free_table(table);
+        }
+    }
+
+    unsafe extern "C" fn record_hash(
+        key: st_data_t,
+        value: st_data_t,
+        hash: st_hash_t,
+        arg: st_data_t,
+        error: c_int,
+    ) -> c_int {
+        let calls = usize::from(arg) as *mut Vec<(usize, usize, st_hash_t, c_int)>;
+        (*calls).push((key.into(), value.into(), hash, error));
+        st_retval::ST_CONTINUE as c_int
+    }
+
+    #[test]
+    fn st_foreach_with_hash_passes_stored_hash() {
+        let table = super::st_init_table(ptr::addr_of!(COUNTING_HASH_TYPE));
+        let mut calls: Vec<(usize, usize, st_hash_t, c_int)> = Vec::new();
+        unsafe {
+            super::st_insert(table, 3.into(), 30.into());
+            super::st_insert(table, 1.into(), 10.into());
+            let supplied = st_hash_t::from(st_index_t::from(0xdead_usize));
+            super::st_insert_with_hash(table, 2.into(), 20.into(), supplied);
+
+            let arg = st_data_t::from(ptr::addr_of_mut!(calls) as usize);
+            assert_eq!(super::st_foreach_with_hash(table, record_hash, arg), 0);
+
+            let hash = |key: usize| st_hash_t::from(counting_numhash(key.into()));
+            assert_eq!(
+                calls,
+                [
+                    (3, 30, hash(3), 0),
+                    (1, 10, hash(1), 0),
+                    (2, 20, supplied, 0)
+                ]
+            );
+
+            super::st_free_table(table);
+        }
+    }
+
+    struct CheckState {
+        table: *mut st_table,
+        delete: usize,
+        calls: Vec<(usize, usize, c_int)>,
+    }
+
+    unsafe extern "C" fn record_and_check(
+        key: st_data_t,
+        value: st_data_t,
+        arg: st_data_t,
+        error: c_int,
+    ) -> c_int {
+        let state = usize::from(arg) as *mut CheckState;
+        (*state).calls.push((key.into(), value.into(), error));
+        if error == 0 && usize::from(key) == (*state).delete {
+            let mut key = key;
+            super::st_delete((*state).table, ptr::addr_of_mut!(key), ptr::null_mut());
+        }
+        st_retval::ST_CHECK as c_int
+    }
+
+    #[test]
+    fn st_foreach_check_continues_on_st_check() {
+        let table = numtable(&[(3, 30), (1, 10), (2, 20)]);
+        let mut state = CheckState {
+            table,
+            delete: usize::MAX,
+            calls: Vec::new(),
+        };
+        unsafe {
+            let arg = st_data_t::from(ptr::addr_of_mut!(state) as usize);
+            let never = st_data_t::from(usize::MAX);
+            assert_eq!(
+                super::st_foreach_check(table, record_and_check, arg, never),
+                0
+            );
+            assert_eq!(state.calls, [(3, 30, 0), (1, 10, 0), (2, 20, 0)]);
+
+            super::st_free_table(table);
+        }
+    }
+
+    #[test]
+    fn st_foreach_check_skips_never_sentinel() {
+        let table = numtable(&[(3, 30), (99, 0), (2, 20)]);
+        let mut state = CheckState {
+            table,
+            delete: usize::MAX,
+            calls: Vec::new(),
+        };
+        unsafe {
+            let arg = st_data_t::from(ptr::addr_of_mut!(state) as usize);
+            let never = st_data_t::from(99_usize);
+            assert_eq!(
+                super::st_foreach_check(table, record_and_check, arg, never),
+                0
+            );
+            assert_eq!(state.calls, [(3, 30, 0), (2, 20, 0)]);
+
+            super::st_free_table(table);
+        }
+    }
+
+    #[test]
+    fn st_foreach_check_reports_deletion_of_current_entry() {
+        let table = numtable(&[(3, 30), (1, 10), (2, 20)]);
+        let mut state = CheckState {
+            table,
+            delete: 1,
+            calls: Vec::new(),
+        };
+        let mut keys = [st_data_t::default(); 3];
+        unsafe {
+            let arg = st_data_t::from(ptr::addr_of_mut!(state) as usize);
+            let never = st_data_t::from(usize::MAX);
+            assert_eq!(
+                super::st_foreach_check(table, record_and_check, arg, never),
+                1
+            );
+            assert_eq!(state.calls, [(3, 30, 0), (1, 10, 0), (0, 0, 1)]);
+
+            let count = super::st_keys(table, keys.as_mut_ptr(), 3_usize.into());
+            assert_eq!(usize::from(count), 2);
+            assert_eq!(keys[..2], [3_usize, 2]);
+
+            super::st_free_table(table);
+        }
+    }
+
+    struct RekeyState {
+        table: *mut st_table,
+        replacement: st_data_t,
+        seen: Vec<usize>,
+    }
+
+    unsafe extern "C" fn replace_key(
+        key: *mut st_data_t,
+        _value: *mut st_data_t,
+        arg: st_data_t,
+        _existing: c_int,
+    ) -> c_int {
+        *key = arg;
+        st_retval::ST_CONTINUE as c_int
+    }
+
+    unsafe extern "C" fn rekey_and_compact(
+        key: st_data_t,
+        _value: st_data_t,
+        arg: st_data_t,
+        error: c_int,
+    ) -> c_int {
+        if error != 0 {
+            return st_retval::ST_STOP as c_int;
+        }
+        let state = usize::from(arg) as *mut RekeyState;
+        let cell = usize::from(key) as *const Cell<usize>;
+        (*state).seen.push((*cell).get());
+        if (*state).seen.len() == 1 {
+            super::st_update((*state).table, key, replace_key, (*state).replacement);
+            super::st_compact((*state).table);
+        }
+        st_retval::ST_CHECK as c_int
+    }
+
+    #[test]
+    fn st_foreach_check_resumes_after_current_key_is_replaced_by_equal_key() {
+        let cells = (0..4).map(Cell::new).collect::<Vec<_>>();
+        let replacement = Cell::new(0);
+        let record = |cell: &Cell<usize>| st_data_t::from(ptr::from_ref(cell) as usize);
+        let table = super::st_init_table(ptr::addr_of!(DEREF_HASH_TYPE));
+        let mut keys = [st_data_t::default(); 3];
+        unsafe {
+            for (i, cell) in cells.iter().enumerate() {
+                super::st_insert(table, record(cell), i.into());
+            }
+            // Leave a dead slot behind so `st_compact` renumbers ranks.
+            let mut dead = record(&cells[1]);
+            super::st_delete(table, ptr::addr_of_mut!(dead), ptr::null_mut());
+
+            let mut state = RekeyState {
+                table,
+                replacement: record(&replacement),
+                seen: Vec::new(),
+            };
+            let arg = st_data_t::from(ptr::addr_of_mut!(state) as usize);
+            let never = st_data_t::from(usize::MAX);
+            assert_eq!(
+                super::st_foreach_check(table, rekey_and_compact, arg, never),
+                0
+            );
+            assert_eq!(state.seen, [0, 2, 3]);
+
+            let count = super::st_keys(table, keys.as_mut_ptr(), 3_usize.into());
+            assert_eq!(usize::from(count), 3);
+            assert_eq!(
+                keys,
+                [record(&replacement), record(&cells[2]), record(&cells[3])]
+            );
+
+            super::st_free_table(table);
+        }
+    }
+
+    fn pinned_hashes() -> [usize; 4] {
+        let data = b"strudel";
+        let h = st_index_t::from(0x5eed_usize);
+        unsafe {
+            [
+                super::st_hash(data.as_ptr().cast(), data.len(), h).into(),
+                super::st_hash_uint32(h, 0xdead_beef).into(),
+                super::st_hash_uint(h, 42_usize.into()).into(),
+                super::st_hash_end(super::st_hash_start(h)).into(),
+            ]
+        }
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    #[cfg(all(feature = "hash-fnv", not(feature = "hash-siphash")))]
+    fn st_hash_fnv_output_is_pinned() {
+        let expected = [
+            6_542_448_969_756_696_744,
+            2_931_524_200_960_993_587,
+            3_028_118_843_378_285_991,
+            3_317_218_497_479_369_378,
+        ];
+        assert_eq!(pinned_hashes(), expected);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    #[cfg(feature = "hash-siphash")]
+    fn st_hash_siphash_output_is_pinned() {
+        let expected = [
+            16_100_719_698_352_439_314,
+            11_467_233_395_931_219_750,
+            11_612_597_907_421_918_286,
+            5_156_621_130_251_400_790,
+        ];
+        assert_eq!(pinned_hashes(), expected);
+    }
+
+    #[test]
+    fn hash_bytes_matches_st_hash() {
+        let inputs: [&[u8]; 4] = [b"", b"a", b"strudel", &[0xff; 64]];
+        for &data in &inputs {
+            for &seed in &[0_usize, 1, 0xdead_beef, usize::MAX] {
+                let seed = st_index_t::from(seed);
+                let hash = unsafe { super::st_hash(data.as_ptr().cast(), data.len(), seed) };
+                assert_eq!(StTable::hash_bytes(data, seed), hash);
+            }
+        }
+    }
+
+    #[test]
+    fn st_replace_returns_previous_value() {
+        let table = numtable(&[(3, 30), (1, 10)]);
+        let mut old_value = st_data_t::from(99_usize);
+        let mut value = st_data_t::default();
+        let mut keys = [st_data_t::default(); 3];
+        unsafe {
+            let replaced = super::st_replace(
+                table,
+                3_usize.into(),
+                31_usize.into(),
+                ptr::addr_of_mut!(old_value),
+            );
+            assert_eq!(replaced, 1);
+            assert_eq!(old_value, 30_usize);
+
+            let replaced =
+                super::st_replace(table, 1_usize.into(), 11_usize.into(), ptr::null_mut());
+            assert_eq!(replaced, 1);
+
+            let count = super::st_keys(table, keys.as_mut_ptr(), 3_usize.into());
+            assert_eq!(usize::from(count), 2);
+            assert_eq!(keys[..2], [3_usize, 1]);
+            assert_eq!(
+                super::st_lookup(table, 3_usize.into(), ptr::addr_of_mut!(value)),
+                1
+            );
+            assert_eq!(value, 31_usize);
+            assert_eq!(
+                super::st_lookup(table, 1_usize.into(), ptr::addr_of_mut!(value)),
+                1
+            );
+            assert_eq!(value, 11_usize);
+
+            super::st_free_table(table);
+        }
+    }
+
+    #[test]
+    fn st_replace_inserts_absent_key() {
+        let table = numtable(&[(3, 30)]);
+        let mut old_value = st_data_t::from(99_usize);
+        let mut value = st_data_t::default();
+        unsafe {
+            let replaced = super::st_replace(
+                table,
+                4_usize.into(),
+                40_usize.into(),
+                ptr::addr_of_mut!(old_value),
+            );
+            assert_eq!(replaced, 0);
+            assert_eq!(old_value, 99_usize);
+
+            let replaced =
+                super::st_replace(table, 5_usize.into(), 50_usize.into(), ptr::null_mut());
+            assert_eq!(replaced, 0);
+
+            assert_eq!(
+                super::st_lookup(table, 4_usize.into(), ptr::addr_of_mut!(value)),
+                1
+            );
+            assert_eq!(value, 40_usize);
+            assert_eq!(
+                super::st_lookup(table, 5_usize.into(), ptr::addr_of_mut!(value)),
+                1
+            );
+            assert_eq!(value, 50_usize);
+
+            super::st_free_table(table);
+        }
+    }
+
+    #[cfg(all(unix, panic = "unwind"))]
+    const PANIC_CHILD_VAR: &str = "STRUDEL_FFI_PANIC_CHILD";
+
+    // Run `body` in a child process running only the test named `test`, and
+    // return the signal that terminated the child.
+    #[cfg(all(unix, panic = "unwind"))]
+    fn signal_in_child(test: &str, body: impl FnOnce()) -> Option<c_int> {
+        use std::env;
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::{Command, Stdio};
+
+        if env::var(PANIC_CHILD_VAR).is_ok_and(|child| child == test) {
+            body();
+            std::process::exit(0);
+        }
+        let module = module_path!().splitn(2, "::").nth(1).unwrap();
+        let status = Command::new(env::current_exe().unwrap())
+            .arg(format!("{module}::{test}"))
+            .args(["--exact", "--test-threads=1"])
+            .env(PANIC_CHILD_VAR, test)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        status.signal()
+    }
+
+    unsafe extern "C" fn panic_foreach(
+        _key: st_data_t,
+        _value: st_data_t,
+        _arg: st_data_t,
+        _error: c_int,
+    ) -> c_int {
+        panic!("st_foreach callback panicked");
+    }
+
+    unsafe extern "C" fn panic_update(
+        _key: *mut st_data_t,
+        _value: *mut st_data_t,
+        _arg: st_data_t,
+        _existing: c_int,
+    ) -> c_int {
+        panic!("st_update callback panicked");
+    }
+
+    unsafe extern "C" fn panic_insert2(_key: st_data_t) -> st_data_t {
+        panic!("st_insert2 callback panicked");
+    }
+
+    unsafe extern "C" fn panic_cmp(_x: st_data_t, _y: st_data_t) -> c_int {
+        panic!("st_hash_type compare function panicked");
+    }
+
+    unsafe extern "C" fn panic_hash(_n: st_data_t) -> st_index_t {
+        panic!("st_hash_type hash function panicked");
+    }
+
+    unsafe extern "C" fn colliding_hash(_n: st_data_t) -> st_index_t {
+        0_usize.into()
+    }
+
+    static PANIC_CMP_HASH_TYPE: st_hash_type = st_hash_type {
+        compare: panic_cmp,
+        hash: colliding_hash,
+    };
+
+    static PANIC_HASH_HASH_TYPE: st_hash_type = st_hash_type {
+        compare: numcmp,
+        hash: panic_hash,
+    };
+
+    // The `st_hash_type` functions are called with a `Foreign` guard live. A
+    // panic cannot unwind through their `extern "C"` frames, so it aborts
+    // before the guard could be dropped twice or the table leaked.
+    #[test]
+    #[cfg(all(unix, panic = "unwind"))]
+    fn panicking_compare_function_aborts() {
+        let signal = signal_in_child("panicking_compare_function_aborts", || unsafe {
+            let table = super::st_init_table(ptr::addr_of!(PANIC_CMP_HASH_TYPE));
+            super::st_insert(table, 1_usize.into(), 10_usize.into());
+            super::st_insert(table, 2_usize.into(), 20_usize.into());
+        });
+        assert_eq!(signal, Some(libc::SIGABRT));
+    }
+
+    #[test]
+    #[cfg(all(unix, panic = "unwind"))]
+    fn panicking_hash_function_aborts() {
+        let signal = signal_in_child("panicking_hash_function_aborts", || unsafe {
+            let table = super::st_init_table(ptr::addr_of!(PANIC_HASH_HASH_TYPE));
+            super::st_insert(table, 1_usize.into(), 10_usize.into());
+        });
+        assert_eq!(signal, Some(libc::SIGABRT));
+    }
+
+    #[test]
+    #[cfg(all(unix, panic = "unwind"))]
+    fn panicking_st_foreach_callback_aborts() {
+        let signal = signal_in_child("panicking_st_foreach_callback_aborts", || unsafe {
+            let table = numtable(&[(1, 10), (2, 20)]);
+            super::st_foreach(table, panic_foreach, 0_usize.into());
+        });
+        assert_eq!(signal, Some(libc::SIGABRT));
+    }
+
+    #[test]
+    #[cfg(all(unix, panic = "unwind"))]
+    fn panicking_st_update_callback_aborts() {
+        let signal = signal_in_child("panicking_st_update_callback_aborts", || unsafe {
+            let table = numtable(&[(1, 10)]);
+            super::st_update(table, 1_usize.into(), panic_update, 0_usize.into());
+        });
+        assert_eq!(signal, Some(libc::SIGABRT));
+    }
+
+    #[test]
+    #[cfg(all(unix, panic = "unwind"))]
+    fn panicking_st_insert2_callback_aborts() {
+        let signal = signal_in_child("panicking_st_insert2_callback_aborts", || unsafe {
+            let table = numtable(&[]);
+            super::st_insert2(table, 1_usize.into(), 10_usize.into(), panic_insert2);
+        });
+        assert_eq!(signal, Some(libc::SIGABRT));
+    }
+
+    #[test]
+    #[cfg(feature = "rb-symbols")]
+    fn rb_st_aliases_share_st_implementation() {
+        use crate::ffi::{rb_st_free_table, rb_st_init_table, rb_st_insert, rb_st_lookup};
+
+        unsafe {
+            let table = rb_st_init_table(ptr::addr_of!(NUM_HASH_TYPE));
+            assert_eq!(rb_st_insert(table, 1.into(), 10.into()), 0);
+            assert_eq!(super::st_insert(table, 2.into(), 20.into()), 0);
+            assert_eq!(rb_st_insert(table, 2.into(), 21.into()), 1);
+
+            let mut value = st_data_t::default();
+            assert_eq!(
+                super::st_lookup(table, 1.into(), ptr::addr_of_mut!(value)),
+                1
+            );
+            assert_eq!(usize::from(value), 10);
+            assert_eq!(rb_st_lookup(table, 2.into(), ptr::addr_of_mut!(value)), 1);
+            assert_eq!(usize::from(value), 21);
+
+            rb_st_free_table(table);
+        }
+    }
+
+    /// Read `num_entries` through the C layout of `st_table`, the way MRI
+    /// macros such as `RHASH_ST_SIZE` do.
+    unsafe fn c_num_entries(table: *mut st_table) -> usize {
+        let table = table.cast::<crate::st_table::ffi::tests::st_table>();
+        usize::from((*table).num_entries)
+    }
+
+    unsafe extern "C" fn delete_even(
+        key: st_data_t,
+        _value: st_data_t,
+        _arg: st_data_t,
+        _error: c_int,
+    ) -> c_int {
+        if usize::from(key) % 2 == 0 {
+            st_retval::ST_DELETE as c_int
+        } else {
+            st_retval::ST_CONTINUE as c_int
+        }
+    }
+
+    unsafe extern "C" fn insert_and_stop(
+        key: st_data_t,
+        _value: st_data_t,
+        arg: st_data_t,
+        _error: c_int,
+    ) -> c_int {
+        let table = usize::from(arg) as *mut st_table;
+        super::st_insert(table, (usize::from(key) + 100).into(), 0_usize.into());
+        st_retval::ST_STOP as c_int
+    }
+
+    unsafe extern "C" fn identity(key: st_data_t) -> st_data_t {
+        key
+    }
+
+    #[test]
+    fn num_entries_is_repacked_after_every_mutation() {
+        let table = numtable(&[]);
+        let mut key = st_data_t::from(1_usize);
+        let mut value = st_data_t::default();
+        unsafe {
+            assert_eq!(c_num_entries(table), 0);
+
+            super::st_insert(table, 1_usize.into(), 10_usize.into());
+            assert_eq!(c_num_entries(table), 1);
+            super::st_insert_with_hash(table, 2_usize.into(), 20_usize.into(), 2_usize.into());
+            assert_eq!(c_num_entries(table), 2);
+            super::st_add_direct(table, 3_usize.into(), 30_usize.into());
+            assert_eq!(c_num_entries(table), 3);
+            super::st_add_direct_with_hash(table, 4_usize.into(), 40_usize.into(), 4_usize.into());
+            assert_eq!(c_num_entries(table), 4);
+            super::st_insert2(table, 5_usize.into(), 50_usize.into(), identity);
+            assert_eq!(c_num_entries(table), 5);
+            super::st_replace(table, 6_usize.into(), 60_usize.into(), ptr::null_mut());
+            assert_eq!(c_num_entries(table), 6);
+
+            let keys = [7_usize.into(), 8_usize.into()];
+            let values = [70_usize.into(), 80_usize.into()];
+            super::st_insert_many(table, keys.as_ptr(), values.as_ptr(), 2_usize.into());
+            assert_eq!(c_num_entries(table), 8);
+
+            let argv = [
+                9_usize.into(),
+                90_usize.into(),
+                10_usize.into(),
+                100_usize.into(),
+            ];
+            super::rb_hash_bulk_insert_into_st_table(4, argv.as_ptr(), (table as usize).into());
+            assert_eq!(c_num_entries(table), 10);
+
+            super::st_update(table, 11_usize.into(), double_value, 0_usize.into());
+            assert_eq!(c_num_entries(table), 11);
+            super::st_update(table, 11_usize.into(), delete_entry, 0_usize.into());
+            assert_eq!(c_num_entries(table), 10);
+
+            super::st_delete(table, ptr::addr_of_mut!(key), ptr::addr_of_mut!(value));
+            assert_eq!(c_num_entries(table), 9);
+            key = 2_usize.into();
+            super::st_delete_safe(
+                table,
+                ptr::addr_of_mut!(key),
+                ptr::addr_of_mut!(value),
+                ptr::null(),
+            );
+            assert_eq!(c_num_entries(table), 8);
+            super::st_shift(table, ptr::addr_of_mut!(key), ptr::addr_of_mut!(value));
+            assert_eq!(key, 3_usize);
+            assert_eq!(c_num_entries(table), 7);
+
+            // 4, 6, 8, and 10 are deleted.
+            super::st_foreach(table, delete_even, 0_usize.into());
+            assert_eq!(c_num_entries(table), 3);
+            // The callback inserts through the C API and then stops traversal.
+            super::st_foreach(table, insert_and_stop, (table as usize).into());
+            assert_eq!(c_num_entries(table), 4);
+
+            super::st_compact(table);
+            assert_eq!(c_num_entries(table), 4);
+            super::st_rehash(table);
+            assert_eq!(c_num_entries(table), 4);
+
+            let copy = super::st_copy(table);
+            assert_eq!(c_num_entries(copy), 4);
+            super::st_clear(copy);
+            assert_eq!(c_num_entries(copy), 0);
+            assert_eq!(c_num_entries(table), 4);
+
+            super::st_clear_and_shrink(table);
+            assert_eq!(c_num_entries(table), 0);
+
+            super::st_free_table(copy);
+            super::st_free_table(table);
+        }
+    }
+}
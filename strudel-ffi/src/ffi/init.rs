@@ -1,13 +1,13 @@
 use core::hash::Hasher;
 use core::mem::transmute;
+use core::ptr;
 use core::slice;
 use std::ffi::CStr;
 use std::os::raw::{c_char, c_int};
 
-use fnv::FnvHasher;
-
 use super::{st_init_table, st_init_table_with_size};
-use crate::bindings::st_hash_type;
+use crate::bindings::{st_compare_func, st_hash_type};
+use crate::hasher::default_hasher;
 use crate::primitives::{st_data_t, st_index_t};
 use crate::st_table::ffi::st_table;
 
@@ -43,21 +43,23 @@ static st_hashtype_num: st_hash_type = st_hash_type {
 /// ```
 unsafe extern "C" fn strhash(arg: st_data_t) -> st_index_t {
     let string = CStr::from_ptr(arg.as_const_c_char());
-    let mut hasher = FnvHasher::default();
+    let mut hasher = default_hasher();
     hasher.write(string.to_bytes());
     hasher.finish().into()
 }
 
 static type_strhash: st_hash_type = st_hash_type {
     compare: unsafe {
-        transmute(libc::strcmp as unsafe extern "C" fn(*const c_char, *const c_char) -> c_int)
+        transmute::<unsafe extern "C" fn(*const c_char, *const c_char) -> c_int, st_compare_func>(
+            libc::strcmp,
+        )
     },
     hash: strhash,
 };
 
 unsafe extern "C" fn strcasehash(arg: st_data_t) -> st_index_t {
     let string = CStr::from_ptr(arg.as_const_c_char());
-    let mut hasher = FnvHasher::default();
+    let mut hasher = default_hasher();
     for byte in string.to_bytes() {
         hasher.write_u8(byte.to_ascii_lowercase());
     }
@@ -76,7 +78,7 @@ static type_strcasehash: st_hash_type = st_hash_type {
 /// ```
 #[no_mangle]
 unsafe extern "C" fn st_init_numtable() -> *mut st_table {
-    st_init_table(&st_hashtype_num)
+    st_init_table(ptr::addr_of!(st_hashtype_num))
 }
 
 /// # Header declaration
@@ -86,7 +88,7 @@ unsafe extern "C" fn st_init_numtable() -> *mut st_table {
 /// ```
 #[no_mangle]
 unsafe extern "C" fn st_init_numtable_with_size(size: st_index_t) -> *mut st_table {
-    st_init_table_with_size(&st_hashtype_num, size)
+    st_init_table_with_size(ptr::addr_of!(st_hashtype_num), size)
 }
 
 /// # Header declaration
@@ -96,7 +98,7 @@ unsafe extern "C" fn st_init_numtable_with_size(size: st_index_t) -> *mut st_tab
 /// ```
 #[no_mangle]
 unsafe extern "C" fn st_init_strtable() -> *mut st_table {
-    st_init_table(&type_strhash)
+    st_init_table(ptr::addr_of!(type_strhash))
 }
 
 /// # Header declaration
@@ -106,7 +108,7 @@ unsafe extern "C" fn st_init_strtable() -> *mut st_table {
 /// ```
 #[no_mangle]
 unsafe extern "C" fn st_init_strtable_with_size(size: st_index_t) -> *mut st_table {
-    st_init_table_with_size(&type_strhash, size)
+    st_init_table_with_size(ptr::addr_of!(type_strhash), size)
 }
 
 /// # Header declaration
@@ -116,7 +118,7 @@ unsafe extern "C" fn st_init_strtable_with_size(size: st_index_t) -> *mut st_tab
 /// ```
 #[no_mangle]
 unsafe extern "C" fn st_init_strcasetable() -> *mut st_table {
-    st_init_table(&type_strcasehash)
+    st_init_table(ptr::addr_of!(type_strcasehash))
 }
 
 /// # Header declaration
@@ -126,7 +128,7 @@ unsafe extern "C" fn st_init_strcasetable() -> *mut st_table {
 /// ```
 #[no_mangle]
 unsafe extern "C" fn st_init_strcasetable_with_size(size: st_index_t) -> *mut st_table {
-    st_init_table_with_size(&type_strcasehash, size)
+    st_init_table_with_size(ptr::addr_of!(type_strcasehash), size)
 }
 
 /// # Header declaration
@@ -172,7 +174,10 @@ unsafe extern "C" fn st_locale_insensitive_strncasecmp(
     let s2 = slice::from_raw_parts(s2.as_const_c_char(), n);
 
     for (&left, &right) in s1.iter().zip(s2.iter()) {
-        match (transmute(left), transmute(right)) {
+        match (
+            u8::from_ne_bytes(left.to_ne_bytes()),
+            u8::from_ne_bytes(right.to_ne_bytes()),
+        ) {
             (b'\0', b'\0') => return 0,
             (_, b'\0') => return 1,
             (b'\0', _) => return -1,
@@ -185,3 +190,17 @@ unsafe extern "C" fn st_locale_insensitive_strncasecmp(
     }
     0
 }
+
+#[cfg(feature = "rb-symbols")]
+rb_st_aliases! {
+    fn rb_st_numcmp => st_numcmp(x: st_data_t, y: st_data_t) -> c_int;
+    fn rb_st_numhash => st_numhash(n: st_data_t) -> st_index_t;
+    fn rb_st_init_numtable => st_init_numtable() -> *mut st_table;
+    fn rb_st_init_numtable_with_size => st_init_numtable_with_size(size: st_index_t) -> *mut st_table;
+    fn rb_st_init_strtable => st_init_strtable() -> *mut st_table;
+    fn rb_st_init_strtable_with_size => st_init_strtable_with_size(size: st_index_t) -> *mut st_table;
+    fn rb_st_init_strcasetable => st_init_strcasetable() -> *mut st_table;
+    fn rb_st_init_strcasetable_with_size => st_init_strcasetable_with_size(size: st_index_t) -> *mut st_table;
+    fn rb_st_locale_insensitive_strcasecmp => st_locale_insensitive_strcasecmp(s1: st_data_t, s2: st_data_t) -> libc::c_int;
+    fn rb_st_locale_insensitive_strncasecmp => st_locale_insensitive_strncasecmp(s1: st_data_t, s2: st_data_t, n: libc::size_t) -> libc::c_int;
+}
@@ -3,10 +3,17 @@
 use core::hash::{BuildHasher, Hasher};
 use core::mem::size_of;
 
+#[cfg(all(feature = "hash-fnv", not(feature = "hash-siphash")))]
+use fnv::FnvHasher;
+
 use crate::bindings::st_hash_type;
 use crate::primitives::st_data_t;
 
-/// `StBuildHasher` is the default state for `ExternStHashMap`s.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// `StBuildHasher` creates [`StHasher`]s which hash values with the `hash`
+/// function of an `st_hash_type`.
 ///
 /// A particular instance of `StBuildHasher` will create the same instances of
 /// [`Hasher`], but hashers created by two different `StBuildHasher` instances
@@ -41,7 +48,7 @@ impl BuildHasher for StBuildHasher {
     fn build_hasher(&self) -> Self::Hasher {
         Self::Hasher {
             hash_type: self.hash_type,
-            state: 0,
+            state: FNV_OFFSET_BASIS,
         }
     }
 }
@@ -51,14 +58,18 @@ impl BuildHasher for Box<StBuildHasher> {
 
     #[inline]
     fn build_hasher(&self) -> Self::Hasher {
-        Self::Hasher {
-            hash_type: self.hash_type,
-            state: 0,
-        }
+        (**self).build_hasher()
     }
 }
 
-/// The default [`Hasher`] used by [`StBuildHasher`].
+/// The [`Hasher`] created by [`StBuildHasher`].
+///
+/// Input is split into `st_data_t` sized words. Each word is transformed by the
+/// `st_hash_type` hash function and folded into the hasher state with an
+/// FNV-style xor-multiply step, so values whose [`Hash`] implementation issues
+/// several writes, like tuples and structs, are supported.
+///
+/// [`Hash`]: core::hash::Hash
 #[derive(Debug, Clone)]
 #[allow(clippy::module_name_repetitions)]
 pub struct StHasher {
@@ -69,9 +80,6 @@ pub struct StHasher {
 impl StHasher {
     #[inline]
     unsafe fn add_to_hash(&mut self, i: st_data_t) {
-        // `StHasher` should only be called with one round.
-        debug_assert!(self.state == 0);
-
         // Safety:
         //
         // `StHasher` assumes the `*const st_hash_type` pointer has `'static`
@@ -81,7 +89,8 @@ impl StHasher {
             let hash = (*self.hash_type).hash;
             (hash)(i)
         };
-        self.state += u64::from(hash_val);
+        self.state ^= usize::from(hash_val) as u64;
+        self.state = self.state.wrapping_mul(FNV_PRIME);
     }
 
     /// Return the underlying equality comparator and hash function used to
@@ -155,6 +164,11 @@ impl Hasher for StHasher {
         }
     }
 
+    #[inline]
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_ne_bytes());
+    }
+
     #[inline]
     fn write_usize(&mut self, i: usize) {
         unsafe {
@@ -162,8 +176,328 @@ impl Hasher for StHasher {
         }
     }
 
+    // Signed integers hash like the unsigned integer of the same width with
+    // the same bits.
+
+    #[inline]
+    fn write_i8(&mut self, i: i8) {
+        self.write_u8(u8::from_ne_bytes(i.to_ne_bytes()));
+    }
+
+    #[inline]
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(u16::from_ne_bytes(i.to_ne_bytes()));
+    }
+
+    #[inline]
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(u32::from_ne_bytes(i.to_ne_bytes()));
+    }
+
+    #[inline]
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(u64::from_ne_bytes(i.to_ne_bytes()));
+    }
+
+    #[inline]
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(u128::from_ne_bytes(i.to_ne_bytes()));
+    }
+
+    #[inline]
+    fn write_isize(&mut self, i: isize) {
+        self.write_usize(usize::from_ne_bytes(i.to_ne_bytes()));
+    }
+
     #[inline]
     fn finish(&self) -> u64 {
         self.state
     }
 }
+
+/// `KeyBuildHasher` is the default state for `ExternStHashMap`s.
+///
+/// Keys stored in an `ExternStHashMap` carry the [`st_hash_t`] computed by
+/// their `st_hash_type` (or supplied by the caller, see
+/// [`StTable::insert_with_hash_raw`]). The [`KeyHasher`]s created by this
+/// builder fold these precomputed hashes into their state without calling back
+/// into the `st_hash_type` hash function.
+///
+/// [`st_hash_t`]: crate::primitives::st_hash_t
+/// [`StTable::insert_with_hash_raw`]: crate::st_table::StTable::insert_with_hash_raw
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct KeyBuildHasher {
+    inner: StBuildHasher,
+}
+
+impl KeyBuildHasher {
+    /// Return the underlying equality comparator and hash function used to
+    /// construct this [`BuildHasher`].
+    #[inline]
+    #[must_use]
+    pub fn hash_type(&self) -> *const st_hash_type {
+        self.inner.hash_type()
+    }
+}
+
+impl From<*const st_hash_type> for KeyBuildHasher {
+    #[inline]
+    fn from(hash_type: *const st_hash_type) -> Self {
+        let inner = StBuildHasher::from(hash_type);
+        Self { inner }
+    }
+}
+
+// Safety:
+//
+// `KeyBuildHasher` never calls the `st_hash_type` functions and the
+// `st_hash_type` is immutable with `'static` lifetime, so the pointer may be
+// shared with and sent to other threads.
+#[cfg(feature = "sync")]
+unsafe impl Send for KeyBuildHasher {}
+
+#[cfg(feature = "sync")]
+unsafe impl Sync for KeyBuildHasher {}
+
+impl BuildHasher for KeyBuildHasher {
+    type Hasher = KeyHasher;
+
+    #[inline]
+    fn build_hasher(&self) -> Self::Hasher {
+        KeyHasher {
+            state: FNV_OFFSET_BASIS,
+        }
+    }
+}
+
+/// The [`Hasher`] created by [`KeyBuildHasher`].
+///
+/// Input is split into `st_data_t` sized words which are folded into the
+/// hasher state with an FNV-style xor-multiply step.
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct KeyHasher {
+    state: u64,
+}
+
+impl Hasher for KeyHasher {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(size_of::<st_data_t>()) {
+            let mut buf = [0_u8; size_of::<st_data_t>()];
+            buf[..chunk.len()].copy_from_slice(chunk);
+            self.write_usize(usize::from_ne_bytes(buf));
+        }
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.state ^= i as u64;
+        self.state = self.state.wrapping_mul(FNV_PRIME);
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.state
+    }
+}
+
+/// Construct the [`Hasher`] used by the `st_hash` family of functions, seeded
+/// with `key`.
+///
+/// The hash algorithm is selected at compile time with the `hash-fnv` and
+/// `hash-siphash` Cargo features.
+#[inline]
+#[cfg(all(feature = "hash-fnv", not(feature = "hash-siphash")))]
+pub fn keyed_hasher(key: u64) -> FnvHasher {
+    FnvHasher::with_key(key)
+}
+
+/// Construct the [`Hasher`] used by the `st_hash` family of functions, seeded
+/// with `key`.
+///
+/// The hash algorithm is selected at compile time with the `hash-fnv` and
+/// `hash-siphash` Cargo features.
+#[inline]
+#[cfg(feature = "hash-siphash")]
+#[allow(deprecated)]
+pub fn keyed_hasher(key: u64) -> core::hash::SipHasher {
+    core::hash::SipHasher::new_with_keys(key, 0)
+}
+
+/// Construct the unseeded [`Hasher`] used by `st_hash_start` and the string
+/// hash functions of the builtin `st_hash_type`s.
+#[inline]
+#[cfg(all(feature = "hash-fnv", not(feature = "hash-siphash")))]
+pub fn default_hasher() -> FnvHasher {
+    FnvHasher::default()
+}
+
+/// Construct the unseeded [`Hasher`] used by `st_hash_start` and the string
+/// hash functions of the builtin `st_hash_type`s.
+#[inline]
+#[cfg(feature = "hash-siphash")]
+pub fn default_hasher() -> impl Hasher {
+    keyed_hasher(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::hash::{BuildHasher, Hasher};
+    use core::ptr;
+    use std::collections::HashSet;
+    use std::os::raw::c_int;
+
+    use strudel::StHashMap;
+
+    use super::{StBuildHasher, StHasher};
+    use crate::bindings::st_hash_type;
+    use crate::primitives::{st_data_t, st_index_t};
+
+    unsafe extern "C" fn numcmp(x: st_data_t, y: st_data_t) -> c_int {
+        c_int::from(x != y)
+    }
+
+    unsafe extern "C" fn numhash(n: st_data_t) -> st_index_t {
+        n.into()
+    }
+
+    static NUM_HASH_TYPE: st_hash_type = st_hash_type {
+        compare: numcmp,
+        hash: numhash,
+    };
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    struct Point {
+        x: u32,
+        y: u32,
+        label: &'static str,
+    }
+
+    #[test]
+    fn tuple_keys_hash_all_fields() {
+        let build_hasher = StBuildHasher::from(ptr::addr_of!(NUM_HASH_TYPE));
+        assert_eq!(
+            build_hasher.hash_one((1_usize, 2_usize)),
+            build_hasher.hash_one((1_usize, 2_usize))
+        );
+        assert_ne!(
+            build_hasher.hash_one((1_usize, 2_usize)),
+            build_hasher.hash_one((2_usize, 1_usize))
+        );
+        assert_ne!(
+            build_hasher.hash_one((1_usize, 2_usize)),
+            build_hasher.hash_one((1_usize, 3_usize))
+        );
+
+        let mut map = StHashMap::with_hasher(build_hasher);
+        for x in 0..32_usize {
+            for y in 0..32_usize {
+                let _ = map.insert((x, y), x * 32 + y);
+            }
+        }
+        assert_eq!(map.len(), 32 * 32);
+        for x in 0..32_usize {
+            for y in 0..32_usize {
+                assert_eq!(map.get(&(x, y)), Some(&(x * 32 + y)));
+            }
+        }
+    }
+
+    #[test]
+    fn struct_keys_hash_all_fields() {
+        let build_hasher = StBuildHasher::from(ptr::addr_of!(NUM_HASH_TYPE));
+        let a = Point {
+            x: 1,
+            y: 2,
+            label: "a",
+        };
+        let b = Point {
+            x: 2,
+            y: 1,
+            label: "a",
+        };
+        let c = Point {
+            x: 1,
+            y: 2,
+            label: "c",
+        };
+        assert_eq!(build_hasher.hash_one(a), build_hasher.hash_one(a));
+        assert_ne!(build_hasher.hash_one(a), build_hasher.hash_one(b));
+        assert_ne!(build_hasher.hash_one(a), build_hasher.hash_one(c));
+
+        let mut map = StHashMap::with_hasher(build_hasher);
+        let _ = map.insert(a, 1);
+        let _ = map.insert(b, 2);
+        let _ = map.insert(c, 3);
+        assert_eq!(map.get(&a), Some(&1));
+        assert_eq!(map.get(&b), Some(&2));
+        assert_eq!(map.get(&c), Some(&3));
+    }
+
+    #[test]
+    fn signed_keys_hash_like_unsigned_words() {
+        let build_hasher = StBuildHasher::from(ptr::addr_of!(NUM_HASH_TYPE));
+        let hash_with = |write: &dyn Fn(&mut StHasher)| {
+            let mut hasher = build_hasher.build_hasher();
+            write(&mut hasher);
+            hasher.finish()
+        };
+
+        assert_eq!(
+            hash_with(&|h| h.write_i8(-1)),
+            hash_with(&|h| h.write_u8(u8::MAX))
+        );
+        assert_eq!(
+            hash_with(&|h| h.write_i16(-2)),
+            hash_with(&|h| h.write_u16(u16::MAX - 1))
+        );
+        assert_eq!(
+            hash_with(&|h| h.write_i32(-3)),
+            hash_with(&|h| h.write_u32(u32::MAX - 2))
+        );
+        assert_eq!(
+            hash_with(&|h| h.write_i64(-4)),
+            hash_with(&|h| h.write_u64(u64::MAX - 3))
+        );
+        assert_eq!(
+            hash_with(&|h| h.write_isize(-5)),
+            hash_with(&|h| h.write_usize(usize::MAX - 4))
+        );
+        assert_eq!(
+            hash_with(&|h| h.write_i128(-6)),
+            hash_with(&|h| h.write_u128(u128::MAX - 5))
+        );
+        assert_ne!(
+            hash_with(&|h| h.write_u128(1)),
+            hash_with(&|h| h.write_u128(1 << 64))
+        );
+    }
+
+    #[test]
+    fn signed_keys_hash_without_collisions() {
+        let build_hasher = StBuildHasher::from(ptr::addr_of!(NUM_HASH_TYPE));
+        let keys = (-1000_i64..1000)
+            .map(|i| (i as i8, i as i16, i as i32, i, i128::from(i), i as isize))
+            .collect::<Vec<_>>();
+
+        let hashes = keys
+            .iter()
+            .map(|key| build_hasher.hash_one(key))
+            .collect::<HashSet<_>>();
+        assert_eq!(hashes.len(), keys.len());
+        assert!(keys
+            .iter()
+            .all(|key| build_hasher.hash_one(key) == build_hasher.hash_one(*key)));
+
+        let mut map = StHashMap::with_hasher(build_hasher);
+        for (value, &key) in keys.iter().enumerate() {
+            let _ = map.insert(key, value);
+        }
+        assert_eq!(map.len(), keys.len());
+        for (value, key) in keys.iter().enumerate() {
+            assert_eq!(map.get(key), Some(&value));
+        }
+    }
+}
@@ -128,3 +128,7 @@ mod ffi;
 mod hasher;
 mod primitives;
 mod st_table;
+
+#[cfg(feature = "sync")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sync")))]
+pub use st_table::sync;
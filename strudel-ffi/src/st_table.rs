@@ -1,29 +1,71 @@
+use core::cell::Cell;
 use core::hash::{Hash, Hasher};
+use std::os::raw::c_uchar;
 
 use strudel::StHashMap;
 
 use crate::bindings::{st_compare_func, st_hash_type};
-use crate::hasher::StBuildHasher;
-use crate::primitives::st_data_t;
+use crate::hasher::{keyed_hasher, KeyBuildHasher};
+use crate::primitives::{st_data_t, st_hash_t, st_index_t};
 
 pub mod ffi;
 pub mod foreign;
+#[cfg(feature = "sync")]
+pub mod sync;
 
 /// A wrapper around a raw `st_data_t` key that includes a vtable for equality
-/// comparisons.
+/// comparisons and the key's precomputed hash.
 #[derive(Debug, Clone)]
 pub struct Key {
     record: st_data_t,
     eq: st_compare_func,
+    // A `Cell` so `StTable::rehash` can recompute the hashes of stored keys in
+    // place without renumbering their insertion ranks.
+    hash: Cell<st_hash_t>,
 }
 
 impl Key {
+    /// Wrap `record` in a key which is compared and hashed with the given
+    /// `st_hash_type`.
+    ///
+    /// Use this to look up entries in a [`SyncTable`], passing the hash type
+    /// of the table.
+    ///
+    /// # Safety
+    ///
+    /// `hash_type` must be non-null and point to an `st_hash_type` with
+    /// `'static` lifetime whose `compare` and `hash` functions are valid for
+    /// `record`.
+    ///
+    /// [`SyncTable`]: crate::sync::SyncTable
+    #[cfg(feature = "sync")]
+    #[inline]
+    #[must_use]
+    pub unsafe fn with_hash_type(record: st_data_t, hash_type: *const st_hash_type) -> Self {
+        let st_hash_type { compare, hash } = *hash_type;
+        Self {
+            record,
+            eq: compare,
+            hash: Cell::new(hash(record).into()),
+        }
+    }
+
     /// Return a reference to the inner key record.
     #[inline]
     #[must_use]
     pub fn inner(&self) -> &st_data_t {
         &self.record
     }
+
+    /// Return the hash of this key.
+    ///
+    /// This is either the result of the table's `st_hash_type` hash function
+    /// or the hash supplied by the caller of a `*_with_hash` function.
+    #[inline]
+    #[must_use]
+    pub fn hash(&self) -> st_hash_t {
+        self.hash.get()
+    }
 }
 
 impl From<Key> for st_data_t {
@@ -59,21 +101,24 @@ impl Eq for Key {}
 impl Hash for Key {
     #[inline]
     fn hash<H: Hasher>(&self, state: &mut H) {
-        state.write_usize(self.record.into());
+        state.write_usize(self.hash.get().into());
     }
 }
 
 /// Type alias for an [`StHashMap`] that stores opaque pointers with a
-/// [`st_hash_type`] derived [`StBuildHasher`].
+/// [`st_hash_type`] derived [`KeyBuildHasher`].
 ///
 /// `ExternStHashMap` stores pointers to its keys and values. It owns hasher and
 /// comaparator functions given at construction time to implement [`Hash`] and
 /// [`Eq`] for these opaque keys. See [`StHashMap::with_hash_type`].
-pub type Table = StHashMap<Key, st_data_t, StBuildHasher>;
+pub type Table = StHashMap<Key, st_data_t, KeyBuildHasher>;
 
 #[derive(Debug, Clone)]
 pub struct StTable {
     pub(crate) inner: Table,
+    rebuilds: usize,
+    observed_capacity: usize,
+    observed_map_rebuilds: usize,
 }
 
 impl StTable {
@@ -82,29 +127,98 @@ impl StTable {
     ///
     /// The created map has the default initial capacity.
     ///
-    /// A [`Hasher`] is constructed from an [`StBuildHasher`].
+    /// A [`Hasher`] is constructed from a [`KeyBuildHasher`].
     #[inline]
     #[must_use]
     #[allow(dead_code)]
     pub fn with_hash_type(hash_type: *const st_hash_type) -> Self {
-        let hasher = StBuildHasher::from(hash_type);
+        let hasher = KeyBuildHasher::from(hash_type);
         let map = Table::with_hasher(hasher);
-        Self { inner: map }
+        Self::from_table(map)
     }
 
-    /// Creates an empty `StHash` with the specified capacity which will use the
+    /// Creates an empty `StTable` with the specified capacity which will use the
     /// given `st_hash_type` to hash keys.
     ///
     /// The hash map will be able to hold at least `capacity` elements without
     /// reallocating. If `capacity` is 0, the hash map will not allocate.
     ///
-    /// A [`Hasher`] is constructed from an [`StBuildHasher`].
+    /// A [`Hasher`] is constructed from a [`KeyBuildHasher`].
     #[inline]
     #[must_use]
     pub fn with_capacity_and_hash_type(capacity: usize, hash_type: *const st_hash_type) -> Self {
-        let hasher = StBuildHasher::from(hash_type);
+        let hasher = KeyBuildHasher::from(hash_type);
         let map = Table::with_capacity_and_hasher(capacity, hasher);
-        Self { inner: map }
+        Self::from_table(map)
+    }
+
+    /// Creates an empty `StTable` with capacity for at least `size` elements
+    /// rounded up to the next power of two which will use the given
+    /// `st_hash_type` to hash keys.
+    ///
+    /// See [`StHashMap::with_pow2_capacity`].
+    ///
+    /// A [`Hasher`] is constructed from a [`KeyBuildHasher`].
+    #[inline]
+    #[must_use]
+    pub fn with_pow2_capacity_and_hash_type(size: usize, hash_type: *const st_hash_type) -> Self {
+        let hasher = KeyBuildHasher::from(hash_type);
+        let map = Table::with_pow2_capacity_and_hasher(size, hasher);
+        Self::from_table(map)
+    }
+
+    #[inline]
+    fn from_table(map: Table) -> Self {
+        Self {
+            observed_capacity: map.capacity(),
+            observed_map_rebuilds: map.rebuilds(),
+            inner: map,
+            rebuilds: 0,
+        }
+    }
+
+    /// Reinitialize this table in place as an empty table which will use the
+    /// given `st_hash_type` to hash keys.
+    ///
+    /// The reinitialized map can hold at least as many elements as this table
+    /// could hold without reallocating. The rebuild counter is reset.
+    #[inline]
+    pub fn reinit_with_hash_type(&mut self, hash_type: *const st_hash_type) {
+        let capacity = self.inner.capacity();
+        *self = Self::with_capacity_and_hash_type(capacity, hash_type);
+    }
+
+    /// Return how many times the table was rebuilt, counting changes to the
+    /// table's capacity and reclaims of dead slots since the last call.
+    ///
+    /// This counter backs the `rebuilds_num` field of the FFI `st_table`. It
+    /// is bumped at most once per call, so several rebuilds between two calls
+    /// are only counted once.
+    #[inline]
+    pub fn sync_rebuilds(&mut self) -> usize {
+        let capacity = self.inner.capacity();
+        let map_rebuilds = self.inner.rebuilds();
+        if capacity != self.observed_capacity || map_rebuilds != self.observed_map_rebuilds {
+            self.observed_capacity = capacity;
+            self.observed_map_rebuilds = map_rebuilds;
+            self.rebuilds = self.rebuilds.wrapping_add(1);
+        }
+        self.rebuilds
+    }
+
+    /// Return the base 2 logarithm of the capacity of the insertion list
+    /// rounded up to a power of two.
+    ///
+    /// This backs the `entry_power` field of the FFI `st_table`.
+    #[inline]
+    #[must_use]
+    pub fn entry_power(&self) -> c_uchar {
+        // The capacity of a `Vec` is at most `isize::MAX`, so rounding up to a
+        // power of two cannot overflow.
+        self.inner
+            .ordered_capacity()
+            .next_power_of_two()
+            .trailing_zeros() as c_uchar
     }
 
     #[inline]
@@ -113,6 +227,59 @@ impl StTable {
         self.inner.len()
     }
 
+    /// Hash a bare `st_data_t` with the table's `st_hash_type`.
+    ///
+    /// The result can be passed to the `*_with_hash_raw` wrappers to avoid
+    /// calling the `st_hash_type` hash function again for the same key.
+    #[inline]
+    #[must_use]
+    pub fn hash_raw(&self, record: st_data_t) -> st_hash_t {
+        let hash_type = self.inner.hasher().hash_type();
+        // Safety
+        //
+        // `StHashMap` assumes `hash_type` has `'static` lifetime.
+        // `StHashMap` assumes `hash` is a valid non-NULL function pointer.
+        let hash = unsafe { ((*hash_type).hash)(record) };
+        hash.into()
+    }
+
+    /// Hash a byte slice with the seeded FNV or `SipHash` hasher selected at
+    /// compile time.
+    ///
+    /// This is the hash computed by `st_hash`, so Rust callers can produce
+    /// values that agree with hashes computed over the C API.
+    #[inline]
+    #[must_use]
+    pub fn hash_bytes(bytes: &[u8], seed: st_index_t) -> st_index_t {
+        let mut hasher = keyed_hasher(seed.into());
+        hasher.write(bytes);
+        hasher.finish().into()
+    }
+
+    /// Wrap a bare `st_data_t` in a key type that can be checked for equality,
+    /// hashing it with the table's `st_hash_type`.
+    #[inline]
+    fn key(&self, record: st_data_t) -> Key {
+        self.key_with_hash(record, self.hash_raw(record))
+    }
+
+    /// Wrap a bare `st_data_t` in a key type that can be checked for equality
+    /// using a precomputed hash.
+    #[inline]
+    fn key_with_hash(&self, record: st_data_t, hash: st_hash_t) -> Key {
+        let hash_type = self.inner.hasher().hash_type();
+        // Safety
+        //
+        // `StHashMap` assumes `hash_type` has `'static` lifetime.
+        // `StHashMap` assumes `cmp` is a valid non-NULL function pointer.
+        let eq = unsafe { (*hash_type).compare };
+        Key {
+            record,
+            eq,
+            hash: Cell::new(hash),
+        }
+    }
+
     /// Wrapper around [`StHashMap::first`] that wraps a bare `st_data_t` in a
     /// key type that can be checked for equality.
     #[inline]
@@ -126,15 +293,19 @@ impl StTable {
     /// type that can be checked for equality.
     #[inline]
     #[must_use]
-    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn get_raw(&self, key: st_data_t) -> Option<&st_data_t> {
-        let hash_type = self.inner.hasher().hash_type();
-        // Safety
-        //
-        // `StHashMap` assumes `hash_type` has `'static` lifetime.
-        // `StHashMap` assumes `cmp` is a valid non-NULL function pointer.
-        let eq = unsafe { (*hash_type).compare };
-        let key = Key { record: key, eq };
+        let key = self.key(key);
+        self.inner.get(&key)
+    }
+
+    /// Wrapper around [`StHashMap::get`] that wraps a bare `st_data_t` in a key
+    /// type using the given precomputed `hash`.
+    ///
+    /// The `st_hash_type` hash function is not called.
+    #[inline]
+    #[must_use]
+    pub fn get_with_hash_raw(&self, key: st_data_t, hash: st_hash_t) -> Option<&st_data_t> {
+        let key = self.key_with_hash(key, hash);
         self.inner.get(&key)
     }
 
@@ -142,47 +313,87 @@ impl StTable {
     /// `st_data_t` in a key type that can be checked for equality.
     #[inline]
     #[must_use]
-    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn get_key_value_raw(&self, key: st_data_t) -> Option<(&st_data_t, &st_data_t)> {
-        let hash_type = self.inner.hasher().hash_type();
-        // Safety
-        //
-        // `StHashMap` assumes `hash_type` has `'static` lifetime.
-        // `StHashMap` assumes `cmp` is a valid non-NULL function pointer.
-        let eq = unsafe { (*hash_type).compare };
-        let key = Key { record: key, eq };
+        let key = self.key(key);
+        let (key, value) = self.inner.get_key_value(&key)?;
+        Some((&key.record, value))
+    }
+
+    /// Wrapper around [`StHashMap::get_key_value`] that wraps a bare
+    /// `st_data_t` in a key type using the given precomputed `hash`.
+    ///
+    /// The `st_hash_type` hash function is not called.
+    #[inline]
+    #[must_use]
+    pub fn get_key_value_with_hash_raw(
+        &self,
+        key: st_data_t,
+        hash: st_hash_t,
+    ) -> Option<(&st_data_t, &st_data_t)> {
+        let key = self.key_with_hash(key, hash);
         let (key, value) = self.inner.get_key_value(&key)?;
         Some((&key.record, value))
     }
 
+    /// Wrapper around [`StHashMap::get_index_of`] that wraps a bare `st_data_t`
+    /// in a key type that can be checked for equality.
+    ///
+    /// This is used to resume traversal after the table reclaims dead slots.
+    /// See [`StHashMap::rebuilds`].
+    #[inline]
+    #[must_use]
+    pub fn insert_rank_raw(&self, key: st_data_t) -> Option<usize> {
+        let key = self.key(key);
+        self.inner.get_index_of(&key)
+    }
+
     /// Wrapper around [`StHashMap::insert`] that wraps a bare `st_data_t` in a
     /// key type that can be checked for equality.
     #[inline]
     #[must_use]
-    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn insert_raw(&mut self, key: st_data_t, value: st_data_t) -> Option<st_data_t> {
-        let hash_type = self.inner.hasher().hash_type();
-        // Safety
-        //
-        // `StHashMap` assumes `hash_type` has `'static` lifetime.
-        // `StHashMap` assumes `cmp` is a valid non-NULL function pointer.
-        let eq = unsafe { (*hash_type).compare };
-        let key = Key { record: key, eq };
+        let key = self.key(key);
+        self.inner.insert(key, value)
+    }
+
+    /// Rebuild this table, recomputing the hash of every key with the table's
+    /// `st_hash_type`.
+    ///
+    /// Keys store their hash, so the stored hash of every live key is
+    /// recomputed first and then the table is rebuilt with
+    /// [`StHashMap::rehash`]. Hashes supplied to a `*_with_hash` function are
+    /// replaced. Insertion ranks are preserved, so a traversal in progress can
+    /// continue after a rehash.
+    #[inline]
+    pub fn rehash(&mut self) {
+        for key in self.inner.keys() {
+            key.hash.set(self.hash_raw(key.record));
+        }
+        self.inner.rehash();
+    }
+
+    /// Wrapper around [`StHashMap::insert`] that wraps a bare `st_data_t` in a
+    /// key type using the given precomputed `hash`.
+    ///
+    /// `hash` is stored with the key and is used whenever the table needs to
+    /// hash this entry again. The `st_hash_type` hash function is not called.
+    #[inline]
+    #[must_use]
+    pub fn insert_with_hash_raw(
+        &mut self,
+        key: st_data_t,
+        value: st_data_t,
+        hash: st_hash_t,
+    ) -> Option<st_data_t> {
+        let key = self.key_with_hash(key, hash);
         self.inner.insert(key, value)
     }
 
     /// Wrapper around [`StHashMap::update`] that wraps a bare `st_data_t` in a
     /// key type that can be checked for equality.
     #[inline]
-    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn update_raw(&mut self, key: st_data_t, value: st_data_t) {
-        let hash_type = self.inner.hasher().hash_type();
-        // Safety
-        //
-        // `StHashMap` assumes `hash_type` has `'static` lifetime.
-        // `StHashMap` assumes `cmp` is a valid non-NULL function pointer.
-        let eq = unsafe { (*hash_type).compare };
-        let key = Key { record: key, eq };
+        let key = self.key(key);
         self.inner.update(key, value);
     }
 
@@ -190,15 +401,19 @@ impl StTable {
     /// key type that can be checked for equality.
     #[inline]
     #[must_use]
-    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn remove_raw(&mut self, key: st_data_t) -> Option<st_data_t> {
-        let hash_type = self.inner.hasher().hash_type();
-        // Safety
-        //
-        // `StHashMap` assumes `hash_type` has `'static` lifetime.
-        // `StHashMap` assumes `cmp` is a valid non-NULL function pointer.
-        let eq = unsafe { (*hash_type).compare };
-        let key = Key { record: key, eq };
+        let key = self.key(key);
+        self.inner.remove(&key)
+    }
+
+    /// Wrapper around [`StHashMap::remove`] that wraps a bare `st_data_t` in a
+    /// key type using the given precomputed `hash`.
+    ///
+    /// The `st_hash_type` hash function is not called.
+    #[inline]
+    #[must_use]
+    pub fn remove_with_hash_raw(&mut self, key: st_data_t, hash: st_hash_t) -> Option<st_data_t> {
+        let key = self.key_with_hash(key, hash);
         self.inner.remove(&key)
     }
 
@@ -206,15 +421,8 @@ impl StTable {
     /// in a key type that can be checked for equality.
     #[inline]
     #[must_use]
-    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn remove_entry_raw(&mut self, key: st_data_t) -> Option<(st_data_t, st_data_t)> {
-        let hash_type = self.inner.hasher().hash_type();
-        // Safety
-        //
-        // `StHashMap` assumes `hash_type` has `'static` lifetime.
-        // `StHashMap` assumes `cmp` is a valid non-NULL function pointer.
-        let eq = unsafe { (*hash_type).compare };
-        let key = Key { record: key, eq };
+        let key = self.key(key);
         let (key, value) = self.inner.remove_entry(&key)?;
         Some((key.into(), value))
     }
@@ -1,6 +1,7 @@
 //! FFI helpers.
 
 use core::fmt;
+use std::os::raw::{c_uchar, c_uint};
 
 use super::foreign::{Foreign, Repack};
 use super::StTable;
@@ -9,29 +10,38 @@ use crate::primitives::st_index_t;
 
 // These values enforced by test.
 #[cfg(target_pointer_width = "64")]
-const PADDING_TO_NUM_ENTRIES: usize = 0;
-#[cfg(target_pointer_width = "64")]
-const PADDING_TO_END: usize = 32;
+pub const PADDING_TO_END: usize = 24;
 
 #[cfg(target_pointer_width = "32")]
-const PADDING_TO_NUM_ENTRIES: usize = 4;
-#[cfg(target_pointer_width = "32")]
-const PADDING_TO_END: usize = 16;
+pub const PADDING_TO_END: usize = 12;
 
 /// C struct wrapper around an [`StHashMap`].
 ///
 /// This wrapper is FFI compatible with the C definition for access to the
-/// `hash->type` and `hash->num_entries` struct fields.
+/// `hash->entry_power`, `hash->bin_power`, `hash->rebuilds_num`, `hash->type`
+/// and `hash->num_entries` struct fields.
+///
+/// `entry_power` is the base 2 logarithm of the capacity of the insertion list
+/// rounded up to a power of two. Like MRI, `bin_power` is one more than
+/// `entry_power`.
+/// The pointer to the underlying table is stored in the space of the `bins`
+/// field.
 ///
 /// This wrapper has the same `size_of` the C definition.
 ///
-/// [`StHashMap`]: crate::StHashMap
+/// [`StHashMap`]: strudel::StHashMap
+///
+/// cbindgen:field-names=[entry_power, bin_power, _size_ind, rebuilds_num, type, num_entries, table, _padding_end]
 #[repr(C)]
+#[allow(clippy::doc_markdown)]
 pub struct st_table {
-    pub(super) table: *mut StTable,
-    _padding: [u8; PADDING_TO_NUM_ENTRIES],
+    entry_power: c_uchar,
+    bin_power: c_uchar,
+    _size_ind: c_uchar,
+    rebuilds_num: c_uint,
     type_: *const st_hash_type,
     num_entries: st_index_t,
+    pub(super) table: *mut StTable,
     _padding_end: [u8; PADDING_TO_END],
 }
 
@@ -49,8 +59,8 @@ impl Repack for st_table {
 }
 
 impl st_table {
-    /// Sync the `num_entries` field on the FFI wrapper with the underlying
-    /// table.
+    /// Sync the `num_entries` and `rebuilds_num` fields on the FFI wrapper
+    /// with the underlying table.
     ///
     /// This method should be called after mutable operations to the underlying
     /// [`StHashMap`].
@@ -59,11 +69,30 @@ impl st_table {
     ///
     /// Callers must not invalidate other in-use pointers.
     ///
-    /// [`StHashMap`]: crate::StHashMap
+    /// [`StHashMap`]: strudel::StHashMap
     #[inline]
-    pub unsafe fn repack(&mut self) {
+    pub(crate) unsafe fn repack(&mut self) {
         let len = (*self.table).len();
         self.num_entries = len.into();
+        self.rebuilds_num = (*self.table).sync_rebuilds() as c_uint;
+        self.entry_power = (*self.table).entry_power();
+        self.bin_power = self.entry_power + 1;
+    }
+
+    /// Reinitialize the underlying table as an empty table which will use the
+    /// given `st_hash_type` and sync the FFI wrapper fields.
+    ///
+    /// Both the `st_table` wrapper and the underlying table allocation are
+    /// reused.
+    ///
+    /// # Safety
+    ///
+    /// Callers must not invalidate other in-use pointers.
+    #[inline]
+    pub(crate) unsafe fn reinit(&mut self, hash_type: *const st_hash_type) {
+        (*self.table).reinit_with_hash_type(hash_type);
+        self.type_ = hash_type;
+        self.repack();
     }
 
     /// Consumes the table, returning a wrapped raw pointer.
@@ -81,7 +110,7 @@ impl st_table {
     /// it as `st_table::into_raw(table)` instead of `table.into_raw()`.
     #[inline]
     #[must_use]
-    pub fn into_raw(table: Self) -> *mut Self {
+    pub(crate) fn into_raw(table: Self) -> *mut Self {
         let table = Box::new(table);
         Box::into_raw(table)
     }
@@ -101,7 +130,7 @@ impl st_table {
     /// it as `st_table::into_raw(table)` instead of `table.into_raw()`.
     #[inline]
     #[must_use]
-    pub fn boxed_into_raw(table: Box<Self>) -> *mut Self {
+    pub(crate) fn boxed_into_raw(table: Box<Self>) -> *mut Self {
         Box::into_raw(table)
     }
 
@@ -117,7 +146,7 @@ impl st_table {
     /// [`st_table::into_raw`] or [`st_table::boxed_into_raw`].
     #[inline]
     #[must_use]
-    pub unsafe fn from_raw(table: *mut Self) -> Foreign<Self> {
+    pub(crate) unsafe fn from_raw(table: *mut Self) -> Foreign<Self> {
         Foreign::new_from_raw(table)
     }
 }
@@ -137,28 +166,35 @@ impl fmt::Debug for st_table {
 
 impl From<StTable> for st_table {
     #[inline]
-    fn from(table: StTable) -> Self {
+    fn from(mut table: StTable) -> Self {
         let num_entries = st_index_t::from(table.inner.len());
+        let rebuilds_num = table.sync_rebuilds() as c_uint;
         let hash_type = table.inner.hasher().hash_type();
+        let entry_power = table.entry_power();
         let table = Box::new(table);
         let table = Box::into_raw(table);
         Self {
-            table,
-            _padding: [0; PADDING_TO_NUM_ENTRIES],
+            entry_power,
+            bin_power: entry_power + 1,
+            _size_ind: 0,
+            rebuilds_num,
             type_: hash_type,
             num_entries,
+            table,
             _padding_end: [0; PADDING_TO_END],
         }
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use core::mem::size_of;
-    use std::os::raw::{c_uchar, c_uint};
+    use core::ptr;
+    use std::os::raw::{c_int, c_uchar, c_uint};
 
     use crate::bindings::st_hash_type;
     use crate::primitives::{st_data_t, st_hash_t, st_index_t};
+    use crate::st_table::StTable;
 
     /// `st_table` struct definition from C in `st.h`.
     ///
@@ -222,6 +258,27 @@ mod tests {
         assert_eq!(c_struct, rust_struct);
     }
 
+    #[test]
+    fn entry_power_offset_ffi_compat() {
+        let c_struct = memoffset::offset_of!(st_table, entry_power);
+        let rust_struct = memoffset::offset_of!(super::st_table, entry_power);
+        assert_eq!(c_struct, rust_struct);
+    }
+
+    #[test]
+    fn bin_power_offset_ffi_compat() {
+        let c_struct = memoffset::offset_of!(st_table, bin_power);
+        let rust_struct = memoffset::offset_of!(super::st_table, bin_power);
+        assert_eq!(c_struct, rust_struct);
+    }
+
+    #[test]
+    fn rebuilds_num_offset_ffi_compat() {
+        let c_struct = memoffset::offset_of!(st_table, rebuilds_num);
+        let rust_struct = memoffset::offset_of!(super::st_table, rebuilds_num);
+        assert_eq!(c_struct, rust_struct);
+    }
+
     #[test]
     fn type_offset_ffi_compat() {
         let c_struct = memoffset::offset_of!(st_table, type_);
@@ -235,4 +292,122 @@ mod tests {
         let rust_struct = size_of::<super::st_table>();
         assert_eq!(c_struct, rust_struct);
     }
+
+    unsafe extern "C" fn numcmp(x: st_data_t, y: st_data_t) -> c_int {
+        c_int::from(x != y)
+    }
+
+    unsafe extern "C" fn numhash(n: st_data_t) -> st_index_t {
+        n.into()
+    }
+
+    static NUM_HASH_TYPE: st_hash_type = st_hash_type {
+        compare: numcmp,
+        hash: numhash,
+    };
+
+    #[test]
+    fn resize_bumps_rebuilds_num() {
+        let table = StTable::with_capacity_and_hash_type(4, ptr::addr_of!(NUM_HASH_TYPE));
+        let table = super::st_table::into_raw(table.into());
+        let c_table = table.cast::<st_table>();
+        unsafe {
+            assert_eq!((*c_table).rebuilds_num, 0);
+            assert_eq!(usize::from((*c_table).num_entries), 0);
+
+            let mut foreign = super::st_table::from_raw(table);
+            let _ = (*foreign.as_inner_mut()).insert_raw(1_usize.into(), 10_usize.into());
+            drop(foreign);
+            assert_eq!((*c_table).rebuilds_num, 0);
+            assert_eq!(usize::from((*c_table).num_entries), 1);
+
+            let mut foreign = super::st_table::from_raw(table);
+            for key in 2..=64_usize {
+                let _ = (*foreign.as_inner_mut()).insert_raw(key.into(), key.into());
+            }
+            drop(foreign);
+            assert_eq!((*c_table).rebuilds_num, 1);
+            assert_eq!(usize::from((*c_table).num_entries), 64);
+
+            drop(Box::from_raw(table));
+        }
+    }
+
+    #[test]
+    fn pow2_size_sets_power_fields() {
+        let table = StTable::with_pow2_capacity_and_hash_type(5, ptr::addr_of!(NUM_HASH_TYPE));
+        let table = super::st_table::into_raw(table.into());
+        let c_table = table.cast::<st_table>();
+        unsafe {
+            assert_eq!((*c_table).entry_power, 3);
+            assert_eq!((*c_table).bin_power, 4);
+            assert_eq!(usize::from((*c_table).num_entries), 0);
+
+            let mut foreign = super::st_table::from_raw(table);
+            assert!((*foreign.as_inner_mut()).inner.capacity() >= 8);
+            for key in 0..9_usize {
+                let _ = (*foreign.as_inner_mut()).insert_raw(key.into(), key.into());
+            }
+            drop(foreign);
+            // Growing the insertion list past 8 slots updates the power fields.
+            assert!((*c_table).entry_power >= 4);
+            assert_eq!((*c_table).bin_power, (*c_table).entry_power + 1);
+
+            drop(Box::from_raw(table));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "capi")]
+    fn generated_header_field_order_ffi_compat() {
+        let header = include_str!(concat!(env!("OUT_DIR"), "/st.h"));
+        let start = header
+            .find("typedef struct st_table {")
+            .expect("st.h defines st_table");
+        let body = &header[start..];
+        let body = &body[body.find('{').unwrap() + 1..body.find('}').unwrap()];
+        let c_fields = body
+            .split(';')
+            .map(str::trim)
+            .filter(|decl| !decl.is_empty())
+            .map(|decl| {
+                let decl = decl.split('[').next().unwrap();
+                decl.rsplit(|c: char| c.is_whitespace() || c == '*')
+                    .next()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let mut rust_fields = [
+            (
+                "entry_power",
+                memoffset::offset_of!(super::st_table, entry_power),
+            ),
+            (
+                "bin_power",
+                memoffset::offset_of!(super::st_table, bin_power),
+            ),
+            (
+                "_size_ind",
+                memoffset::offset_of!(super::st_table, _size_ind),
+            ),
+            (
+                "rebuilds_num",
+                memoffset::offset_of!(super::st_table, rebuilds_num),
+            ),
+            ("type", memoffset::offset_of!(super::st_table, type_)),
+            (
+                "num_entries",
+                memoffset::offset_of!(super::st_table, num_entries),
+            ),
+            ("table", memoffset::offset_of!(super::st_table, table)),
+            (
+                "_padding_end",
+                memoffset::offset_of!(super::st_table, _padding_end),
+            ),
+        ];
+        rust_fields.sort_by_key(|&(_, offset)| offset);
+        let rust_fields = rust_fields.map(|(name, _)| name);
+        assert_eq!(c_fields, rust_fields);
+    }
 }
@@ -4,6 +4,7 @@ use core::mem::ManuallyDrop;
 
 use super::ffi::st_table;
 use super::StTable;
+use crate::bindings::st_hash_type;
 
 pub trait Repack {
     unsafe fn repack(&mut self);
@@ -13,6 +14,11 @@ pub trait Repack {
 ///
 /// This struct will repack metadata on drop but will not free the underlying
 /// table.
+///
+/// C callers read fields like `num_entries` directly, so every `st_*` function
+/// that mutates a table must drop its `Foreign` after the last mutation and
+/// before returning. Functions that hand control to a callback re-wrap the
+/// table afterward for this reason.
 #[derive(Debug, Clone)]
 pub struct Foreign<T>
 where
@@ -39,13 +45,18 @@ where
 
     /// Return the inner owned `st_table`.
     ///
+    /// The metadata of the table is not repacked.
+    ///
     /// # Safety
     ///
     /// Callers must ensure the table is not owned by foreign code so it is not
     /// prematurely dropped.
     #[must_use]
-    pub unsafe fn take(mut self) -> Box<T> {
-        ManuallyDrop::take(&mut self.inner)
+    pub unsafe fn take(self) -> Box<T> {
+        // Skip `Drop`, which would repack through the `Box` after it has been
+        // moved out of `self`.
+        let mut this = ManuallyDrop::new(self);
+        ManuallyDrop::take(&mut this.inner)
     }
 }
 
@@ -62,6 +73,19 @@ impl Foreign<st_table> {
     pub unsafe fn as_inner_mut(&mut self) -> *mut StTable {
         self.inner.as_mut().table
     }
+
+    /// Reinitialize the foreign-owned `st_table` in place. See
+    /// [`st_table::reinit`].
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure no pointers derived from [`as_inner_mut`] are in
+    /// use.
+    ///
+    /// [`as_inner_mut`]: Self::as_inner_mut
+    pub unsafe fn reinit(&mut self, hash_type: *const st_hash_type) {
+        self.inner.as_mut().reinit(hash_type);
+    }
 }
 
 impl<T> Drop for Foreign<T>
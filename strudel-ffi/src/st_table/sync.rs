@@ -0,0 +1,167 @@
+//! Thread-safe ownership of `st_table`s.
+//!
+//! An `st_table` is a raw pointer owned by C and cannot cross threads. Once C
+//! code hands ownership of a table back to Rust, [`into_sync`] converts it into
+//! a [`SyncTable`], which is [`Send`] and [`Sync`] and guards the table with a
+//! lock. [`from_sync`] converts it back into an `st_table` for C code once the
+//! threads are done with it.
+//!
+//! Keys are compared with the table's `st_hash_type` `compare` function on
+//! whichever thread holds the lock, so this function must be thread-safe. Use
+//! [`Key::with_hash_type`] to build keys for lookups.
+
+use core::mem;
+
+use strudel::SyncStHashMap;
+
+pub use super::ffi::st_table;
+pub use super::Key;
+use super::{StTable, Table};
+pub use crate::bindings::st_hash_type;
+use crate::hasher::KeyBuildHasher;
+pub use crate::primitives::st_data_t;
+
+/// Type alias for a thread-safe handle to the entries of an `st_table`.
+pub type SyncTable = SyncStHashMap<Key, st_data_t, KeyBuildHasher>;
+
+/// Consume an `st_table` and move its entries into a thread-safe
+/// [`SyncTable`].
+///
+/// Entries keep their insertion order and precomputed hashes. The `st_table`
+/// is freed.
+///
+/// # Safety
+///
+/// The `table` pointer must be non-null and returned by one of the
+/// `st_init_table` functions or [`from_sync`]. Foreign code must not use the
+/// pointer after this call.
+#[must_use]
+pub unsafe fn into_sync(table: *mut st_table) -> SyncTable {
+    let table = st_table::from_raw(table).take();
+    // Safety:
+    //
+    // `st_table` owns its `StTable` and nothing else references it once
+    // foreign code has given up the `st_table`.
+    let inner = &mut (*table.table).inner;
+    let hasher = inner.hasher().clone();
+    let map = mem::replace(inner, Table::with_hasher(hasher));
+    drop(table);
+    SyncTable::from(map)
+}
+
+/// Consume a [`SyncTable`] and return a new raw `st_table` which owns its
+/// entries.
+///
+/// Entries keep their insertion order and precomputed hashes. The rebuild
+/// counter of the returned table starts from zero.
+///
+/// After calling this function, the caller is responsible for the allocated
+/// memory. Free it with `st_free_table` or pass it back to [`into_sync`].
+#[must_use]
+pub fn from_sync(table: SyncTable) -> *mut st_table {
+    let table = StTable::from_table(table.into_inner());
+    st_table::into_raw(table.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::ptr;
+    use std::os::raw::c_int;
+    use std::sync::Arc;
+    use std::thread;
+    use std::vec::Vec;
+
+    use super::{from_sync, into_sync, st_table};
+    use crate::bindings::st_hash_type;
+    use crate::primitives::{st_data_t, st_index_t};
+    use crate::st_table::{Key, StTable};
+
+    unsafe extern "C" fn numcmp(x: st_data_t, y: st_data_t) -> c_int {
+        c_int::from(x != y)
+    }
+
+    unsafe extern "C" fn numhash(n: st_data_t) -> st_index_t {
+        n.into()
+    }
+
+    static NUM_HASH_TYPE: st_hash_type = st_hash_type {
+        compare: numcmp,
+        hash: numhash,
+    };
+
+    fn numkey(record: usize) -> Key {
+        let record = st_data_t::from(record);
+        Key {
+            record,
+            eq: numcmp,
+            hash: Cell::new(usize::from(record).into()),
+        }
+    }
+
+    #[test]
+    fn sync_table_round_trips_across_threads() {
+        let mut table = StTable::with_hash_type(ptr::addr_of!(NUM_HASH_TYPE));
+        let _ = table.inner.insert(numkey(0), 100.into());
+        let table = st_table::into_raw(table.into());
+
+        let sync = Arc::new(unsafe { into_sync(table) });
+        let handles = (1..=4_usize)
+            .map(|thread| {
+                let sync = Arc::clone(&sync);
+                thread::spawn(move || {
+                    for i in 0..250 {
+                        let key = thread * 1000 + i;
+                        assert_eq!(sync.insert(numkey(key), key.into()), None);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(sync.get(&numkey(0)), Some(100.into()));
+
+        let sync = Arc::try_unwrap(sync).unwrap();
+        let table = from_sync(sync);
+        let table = unsafe { Box::from_raw(table) };
+        let inner = unsafe { &(*table.table).inner };
+        assert_eq!(inner.len(), 1001);
+        assert_eq!(inner.first().map(|(key, _)| *key.inner()), Some(0.into()));
+        for thread in 1..=4_usize {
+            let keys = inner
+                .keys()
+                .map(|key| usize::from(*key.inner()))
+                .filter(|key| key / 1000 == thread)
+                .collect::<Vec<_>>();
+            assert_eq!(
+                keys,
+                (0..250).map(|i| thread * 1000 + i).collect::<Vec<_>>()
+            );
+            for key in keys {
+                assert_eq!(inner.get(&numkey(key)), Some(&key.into()));
+            }
+        }
+    }
+
+    #[test]
+    fn key_with_hash_type_finds_entries_of_sync_table() {
+        let mut table = StTable::with_hash_type(ptr::addr_of!(NUM_HASH_TYPE));
+        let _ = table.inner.insert(numkey(7), 700.into());
+        let _ = table.inner.insert(numkey(8), 800.into());
+        let table = st_table::into_raw(table.into());
+
+        let sync = unsafe { into_sync(table) };
+        let hash_type = sync.lock().hasher().hash_type();
+        let key = |record: usize| unsafe { Key::with_hash_type(record.into(), hash_type) };
+        assert_eq!(key(8).hash(), numkey(8).hash());
+        assert_eq!(sync.get(&key(8)), Some(800.into()));
+        assert_eq!(sync.remove(&key(7)), Some(700.into()));
+        assert_eq!(sync.get(&key(7)), None);
+
+        let table = unsafe { Box::from_raw(from_sync(sync)) };
+        let inner = unsafe { &(*table.table).inner };
+        assert_eq!(inner.len(), 1);
+        assert_eq!(inner.get(&key(8)), Some(&800.into()));
+    }
+}
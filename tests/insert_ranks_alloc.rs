@@ -0,0 +1,83 @@
+//! Check that walking a map by insertion rank does not allocate.
+//!
+//! `st_foreach` in `strudel-ffi` re-creates the [`InsertRanks`] iterator after
+//! every callback so the table may be mutated mid-iteration. These re-scans
+//! must stay allocation free for large tables.
+//!
+//! [`InsertRanks`]: strudel::st_hash_map::InsertRanks
+
+#![cfg(feature = "std")]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use strudel::StHashMap;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+struct CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn rescanning_insert_ranks_does_not_allocate() {
+    let mut map = StHashMap::new();
+    for i in 0..100_000_usize {
+        let _ = map.insert(i, i);
+    }
+    for i in (0..100_000_usize).step_by(3) {
+        let _ = map.remove(&i);
+    }
+
+    let before = allocations();
+    let mut visited = 0;
+    let mut sum = 0;
+    let mut ranks = map.insert_ranks_from(0);
+    while let Some(rank) = ranks.next() {
+        let key = match map.get_nth(rank) {
+            Some((&key, _)) => key,
+            None => continue,
+        };
+        visited += 1;
+        sum += key;
+        // Mutate the map between steps the way `st_foreach` callbacks may, then
+        // resume from the next rank.
+        if key % 2 == 0 {
+            let _ = map.remove(&key);
+        } else {
+            map.update(key, key + 1);
+        }
+        ranks = map.insert_ranks_from(rank + 1);
+    }
+    assert_eq!(allocations(), before);
+
+    assert_eq!(visited, 66_666);
+    assert_eq!(sum, (0..100_000_usize).filter(|i| i % 3 != 0).sum());
+    assert_eq!(map.len(), 33_333);
+    assert!(map
+        .iter()
+        .all(|(key, value)| key % 2 == 1 && *value == key + 1));
+}
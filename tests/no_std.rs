@@ -0,0 +1,78 @@
+//! Exercise `StHashMap` and `StHashSet` from a `no_std` crate with a
+//! caller-supplied hasher.
+//!
+//! Run with `cargo test --no-default-features` to build `strudel` without
+//! `std`.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::hash::{BuildHasherDefault, Hasher};
+
+use strudel::{StHashMap, StHashSet};
+
+// A minimal FNV-1a hasher so these tests do not depend on a `std` hasher.
+#[derive(Debug, Clone, Copy)]
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+type BuildFnv1a = BuildHasherDefault<Fnv1a>;
+
+#[test]
+fn map_with_hasher() {
+    let mut map = StHashMap::with_hasher(BuildFnv1a::default());
+    assert!(map.insert("c", 3).is_none());
+    assert!(map.insert("a", 1).is_none());
+    assert!(map.insert("b", 2).is_none());
+    assert_eq!(map.insert("a", 10), Some(1));
+    assert_eq!(map.remove(&"c"), Some(3));
+
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get(&"a"), Some(&10));
+    assert_eq!(map.get(&"c"), None);
+    assert_eq!(map.keys().copied().collect::<Vec<_>>(), ["a", "b"]);
+}
+
+#[test]
+fn map_with_capacity_and_hasher() {
+    let mut map = StHashMap::with_capacity_and_hasher(32, BuildFnv1a::default());
+    assert!(map.capacity() >= 32);
+    for i in 0..32 {
+        assert!(map.insert(i, i * 2).is_none());
+    }
+    assert_eq!(map.len(), 32);
+    assert_eq!(map.get(&31), Some(&62));
+    assert_eq!(map.first(), Some((&0, &0)));
+    assert_eq!(map.last(), Some((&31, &62)));
+}
+
+#[test]
+fn set_with_hasher() {
+    let mut set = StHashSet::with_hasher(BuildFnv1a::default());
+    // Like `st_insert`, `insert` returns whether the element was present.
+    assert!(!set.insert(3));
+    assert!(!set.insert(1));
+    assert!(set.insert(3));
+    assert!(set.contains(&1));
+    assert_eq!(set.iter().copied().collect::<Vec<_>>(), [3, 1]);
+}